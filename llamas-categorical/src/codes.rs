@@ -0,0 +1,180 @@
+//! Storage for the per-row codes of a CategoricalVec.
+//!
+//! Codes are stored in the narrowest unsigned int that can hold
+//! the largest code, so a column with a handful of categories costs
+//! one byte per row instead of eight. Storage widens as categories
+//! are added; it never narrows (removals are rare).
+
+use rayon::prelude::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Codes {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+macro_rules! each_width {
+    ($codes:expr, $v:ident => $body:expr) => {
+        match $codes {
+            Codes::U8($v) => $body,
+            Codes::U16($v) => $body,
+            Codes::U32($v) => $body,
+        }
+    };
+}
+
+impl Codes {
+    pub fn new() -> Self {
+        Codes::U8(Vec::new())
+    }
+
+    /// Bytes used per row.
+    pub fn width(&self) -> usize {
+        match *self {
+            Codes::U8(_) => 1,
+            Codes::U16(_) => 2,
+            Codes::U32(_) => 4,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        each_width!(self, v => v.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<usize> {
+        each_width!(self, v => v.get(i).map(|&c| c as usize))
+    }
+
+    /// Widens storage if needed so that `code` fits.
+    pub fn reserve_code(&mut self, code: usize) {
+        let widened = match *self {
+            Codes::U8(ref v) if code > u8::MAX as usize => {
+                if code > u16::MAX as usize {
+                    Codes::U32(v.iter().map(|&c| c as u32).collect())
+                } else {
+                    Codes::U16(v.iter().map(|&c| c as u16).collect())
+                }
+            },
+            Codes::U16(ref v) if code > u16::MAX as usize => {
+                Codes::U32(v.iter().map(|&c| c as u32).collect())
+            },
+            Codes::U32(_) if code > u32::MAX as usize => {
+                panic!("CategoricalVec supports at most 2^32 categories");
+            },
+            _ => return,
+        };
+        *self = widened;
+    }
+
+    /// Reserves room for `additional` more rows at the current width.
+    pub fn reserve(&mut self, additional: usize) {
+        each_width!(self, v => v.reserve(additional))
+    }
+
+    pub fn insert(&mut self, i: usize, code: usize) {
+        self.reserve_code(code);
+        match *self {
+            Codes::U8(ref mut v) => v.insert(i, code as u8),
+            Codes::U16(ref mut v) => v.insert(i, code as u16),
+            Codes::U32(ref mut v) => v.insert(i, code as u32),
+        }
+    }
+
+    pub fn push(&mut self, code: usize) {
+        let len = self.len();
+        self.insert(len, code);
+    }
+
+    pub fn remove(&mut self, i: usize) -> usize {
+        each_width!(self, v => v.remove(i) as usize)
+    }
+
+    pub fn contains(&self, code: usize) -> bool {
+        self.iter().any(|c| c == code)
+    }
+
+    /// Shifts every code above `code` down by one, after the category
+    /// at `code` was dropped from the dictionary.
+    pub fn close_gap(&mut self, code: usize) {
+        each_width!(self, v => {
+            v.par_iter_mut().for_each(|c| if *c as usize > code { *c -= 1 })
+        })
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            codes: self,
+            i: 0,
+        }
+    }
+}
+
+impl Default for Codes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Extend<usize> for Codes {
+    fn extend<I>(&mut self, iter: I)
+        where I: IntoIterator<Item=usize>
+    {
+        for code in iter {
+            self.push(code);
+        }
+    }
+}
+
+pub struct Iter<'a> {
+    codes: &'a Codes,
+    i: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let res = self.codes.get(self.i);
+        self.i += 1;
+        res
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.codes.len().saturating_sub(self.i);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widen() {
+        let mut codes = Codes::new();
+        codes.push(0);
+        codes.push(255);
+        assert_eq!(codes.width(), 1);
+        codes.push(256);
+        assert_eq!(codes.width(), 2);
+        codes.insert(0, 70_000);
+        assert_eq!(codes.width(), 4);
+        assert_eq!(codes.iter().collect::<Vec<_>>(), vec![70_000, 0, 255, 256]);
+    }
+
+    #[test]
+    fn close_gap() {
+        let mut codes = Codes::new();
+        codes.extend(vec![0, 2, 1, 2]);
+        codes.remove(2);
+        codes.close_gap(1);
+        assert_eq!(codes.iter().collect::<Vec<_>>(), vec![0, 1, 1]);
+    }
+}
@@ -1,10 +1,9 @@
 //! CategoricalVec
 //!
-//! In llamas, provides backing for
+//! In llamas, provides backing for these datatype columns:
 //! - binary
 //! - categorical
 //! - string
-//! datatype columns.
 //!
 //! A CategoricalVec stores distinct values of
 //! bytestrings/strings in a monolithic array. Indexing
@@ -24,12 +23,19 @@
 
 extern crate rayon;
 
+mod codes;
+
+pub use codes::Codes;
 use rayon::prelude::*;
+use std::collections::HashMap;
+use std::iter::FromIterator;
 use std::ops::Index;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CategoricalVec {
-    indices: Vec<usize>,
+    // Per-row codes, stored as u8/u16/u32 depending on
+    // the number of categories.
+    indices: Codes,
     offsets: Vec<usize>,
     data: Vec<u8>,
 }
@@ -37,7 +43,7 @@ pub struct CategoricalVec {
 impl CategoricalVec {
     pub fn new() -> Self {
         CategoricalVec {
-            indices: Vec::new(),
+            indices: Codes::new(),
             offsets: vec![0],
             data: Vec::new(),
         }
@@ -95,23 +101,11 @@ impl CategoricalVec {
     }
 
     pub fn contains(&self, bytes: &[u8]) -> bool {
-        match self.offset_position(bytes) {
-            Some(_) => true,
-            _ => false,
-        }
+        self.offset_position(bytes).is_some()
     }
 
     pub fn get(&self, i: usize) -> Option<&[u8]> {
-        if i < self.indices.len() {
-            let offset_ptr = self.indices[i];
-            let offset_range = self.offsets[offset_ptr]..self.offsets[offset_ptr + 1];
-
-            // unwrap here because we put in correct utf8,
-            // this must also output correct utf8
-            Some(&self.data[offset_range])
-        } else {
-            None
-        }
+        self.indices.get(i).and_then(|offset_ptr| self.category(offset_ptr))
     }
 
     /// Should panic if out of bounds, just like Vec::remove()
@@ -124,9 +118,7 @@ impl CategoricalVec {
         // In this vein, it's fine to just compact the
         // data vec immediately to prevent floating
         // data.
-        let offset_ptr = self.indices[index];
-
-        self.indices.remove(index);
+        let offset_ptr = self.indices.remove(index);
 
         let offset_start = self.offsets[offset_ptr];
         let offset_end = self.offsets[offset_ptr + 1];
@@ -134,7 +126,7 @@ impl CategoricalVec {
 
         // since there's no more references to that offset,
         // we should delete the data in self.data
-        if !self.indices.contains(&offset_ptr) {
+        if !self.indices.contains(offset_ptr) {
             let offset_len = offset_end - offset_start;
 
             let res_bytes = self.data.drain(offset_range);
@@ -150,9 +142,7 @@ impl CategoricalVec {
             // need to be moved one to the left
             // note that -= 1 is ok, because offset_ptr will always be > 0
             // in the below calculation
-            self.indices
-                .par_iter_mut()
-                .for_each(|p| if *p > offset_ptr { *p -= 1});
+            self.indices.close_gap(offset_ptr);
 
             res_bytes.collect::<Vec<u8>>()
 
@@ -163,6 +153,148 @@ impl CategoricalVec {
         }
     }
 
+    /// Code (the position in the dictionary of distinct values)
+    /// of the value at row `i`.
+    pub fn code(&self, i: usize) -> Option<usize> {
+        self.indices.get(i)
+    }
+
+    /// Value in the dictionary for `code`.
+    pub fn category(&self, code: usize) -> Option<&[u8]> {
+        if code + 1 < self.offsets.len() {
+            Some(&self.data[self.offsets[code]..self.offsets[code + 1]])
+        } else {
+            None
+        }
+    }
+
+    /// Number of distinct values.
+    pub fn n_categories(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Iterates over the distinct values, in code order.
+    pub fn categories(&self) -> Categories<'_> {
+        Categories {
+            vec: self,
+            code: 0,
+        }
+    }
+
+    /// Builds a dictionary holding the distinct values of both `a` and `b`,
+    /// with no rows.
+    ///
+    /// The codes of `a` keep their positions in the shared dictionary, so
+    /// `left` is always the identity; values only in `b` are added after.
+    /// Comparing row `i` of `a` to row `j` of `b` is then
+    /// `left[a.code(i)] == right[b.code(j)]`, with no bytes compared.
+    pub fn unify(a: &CategoricalVec, b: &CategoricalVec) -> Unified {
+        let mut categories = CategoricalVec {
+            indices: Codes::new(),
+            offsets: a.offsets.clone(),
+            data: a.data.clone(),
+        };
+        let left = (0..a.n_categories()).collect();
+
+        let mut lookup: HashMap<&[u8], usize> = a.categories()
+            .enumerate()
+            .map(|(code, bytes)| (bytes, code))
+            .collect();
+
+        let right = b.categories()
+            .map(|bytes| {
+                if let Some(&code) = lookup.get(bytes) {
+                    return code;
+                }
+                let code = categories.push_category(bytes);
+                lookup.insert(bytes, code);
+                code
+            })
+            .collect();
+
+        Unified {
+            categories,
+            left,
+            right,
+        }
+    }
+
+    /// All rows of `vecs`, in order, with one shared dictionary. Each
+    /// dictionary is hashed once and each row copied once, so this is
+    /// linear where appending the vecs pairwise with `unify` would
+    /// copy the rows so far at every step.
+    pub fn concat(vecs: &[&CategoricalVec]) -> CategoricalVec {
+        let mut res = CategoricalVec::new();
+        let mut lookup: HashMap<&[u8], usize> = HashMap::new();
+        let remaps: Vec<Vec<usize>> = vecs.iter()
+            .map(|vec| {
+                vec.categories()
+                    .map(|bytes| {
+                        if let Some(&code) = lookup.get(bytes) {
+                            return code;
+                        }
+                        let code = res.push_category(bytes);
+                        lookup.insert(bytes, code);
+                        code
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // widen once, then size for every row
+        res.indices.reserve_code(res.n_categories().saturating_sub(1));
+        res.indices.reserve(vecs.iter().map(|vec| vec.len()).sum());
+        for (vec, remap) in vecs.iter().zip(&remaps) {
+            res.append_remapped(vec, remap);
+        }
+        res
+    }
+
+    /// Applies `f` once per distinct value instead of once per row,
+    /// returning a vec with the same rows. Values which map to the same
+    /// bytes end up sharing a code. `f` is called in code order.
+    pub fn map_categories<F>(&self, mut f: F) -> CategoricalVec
+        where F: FnMut(&[u8]) -> Vec<u8>
+    {
+        let mut res = CategoricalVec::new();
+        let mut lookup: HashMap<Vec<u8>, usize> = HashMap::new();
+
+        let remap: Vec<usize> = self.categories()
+            .map(|bytes| {
+                let mapped = f(bytes);
+                if let Some(&code) = lookup.get(&mapped) {
+                    return code;
+                }
+                let code = res.push_category(&mapped);
+                lookup.insert(mapped, code);
+                code
+            })
+            .collect();
+
+        res.append_remapped(self, &remap);
+        res
+    }
+
+    /// Adds `bytes` to the dictionary without adding a row, and without
+    /// checking whether it's already there. Returns the new code.
+    fn push_category(&mut self, bytes: &[u8]) -> usize {
+        self.data.extend_from_slice(bytes);
+        self.offsets.push(self.data.len());
+        self.n_categories() - 1
+    }
+
+    /// Appends all rows of `other`, translating its codes through `remap`
+    /// (one of the tables from `unify`) instead of searching the dictionary.
+    ///
+    /// Panics if `remap` points past the dictionary of `self`.
+    pub fn append_remapped(&mut self, other: &CategoricalVec, remap: &[usize]) {
+        assert_eq!(remap.len(), other.n_categories());
+        assert!(remap.iter().all(|&code| code < self.n_categories()));
+
+        self.indices.reserve_code(self.n_categories().saturating_sub(1));
+        self.indices.extend(other.indices.iter().map(|code| remap[code]));
+    }
+
     pub fn is_empty(&self) -> bool {
         self.indices.is_empty()
     }
@@ -171,6 +303,12 @@ impl CategoricalVec {
         self.indices.len()
     }
 
+    /// Bytes used per row to store codes: 1, 2 or 4, widening
+    /// automatically as categories are added.
+    pub fn code_width(&self) -> usize {
+        self.indices.width()
+    }
+
     //pub fn split_off(&mut self, at: usize) -> Self {
     //}
 
@@ -182,6 +320,62 @@ impl CategoricalVec {
     //
 }
 
+impl Default for CategoricalVec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds the dictionary with a hash lookup, instead of the linear
+/// search done by `push`, so this is the way to go for bulk loads.
+impl<'a> FromIterator<&'a [u8]> for CategoricalVec {
+    fn from_iter<I>(iter: I) -> Self
+        where I: IntoIterator<Item=&'a [u8]>
+    {
+        let mut res = CategoricalVec::new();
+        let mut lookup: HashMap<&[u8], usize> = HashMap::new();
+        for bytes in iter {
+            let code = match lookup.get(bytes) {
+                Some(&code) => code,
+                None => {
+                    let code = res.push_category(bytes);
+                    lookup.insert(bytes, code);
+                    code
+                },
+            };
+            res.indices.push(code);
+        }
+        res
+    }
+}
+
+/// Result of `CategoricalVec::unify`.
+#[derive(Debug, Clone)]
+pub struct Unified {
+    /// Union of both dictionaries, with no rows.
+    pub categories: CategoricalVec,
+    /// Maps codes of the first vec to codes in `categories`.
+    pub left: Vec<usize>,
+    /// Maps codes of the second vec to codes in `categories`.
+    pub right: Vec<usize>,
+}
+
+/// Iterator over the distinct values of a `CategoricalVec`.
+pub struct Categories<'a> {
+    vec: &'a CategoricalVec,
+    code: usize,
+}
+
+impl<'a> Iterator for Categories<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let res = self.vec.category(self.code);
+        self.code += 1;
+        res
+    }
+}
+
 // don't implement Index.
 // Can only use Get
 // The problem is that [] dereferences
@@ -190,7 +384,7 @@ impl Index<usize> for CategoricalVec {
     type Output = [u8];
 
     fn index(&self, i: usize) -> &[u8] {
-        let ptr_to_offset = self.indices[i];
+        let ptr_to_offset = self.indices.get(i).expect("index out of bounds");
         let offset_range = self.offsets[ptr_to_offset]..self.offsets[ptr_to_offset + 1];
 
         // unwrap here because we put in correct utf8,
@@ -297,4 +491,119 @@ mod tests {
         assert!(sa.offsets.len() == 1);
         assert!(sa.data.is_empty());
     }
+
+    #[test]
+    fn concat() {
+        let a: CategoricalVec = vec![&b"one"[..], b"two", b"one"].into_iter().collect();
+        let b: CategoricalVec = vec![&b"three"[..], b"one"].into_iter().collect();
+        let empty = CategoricalVec::new();
+
+        let concat = CategoricalVec::concat(&[&a, &empty, &b]);
+        assert_eq!(concat.len(), 5);
+        assert_eq!(
+            (0..5).map(|i| concat.get(i).unwrap()).collect::<Vec<_>>(),
+            vec![&b"one"[..], b"two", b"one", b"three", b"one"]
+        );
+        assert_eq!(concat.n_categories(), 3);
+        assert!(CategoricalVec::concat(&[]).is_empty());
+    }
+
+    #[test]
+    fn unify_and_append() {
+        let mut a = CategoricalVec::new();
+        a.push(b"one");
+        a.push(b"two");
+        a.push(b"one");
+        let mut b = CategoricalVec::new();
+        b.push(b"three");
+        b.push(b"one");
+
+        let unified = CategoricalVec::unify(&a, &b);
+        assert_eq!(unified.left, vec![0, 1]);
+        assert_eq!(unified.right, vec![2, 0]);
+        assert_eq!(unified.categories.n_categories(), 3);
+        assert!(unified.categories.is_empty());
+
+        // same value compares equal through codes
+        assert_eq!(
+            unified.left[a.code(0).unwrap()],
+            unified.right[b.code(1).unwrap()]
+        );
+
+        let mut concat = unified.categories;
+        concat.append_remapped(&a, &unified.left);
+        concat.append_remapped(&b, &unified.right);
+        assert_eq!(concat.len(), 5);
+        assert_eq!(concat.n_categories(), 3);
+        assert_eq!(concat.get(0), Some(&b"one"[..]));
+        assert_eq!(concat.get(1), Some(&b"two"[..]));
+        assert_eq!(concat.get(2), Some(&b"one"[..]));
+        assert_eq!(concat.get(3), Some(&b"three"[..]));
+        assert_eq!(concat.get(4), Some(&b"one"[..]));
+        assert_eq!(
+            concat.categories().collect::<Vec<_>>(),
+            vec![&b"one"[..], &b"two"[..], &b"three"[..]]
+        );
+    }
+
+    #[test]
+    fn from_iter() {
+        let sa: CategoricalVec = vec![&b"one"[..], b"two", b"one"].into_iter().collect();
+        assert_eq!(sa.len(), 3);
+        assert_eq!(sa.n_categories(), 2);
+        assert_eq!(sa.get(2), Some(&b"one"[..]));
+        assert_eq!(sa.code(2), Some(0));
+    }
+
+    #[test]
+    fn map_categories() {
+        let mut sa = CategoricalVec::new();
+        sa.push(b"One");
+        sa.push(b"one");
+        sa.push(b"two");
+        sa.push(b"One");
+
+        let mut calls = 0;
+        let lower = sa.map_categories(|bytes| {
+            calls += 1;
+            bytes.to_ascii_lowercase()
+        });
+        // once per category, not per row
+        assert_eq!(calls, 3);
+        assert_eq!(lower.n_categories(), 2);
+        assert_eq!(lower.len(), 4);
+        assert_eq!(lower.get(0), Some(&b"one"[..]));
+        assert_eq!(lower.get(1), Some(&b"one"[..]));
+        assert_eq!(lower.get(2), Some(&b"two"[..]));
+        assert_eq!(lower.get(3), Some(&b"one"[..]));
+    }
+
+    #[test]
+    fn code_width() {
+        let mut sa = CategoricalVec::new();
+        sa.push(b"one");
+        sa.push(b"two");
+        assert_eq!(sa.code_width(), 1);
+
+        for i in 0..300 {
+            sa.push(i.to_string().as_bytes());
+        }
+        assert_eq!(sa.code_width(), 2);
+        assert_eq!(sa.n_categories(), 302);
+        assert_eq!(sa.get(0), Some(&b"one"[..]));
+        assert_eq!(&sa[1], &b"two"[..]);
+        assert_eq!(sa.get(301), Some(&b"299"[..]));
+        assert_eq!(sa.code(301), Some(301));
+    }
+
+    #[test]
+    #[should_panic]
+    fn append_remapped_panic() {
+        let mut a = CategoricalVec::new();
+        a.push(b"one");
+        let mut b = CategoricalVec::new();
+        b.push(b"two");
+        // b's code isn't in a's dictionary
+        a.append_remapped(&b, &[1]);
+    }
 }
@@ -0,0 +1,192 @@
+//! Binary dtype column
+//!
+//! Arbitrary bytestrings, backed by llamas categorical just like
+//! the string column, so the same low cardinality tradeoffs apply.
+//! A StringColumn is a BinaryColumn which is known to hold utf8;
+//! converting from binary to string validates each distinct value.
+
+use bit_vec::BitVec;
+use llamas_categorical::CategoricalVec;
+use std::any::Any;
+use std::convert::From;
+use std::ops::Index;
+
+use error::{Error, Result};
+use super::{Column, Dtype, Value};
+
+#[derive(Debug, Clone)]
+pub struct BinaryColumn {
+    values: CategoricalVec,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+}
+
+impl Column for BinaryColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::Binary
+    }
+
+    fn len(&self) -> usize {
+        BinaryColumn::len(self)
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        BinaryColumn::is_null(self, i)
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        if self.mask[i] { Value::Bytes(self[i].to_vec()) } else { Value::Null }
+    }
+
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push_null(),
+            Value::Bytes(ref bytes) => self.push(bytes),
+            Value::Str(ref s) => self.push(s.as_bytes()),
+            value => return Err(Error::TypeMismatch {
+                expected: Dtype::Binary.to_string(),
+                found: value.kind().to_owned(),
+            }),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(BinaryColumn::new())
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl BinaryColumn {
+    pub fn new() -> Self {
+        BinaryColumn {
+            values: CategoricalVec::new(),
+            mask: BitVec::new(),
+        }
+    }
+
+    pub(crate) fn from_parts(values: CategoricalVec, mask: BitVec) -> Self {
+        assert_eq!(values.len(), mask.len());
+        BinaryColumn {
+            values,
+            mask,
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> (CategoricalVec, BitVec) {
+        (self.values, self.mask)
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.values.push(bytes);
+        self.mask.push(true);
+    }
+
+    pub fn push_null(&mut self) {
+        self.values.push(b"");
+        self.mask.push(false);
+    }
+
+    /// Whether `bytes` is one of the values in the column. This only
+    /// searches the distinct values, not every row.
+    pub fn contains_value(&self, bytes: &[u8]) -> bool {
+        self.values.contains(bytes)
+    }
+
+    pub fn get(&self, i: usize) -> Option<&[u8]> {
+        self.values.get(i)
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        !self.mask.get(i).unwrap_or(true)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+}
+
+impl Default for BinaryColumn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Columns are equal if they have the same rows, regardless
+/// of how the dictionary is laid out.
+impl PartialEq for BinaryColumn {
+    fn eq(&self, other: &BinaryColumn) -> bool {
+        self.len() == other.len() &&
+            (0..self.len()).all(|i| {
+                match (self.is_null(i), other.is_null(i)) {
+                    (true, true) => true,
+                    (false, false) => self.get(i) == other.get(i),
+                    _ => false,
+                }
+            })
+    }
+}
+
+impl<'a> From<Vec<&'a [u8]>> for BinaryColumn {
+    fn from(v: Vec<&'a [u8]>) -> Self {
+        let mut col = BinaryColumn::new();
+        for bytes in v {
+            col.push(bytes);
+        }
+        col
+    }
+}
+
+impl<'a> From<Vec<Option<&'a [u8]>>> for BinaryColumn {
+    fn from(v: Vec<Option<&'a [u8]>>) -> Self {
+        let mut col = BinaryColumn::new();
+        for bytes in v {
+            match bytes {
+                Some(bytes) => col.push(bytes),
+                None => col.push_null(),
+            }
+        }
+        col
+    }
+}
+
+impl Index<usize> for BinaryColumn {
+    type Output = [u8];
+
+    fn index(&self, i: usize) -> &[u8] {
+        &self.values[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_get() {
+        let mut col = BinaryColumn::new();
+        col.push(&[0xff, 0x00]);
+        col.push_null();
+        col.push(b"abc");
+        col.push(&[0xff, 0x00]);
+        assert_eq!(col.len(), 4);
+        assert_eq!(col.get(0), Some(&[0xff, 0x00][..]));
+        assert!(col.is_null(1));
+        assert!(!col.is_null(2));
+        assert_eq!(&col[2], &b"abc"[..]);
+        assert_eq!(col.get(3), Some(&[0xff, 0x00][..]));
+        assert_eq!(col.get(4), None);
+        assert!(col.contains_value(b"abc"));
+    }
+}
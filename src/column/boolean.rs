@@ -0,0 +1,236 @@
+//! Boolean dtype column
+//!
+//! Values are packed into a bitvec, same as the null mask,
+//! so a boolean column costs two bits per row.
+
+use bit_vec::BitVec;
+use std::any::Any;
+use std::convert::From;
+use std::ops::{BitAnd, BitOr, Not};
+
+use error::{Error, Result};
+use super::ops::and_masks;
+use super::{Chunk, Column, DataType, DataTypeMut, Dtype, Series, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BooleanColumn {
+    values: BitVec,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+}
+
+impl Column for BooleanColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::Boolean
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        if self.mask[i] { Value::Bool(self.values[i]) } else { Value::Null }
+    }
+
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push(None),
+            Value::Bool(x) => self.push(Some(x)),
+            value => return Err(Error::TypeMismatch {
+                expected: Dtype::Boolean.to_string(),
+                found: value.kind().to_owned(),
+            }),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(BooleanColumn::new())
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl BooleanColumn {
+    pub fn new() -> Self {
+        BooleanColumn {
+            values: BitVec::new(),
+            mask: BitVec::new(),
+        }
+    }
+
+    /// Both bitvecs must be the same length.
+    pub(crate) fn from_parts(values: BitVec, mask: BitVec) -> Self {
+        assert_eq!(values.len(), mask.len());
+        BooleanColumn {
+            values,
+            mask,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl Default for BooleanColumn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chunk for BooleanColumn {
+    fn append(&mut self, other: &BooleanColumn) {
+        self.values.extend(other.values.iter());
+        self.mask.extend(other.mask.iter());
+    }
+}
+
+impl DataType for BooleanColumn {
+    type Item = bool;
+
+    fn get(&self, index: usize) -> Option<Option<&bool>> {
+        if let Some(mask) = self.mask.get(index) {
+            if !mask {
+                return Some(None);
+            }
+        } else {
+            return None;
+        }
+        // BitVec indexes into static bools, so there's
+        // something to reference.
+        Some(Some(&self.values[index]))
+    }
+
+    fn values(&self) -> Series<'_, Self::Item> {
+        Series::from_dyn(self, self.len())
+    }
+}
+
+impl DataTypeMut for BooleanColumn {
+    fn push(&mut self, item: Option<bool>) {
+        self.values.push(item.unwrap_or(false));
+        self.mask.push(item.is_some());
+    }
+
+    fn apply<F>(&mut self, f: F)
+        where F: Fn(bool) -> bool + ::std::marker::Sync
+    {
+        for i in 0..self.values.len() {
+            if self.mask[i] {
+                let x = f(self.values[i]);
+                self.values.set(i, x);
+            }
+        }
+    }
+}
+
+impl From<Vec<bool>> for BooleanColumn {
+    fn from(v: Vec<bool>) -> Self {
+        let length = v.len();
+        BooleanColumn {
+            values: v.into_iter().collect(),
+            mask: BitVec::from_elem(length, true),
+        }
+    }
+}
+
+impl From<Vec<Option<bool>>> for BooleanColumn {
+    fn from(v: Vec<Option<bool>>) -> Self {
+        BooleanColumn {
+            values: v.iter().map(|x| x.unwrap_or(false)).collect(),
+            mask: v.iter().map(|x| x.is_some()).collect(),
+        }
+    }
+}
+
+/// Logical ops propagate nulls like the arithmetic kernels: null
+/// wherever either side is null. Panics if the lengths differ.
+impl<'a> BitAnd<&'a BooleanColumn> for &'a BooleanColumn {
+    type Output = BooleanColumn;
+
+    fn bitand(self, other: &'a BooleanColumn) -> BooleanColumn {
+        let mask = and_masks(&self.mask, &other.mask);
+        let mut values = self.values.clone();
+        values.intersect(&other.values);
+        values.intersect(&mask);
+        BooleanColumn::from_parts(values, mask)
+    }
+}
+
+impl<'a> BitOr<&'a BooleanColumn> for &'a BooleanColumn {
+    type Output = BooleanColumn;
+
+    fn bitor(self, other: &'a BooleanColumn) -> BooleanColumn {
+        let mask = and_masks(&self.mask, &other.mask);
+        let mut values = self.values.clone();
+        values.union(&other.values);
+        values.intersect(&mask);
+        BooleanColumn::from_parts(values, mask)
+    }
+}
+
+impl Not for &BooleanColumn {
+    type Output = BooleanColumn;
+
+    fn not(self) -> BooleanColumn {
+        let mut values = self.values.clone();
+        values.negate();
+        values.intersect(&self.mask);
+        BooleanColumn::from_parts(values, self.mask.clone())
+    }
+}
+
+impl<'a> IntoIterator for &'a BooleanColumn {
+    type Item = Option<&'a bool>;
+    type IntoIter = Series<'a, bool>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Series::from_dyn(self, self.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_and_get() {
+        let col = BooleanColumn::from(vec![Some(true), None, Some(false)]);
+        assert_eq!(col.len(), 3);
+        assert_eq!(col.get(0), Some(Some(&true)));
+        assert_eq!(col.get(1), Some(None));
+        assert_eq!(col.get(2), Some(Some(&false)));
+        assert_eq!(col.get(3), None);
+    }
+
+    #[test]
+    fn apply_skips_nulls() {
+        let mut col = BooleanColumn::from(vec![Some(true), None, Some(false)]);
+        col.apply(|x| !x);
+        assert_eq!(col, BooleanColumn::from(vec![Some(false), None, Some(true)]));
+    }
+
+    #[test]
+    fn logical_ops() {
+        let a = BooleanColumn::from(vec![Some(true), Some(true), None, Some(false)]);
+        let b = BooleanColumn::from(vec![Some(true), Some(false), Some(true), Some(false)]);
+        assert_eq!(&a & &b, BooleanColumn::from(vec![Some(true), Some(false), None, Some(false)]));
+        assert_eq!(&a | &b, BooleanColumn::from(vec![Some(true), Some(true), None, Some(false)]));
+        assert_eq!(!&a, BooleanColumn::from(vec![Some(false), Some(false), None, Some(true)]));
+    }
+}
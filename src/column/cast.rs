@@ -0,0 +1,308 @@
+//! Casting columns between dtypes
+//!
+//! Casts go value by value through the dynamic interface: each value
+//! is converted to one the target column accepts, then pushed. A value
+//! fails to cast if it can't be converted (an unparsable string) or the
+//! target rejects it (an int too large for the target's width).
+//!
+//! Strict casts error on the first row which fails; lenient casts
+//! make it null instead. Either way, casting to a dtype which can't
+//! be built from scratch (lists, structs, intervals) is an error.
+//!
+//! String columns convert each distinct value once, like the other
+//! string ops.
+
+use std::convert::TryFrom;
+
+use error::{Error, Result};
+use super::datetime::{parse_date, parse_timestamp, SECONDS_PER_DAY};
+use super::decimal::parse_decimal;
+use super::{
+    BinaryColumn, BooleanColumn, Column, DateColumn, Decimal, DecimalColumn, Dtype,
+    Float32Column, Float64Column, Int8Column, Int16Column, Int32Column,
+    Int64Column, PeriodColumn, Rounding, StringColumn, TimedeltaColumn,
+    TimestampColumn, Value,
+};
+
+/// Casts `col` to `dtype`. Failing rows are an error if `strict`,
+/// otherwise null. Timestamps cast to another unit keep their time zone.
+pub(crate) fn cast(col: &dyn Column, dtype: Dtype, strict: bool) -> Result<Box<dyn Column>> {
+    if col.dtype() == dtype {
+        return Ok(col.clone_box());
+    }
+    // chunked columns come out as one plain column
+    if let Some(flat) = col.flatten() {
+        return cast(&*flat, dtype, strict);
+    }
+    let timestamps = col.as_any().downcast_ref::<TimestampColumn>();
+    let mut res = match (timestamps, dtype) {
+        (Some(timestamps), Dtype::Timestamp(unit)) => {
+            let res = TimestampColumn::new(unit);
+            Box::new(match timestamps.tz() {
+                Some(tz) => res.with_tz(tz),
+                None => res,
+            })
+        },
+        _ => empty_column(dtype).ok_or_else(|| Error::TypeMismatch {
+            expected: "a dtype which can be cast to".to_owned(),
+            found: dtype.to_string(),
+        })?,
+    };
+
+    // for strings, convert each distinct value once
+    let strings = col.as_any().downcast_ref::<StringColumn>();
+    let per_category = strings.map(|strings| {
+        strings.per_category(|s| cast_value(Value::Str(s.to_owned()), dtype))
+    });
+
+    for i in 0..col.len() {
+        let converted = match (strings, &per_category) {
+            (Some(strings), Some(per_category)) => match strings.row_code(i) {
+                Some(code) => per_category[code].clone(),
+                None => Some(Value::Null),
+            },
+            _ => match col.get_value(i) {
+                Value::Null => Some(Value::Null),
+                value => cast_value(value, dtype),
+            },
+        };
+        let ok = match converted {
+            Some(value) => res.push_value(value).is_ok(),
+            None => false,
+        };
+        if !ok {
+            if strict {
+                return Err(Error::Cast {
+                    row: i,
+                    value: col.get_value(i).to_string(),
+                    target: dtype.to_string(),
+                });
+            }
+            // any column takes a null
+            res.push_value(Value::Null).unwrap();
+        }
+    }
+    Ok(res)
+}
+
+/// Column of `dtype` with no rows, for the dtypes which don't need
+/// anything more (like a child column) to build.
+pub(crate) fn empty_column(dtype: Dtype) -> Option<Box<dyn Column>> {
+    let col: Box<dyn Column> = match dtype {
+        Dtype::Float32 => Box::new(Float32Column::new()),
+        Dtype::Float64 => Box::new(Float64Column::new()),
+        Dtype::Int8 => Box::new(Int8Column::new()),
+        Dtype::Int16 => Box::new(Int16Column::new()),
+        Dtype::Int32 => Box::new(Int32Column::new()),
+        Dtype::Int64 => Box::new(Int64Column::new()),
+        Dtype::Boolean => Box::new(BooleanColumn::new()),
+        Dtype::String => Box::new(StringColumn::new()),
+        Dtype::Binary => Box::new(BinaryColumn::new()),
+        Dtype::Timestamp(unit) => Box::new(TimestampColumn::new(unit)),
+        Dtype::Date => Box::new(DateColumn::new()),
+        Dtype::Timedelta(unit) => Box::new(TimedeltaColumn::new(unit)),
+        Dtype::Period(freq) => Box::new(PeriodColumn::new(freq).ok()?),
+        Dtype::Decimal { precision, scale } => Box::new(DecimalColumn::new(precision, scale)),
+        Dtype::Interval | Dtype::List | Dtype::Struct => return None,
+    };
+    Some(col)
+}
+
+/// Converts a non-null value to one which a column of `to` accepts
+/// (though it may still reject it, e.g. for being out of range).
+fn cast_value(value: Value, to: Dtype) -> Option<Value> {
+    let res = match to {
+        Dtype::String => match value {
+            Value::Bytes(bytes) => Value::Str(String::from_utf8(bytes).ok()?),
+            value => Value::Str(value.to_string()),
+        },
+        Dtype::Binary => match value {
+            Value::Bytes(bytes) => Value::Bytes(bytes),
+            value => Value::Bytes(value.to_string().into_bytes()),
+        },
+        Dtype::Int8 | Dtype::Int16 | Dtype::Int32 | Dtype::Int64 => Value::Int(to_int(value)?),
+        Dtype::Float32 | Dtype::Float64 => {
+            let x = match value {
+                Value::Bool(x) => if x { 1.0 } else { 0.0 },
+                Value::Str(ref s) => s.trim().parse().ok()?,
+                value => value.as_f64()?,
+            };
+            // finite values which overflow an f32 fail
+            if to == Dtype::Float32 && x.is_finite() && !(x as f32).is_finite() {
+                return None;
+            }
+            Value::Float(x)
+        },
+        Dtype::Boolean => match value {
+            Value::Bool(x) => Value::Bool(x),
+            Value::Int(x) => Value::Bool(x != 0),
+            Value::Float(x) => Value::Bool(x != 0.0),
+            Value::Str(ref s) => match s.trim().to_lowercase().as_str() {
+                "true" | "1" => Value::Bool(true),
+                "false" | "0" => Value::Bool(false),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        Dtype::Timestamp(unit) => match value {
+            Value::Timestamp(..) | Value::Int(_) => value,
+            Value::Date(days) => Value::Int((days as i64).checked_mul(unit.per_second() * SECONDS_PER_DAY)?),
+            Value::Str(ref s) => Value::Int(parse_timestamp(s, unit)?),
+            _ => return None,
+        },
+        Dtype::Date => match value {
+            Value::Date(_) => value,
+            Value::Int(x) => Value::Date(i32::try_from(x).ok()?),
+            Value::Timestamp(x, unit) => {
+                Value::Date(i32::try_from(x.div_euclid(unit.per_second() * SECONDS_PER_DAY)).ok()?)
+            },
+            Value::Str(ref s) => Value::Date(parse_date(s.trim())?),
+            _ => return None,
+        },
+        Dtype::Timedelta(unit) => match value {
+            Value::Int(_) => value,
+            Value::Timedelta(x, from) => Value::Int(from.convert(x, unit)?),
+            _ => return None,
+        },
+        Dtype::Period(freq) => match value {
+            Value::Int(_) | Value::Period(..) => value,
+            Value::Timestamp(x, unit) => {
+                Value::Int(freq.ordinal(x.div_euclid(unit.per_second() * SECONDS_PER_DAY)))
+            },
+            Value::Date(days) => Value::Int(freq.ordinal(days as i64)),
+            _ => return None,
+        },
+        Dtype::Decimal { scale, .. } => match value {
+            Value::Int(_) => value,
+            // decimals are rescaled, rounding if the scale shrinks
+            Value::Decimal(x, from) => {
+                Value::Decimal(Decimal::new(x, from).rescale(scale, Rounding::HalfEven)?.value, scale)
+            },
+            Value::Float(x) if x.is_finite() => {
+                Value::Decimal(parse_decimal(&x.to_string(), scale, Rounding::HalfEven)?, scale)
+            },
+            Value::Str(ref s) => Value::Decimal(parse_decimal(s, scale, Rounding::HalfEven)?, scale),
+            _ => return None,
+        },
+        Dtype::Interval | Dtype::List | Dtype::Struct => return None,
+    };
+    Some(res)
+}
+
+/// Ints, floats (truncated), decimals (truncated), bools, int strings,
+/// and the ints underlying time values.
+fn to_int(value: Value) -> Option<i64> {
+    match value {
+        Value::Int(x) => Some(x),
+        Value::Float(x) => {
+            // 2^63, the first float past i64::MAX
+            let limit = 9_223_372_036_854_775_808.0;
+            if x.is_finite() && x >= -limit && x < limit { Some(x.trunc() as i64) } else { None }
+        },
+        Value::Decimal(x, scale) => {
+            let x = x / 10i128.checked_pow(scale as u32)?;
+            if x >= i64::MIN as i128 && x <= i64::MAX as i128 { Some(x as i64) } else { None }
+        },
+        Value::Bool(x) => Some(x as i64),
+        Value::Str(ref s) => s.trim().parse().ok(),
+        Value::Timestamp(x, _) | Value::Timedelta(x, _) | Value::Period(x, _) => Some(x),
+        Value::Date(x) => Some(x as i64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::Unit;
+
+    #[test]
+    fn ints_and_floats() {
+        let ints = Int8Column::from(vec![Some(1), None, Some(-100)]);
+        let wide = ints.cast(Dtype::Int64).unwrap();
+        assert_eq!(
+            wide.as_any().downcast_ref::<Int64Column>().unwrap(),
+            &Int64Column::from(vec![Some(1), None, Some(-100)])
+        );
+        let floats = wide.cast(Dtype::Float32).unwrap();
+        assert_eq!(
+            floats.as_any().downcast_ref::<Float32Column>().unwrap(),
+            &Float32Column::from(vec![Some(1.0), None, Some(-100.0)])
+        );
+
+        let big = Int64Column::from(vec![Some(1), Some(1000), None]);
+        match big.cast(Dtype::Int8) {
+            Err(Error::Cast { row: 1, ref value, .. }) if value == "1000" => (),
+            res => panic!("{:?}", res),
+        }
+        let lenient = big.cast_lenient(Dtype::Int8).unwrap();
+        assert_eq!(
+            lenient.as_any().downcast_ref::<Int8Column>().unwrap(),
+            &Int8Column::from(vec![Some(1), None, None])
+        );
+
+        let floats = Float64Column::from(vec![2.9, -2.9, f64::NAN]);
+        assert!(floats.cast(Dtype::Int32).is_err());
+        let truncated = floats.cast_lenient(Dtype::Int32).unwrap();
+        assert_eq!(
+            truncated.as_any().downcast_ref::<Int32Column>().unwrap(),
+            &Int32Column::from(vec![Some(2), Some(-2), None])
+        );
+    }
+
+    #[test]
+    fn to_and_from_strings() {
+        let ints = Int16Column::from(vec![Some(7), None]);
+        let strings = ints.cast(Dtype::String).unwrap();
+        assert_eq!(
+            strings.as_any().downcast_ref::<StringColumn>().unwrap(),
+            &StringColumn::from(vec![Some("7"), None])
+        );
+
+        let strings = StringColumn::from(vec![Some(" 12 "), Some("x"), None, Some("12")]);
+        match strings.cast(Dtype::Int64) {
+            Err(Error::Cast { row: 1, .. }) => (),
+            res => panic!("{:?}", res),
+        }
+        assert_eq!(
+            strings.cast_lenient(Dtype::Float64).unwrap().as_any().downcast_ref::<Float64Column>().unwrap(),
+            &Float64Column::from(vec![Some(12.0), None, None, Some(12.0)])
+        );
+
+        let bools = StringColumn::from(vec!["true", "FALSE", "1"]).cast(Dtype::Boolean).unwrap();
+        assert_eq!(
+            bools.as_any().downcast_ref::<BooleanColumn>().unwrap(),
+            &BooleanColumn::from(vec![true, false, true])
+        );
+
+        let ts = StringColumn::from(vec!["2017-06-01T12:00:00", "2017-06-02"])
+            .cast(Dtype::Timestamp(Unit::Seconds))
+            .unwrap();
+        assert_eq!(ts.get_value(0), Value::Timestamp(1496318400, Unit::Seconds));
+        let dates = ts.cast(Dtype::Date).unwrap();
+        assert_eq!(dates.get_value(1).to_string(), "2017-06-02");
+
+        let far = Int64Column::from(vec![1i64 << 40]);
+        assert!(far.cast(Dtype::Date).is_err());
+        let far = TimestampColumn::from_values(vec![Some(i64::MAX)], Unit::Seconds);
+        assert!(far.cast(Dtype::Date).is_err());
+
+        let zoned = TimestampColumn::from_values(vec![Some(1)], Unit::Seconds).with_tz("Europe/Paris");
+        let millis = zoned.cast(Dtype::Timestamp(Unit::Milliseconds)).unwrap();
+        let millis = millis.as_any().downcast_ref::<TimestampColumn>().unwrap();
+        assert_eq!(millis.tz(), Some("Europe/Paris"));
+        assert_eq!(millis.get_value(0), Value::Timestamp(1000, Unit::Milliseconds));
+
+        let decimals = StringColumn::from(vec!["1.005"]).cast(Dtype::Decimal { precision: 5, scale: 2 }).unwrap();
+        assert_eq!(decimals.get_value(0), Value::Decimal(100, 2));
+    }
+
+    #[test]
+    fn unsupported() {
+        let ints = Int8Column::from(vec![1]);
+        assert!(ints.cast(Dtype::List).is_err());
+        assert!(ints.cast_lenient(Dtype::Struct).is_err());
+        let boxed: Box<dyn Column> = Box::new(ints);
+        assert!(boxed.cast(Dtype::Int8).is_ok());
+    }
+}
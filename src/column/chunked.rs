@@ -0,0 +1,325 @@
+//! Chunked columns
+//!
+//! A plain column is one contiguous Vec and mask, so appending another
+//! column copies it, and growing a long one reallocates. A chunked
+//! column is a list of columns (the chunks) read as one: stacking
+//! another chunked column onto it moves the other's chunks over without
+//! copying any rows, and pushing values fills the last chunk up to
+//! `CHUNK_SIZE` rows before starting a new one.
+//!
+//! Kernels can work a chunk at a time through `chunks`, and the
+//! reductions here run across chunks in parallel with rayon. `rechunk`
+//! copies everything into one chunk, for when contiguous data is
+//! worth the copy.
+
+use rayon::prelude::*;
+use std::any::Any;
+use std::slice;
+
+use error::Result;
+use super::{Column, Dtype, Numeric, Primitive, Value};
+
+/// Rows per chunk when pushing values.
+pub const CHUNK_SIZE: usize = 1 << 16;
+
+/// Columns which can be chunks of a ChunkedColumn.
+pub trait Chunk: Column + Clone + Default {
+    /// Appends the rows of `other`.
+    fn append(&mut self, other: &Self);
+
+    /// All rows of `chunks` in one column. By default, appends each
+    /// to a copy of the first.
+    fn concat(chunks: &[Self]) -> Self {
+        let mut chunks = chunks.iter();
+        let mut res = chunks.next().cloned().unwrap_or_default();
+        for chunk in chunks {
+            res.append(chunk);
+        }
+        res
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkedColumn<C> {
+    chunks: Vec<C>,
+    // row where each chunk starts; no chunk is empty, so these
+    // increase strictly
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl<C: Chunk> ChunkedColumn<C> {
+    pub fn new() -> Self {
+        ChunkedColumn {
+            chunks: Vec::new(),
+            starts: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn from_chunks(chunks: Vec<C>) -> Self {
+        let mut res = ChunkedColumn::new();
+        for chunk in chunks {
+            res.append_chunk(chunk);
+        }
+        res
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn n_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn chunks(&self) -> slice::Iter<'_, C> {
+        self.chunks.iter()
+    }
+
+    /// Adds `chunk` at the end, without copying its rows.
+    pub fn append_chunk(&mut self, chunk: C) {
+        if chunk.is_empty() {
+            return;
+        }
+        self.starts.push(self.len);
+        self.len += chunk.len();
+        self.chunks.push(chunk);
+    }
+
+    /// Moves the chunks of `other` to the end, without copying rows.
+    pub fn vstack(&mut self, other: ChunkedColumn<C>) {
+        for chunk in other.chunks {
+            self.append_chunk(chunk);
+        }
+    }
+
+    /// Copies every row into a single chunk.
+    pub fn rechunk(&mut self) {
+        if self.chunks.len() <= 1 {
+            return;
+        }
+        self.chunks = vec![C::concat(&self.chunks)];
+        self.starts = vec![0];
+    }
+
+    /// The chunk holding row `i`, and the row within it.
+    fn locate(&self, i: usize) -> (&C, usize) {
+        assert!(i < self.len, "index {} out of bounds for length {}", i, self.len);
+        let chunk = match self.starts.binary_search(&i) {
+            Ok(chunk) => chunk,
+            Err(next) => next - 1,
+        };
+        (&self.chunks[chunk], i - self.starts[chunk])
+    }
+}
+
+impl<C: Chunk + Sync> ChunkedColumn<C> {
+    /// Applies `f` to each chunk, in parallel.
+    pub fn par_map_chunks<F, R>(&self, f: F) -> Vec<R>
+        where F: Fn(&C) -> R + Sync,
+              R: Send
+    {
+        self.chunks.par_iter().map(f).collect()
+    }
+}
+
+// Reductions, combining the per-chunk results of `Numeric`.
+impl<C> ChunkedColumn<C>
+    where C: Chunk + Numeric + Sync,
+          C::Item: Primitive
+{
+    pub fn sum(&self) -> <C::Item as Primitive>::Sum {
+        self.par_map_chunks(|chunk| chunk.sum())
+            .into_iter()
+            .fold(Default::default(), |a, b| a + b)
+    }
+
+    pub fn count(&self) -> usize {
+        self.par_map_chunks(|chunk| chunk.count()).into_iter().sum()
+    }
+
+    pub fn null_count(&self) -> usize {
+        self.len - self.count()
+    }
+
+    pub fn min(&self) -> Option<C::Item> {
+        self.par_map_chunks(|chunk| chunk.min())
+            .into_iter()
+            .flatten()
+            .fold(None, |acc, x| match acc {
+                Some(acc) if acc <= x => Some(acc),
+                _ => Some(x),
+            })
+    }
+
+    pub fn max(&self) -> Option<C::Item> {
+        self.par_map_chunks(|chunk| chunk.max())
+            .into_iter()
+            .flatten()
+            .fold(None, |acc, x| match acc {
+                Some(acc) if acc >= x => Some(acc),
+                _ => Some(x),
+            })
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        let (sum, count) = self.par_map_chunks(|chunk| {
+                (chunk.mean().map_or(0.0, |mean| mean * chunk.count() as f64), chunk.count())
+            })
+            .into_iter()
+            .fold((0.0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+        if count == 0 { None } else { Some(sum / count as f64) }
+    }
+}
+
+impl<C: Chunk> Default for ChunkedColumn<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Chunk + 'static> Column for ChunkedColumn<C> {
+    fn dtype(&self) -> Dtype {
+        match self.chunks.first() {
+            Some(chunk) => chunk.dtype(),
+            None => C::default().dtype(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        let (chunk, i) = self.locate(i);
+        chunk.is_null(i)
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        let (chunk, i) = self.locate(i);
+        chunk.get_value(i)
+    }
+
+    /// Pushes onto the last chunk, or a new one if it's full.
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match self.chunks.last_mut() {
+            Some(chunk) if chunk.len() < CHUNK_SIZE => chunk.push_value(value)?,
+            _ => {
+                let mut chunk = C::default();
+                chunk.push_value(value)?;
+                self.starts.push(self.len);
+                self.chunks.push(chunk);
+            },
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(ChunkedColumn::<C>::new())
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn flatten(&self) -> Option<Box<dyn Column>> {
+        Some(Box::new(C::concat(&self.chunks)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Int8Column, Float64Column, StringColumn};
+
+    #[test]
+    fn stack_and_rechunk() {
+        let mut a = ChunkedColumn::from_chunks(vec![
+            Int8Column::from(vec![Some(1), None]),
+            Int8Column::new(),
+            Int8Column::from(vec![3]),
+        ]);
+        let b = ChunkedColumn::from_chunks(vec![Int8Column::from(vec![100, 100])]);
+        a.vstack(b);
+        assert_eq!(a.n_chunks(), 3);
+        assert_eq!(a.len(), 5);
+        assert_eq!(a.get_value(1), Value::Null);
+        assert_eq!(a.get_value(2), Value::Int(3));
+        assert_eq!(a.get_value(4), Value::Int(100));
+        assert_eq!(a.dtype(), Dtype::Int8);
+
+        assert_eq!(a.sum(), 204);
+        assert_eq!(a.count(), 4);
+        assert_eq!(a.null_count(), 1);
+        assert_eq!(a.min(), Some(1));
+        assert_eq!(a.max(), Some(100));
+        assert_eq!(a.mean(), Some(51.0));
+
+        a.rechunk();
+        assert_eq!(a.n_chunks(), 1);
+        assert_eq!(
+            a.chunks().next().unwrap(),
+            &Int8Column::from(vec![Some(1), None, Some(3), Some(100), Some(100)])
+        );
+        let taken = a.take(&[Some(4), None]);
+        assert_eq!(taken.get_value(0), Value::Int(100));
+        assert!(taken.is_null(1));
+    }
+
+    #[test]
+    fn push_fills_chunks() {
+        let mut col = ChunkedColumn::<Float64Column>::new();
+        assert_eq!(col.dtype(), Dtype::Float64);
+        assert_eq!(col.mean(), None);
+        for i in 0..CHUNK_SIZE + 1 {
+            col.push_value(Value::Float(i as f64)).unwrap();
+        }
+        assert_eq!(col.n_chunks(), 2);
+        assert_eq!(col.get_value(CHUNK_SIZE), Value::Float(CHUNK_SIZE as f64));
+        assert!(col.push_value(Value::Str("x".to_owned())).is_err());
+        assert_eq!(col.len(), CHUNK_SIZE + 1);
+    }
+
+    #[test]
+    fn strings() {
+        let mut col = ChunkedColumn::from_chunks(vec![
+            StringColumn::from(vec![Some("a"), None]),
+            StringColumn::from(vec!["b", "a"]),
+        ]);
+        col.rechunk();
+        assert_eq!(
+            col.chunks().next().unwrap(),
+            &StringColumn::from(vec![Some("a"), None, Some("b"), Some("a")])
+        );
+        let lens = col.par_map_chunks(|chunk| chunk.len());
+        assert_eq!(lens, vec![4]);
+        assert_eq!(col.chunks().next().unwrap().n_unique(), 2);
+    }
+
+    #[test]
+    fn cast() {
+        let col = ChunkedColumn::from_chunks(vec![
+            StringColumn::from(vec![Some("1"), None]),
+            StringColumn::from(vec!["x", "1"]),
+        ]);
+        let ints = col.cast_lenient(Dtype::Int64).unwrap();
+        assert_eq!(
+            ints.as_any().downcast_ref::<::column::Int64Column>().unwrap(),
+            &::column::Int64Column::from(vec![Some(1), None, None, Some(1)])
+        );
+        match col.cast(Dtype::Int64) {
+            Err(::error::Error::Cast { row: 2, .. }) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+}
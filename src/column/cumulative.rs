@@ -0,0 +1,270 @@
+//! Cumulative and offset kernels
+//!
+//! Running reductions (`cumsum`, `cumprod`, `cummin`, `cummax`) and
+//! operations between a row and the one `n` rows before it (`shift`,
+//! `diff`, `pct_change`; negative `n` looks ahead). All keep the length
+//! of the input and leave its null rows null; the running reductions
+//! skip them, and rows offset past either end are null.
+//!
+//! Sums, products and differences are in the wide type of the column
+//! (Int64 for ints, Float64 for floats), with ints wrapping like the
+//! arithmetic operators. Like those, the kernels are a macro invoked
+//! from the int and float column macros. `Transform` names the kernels
+//! for the dynamic interface, as used by group-by.
+
+use error::{Error, Result};
+use super::{visit_numeric, Column, Numeric, NumericVisitor, Primitive};
+
+/// Row `n` rows before `i` (after it for negative `n`), if in bounds.
+pub(crate) fn offset(i: usize, n: isize, len: usize) -> Option<usize> {
+    let j = (i as isize).checked_sub(n)?;
+    if j >= 0 && (j as usize) < len { Some(j as usize) } else { None }
+}
+
+macro_rules! cumulative_ops {
+    ($name:ident, $t:ty, $wide:ident) => {
+        impl $name {
+            fn valid_at(&self, i: usize) -> Option<$t> {
+                if self.mask[i] { Some(self.values[i]) } else { None }
+            }
+
+            fn accumulate<F>(&self, init: <$t as ::column::Primitive>::Acc, f: F) -> ::column::$wide
+                where F: Fn(<$t as ::column::Primitive>::Acc, <$t as ::column::Primitive>::Acc)
+                    -> <$t as ::column::Primitive>::Acc
+            {
+                let mut acc = init;
+                self.values.iter()
+                    .zip(self.mask.iter())
+                    .map(|(&x, valid)| {
+                        if valid {
+                            acc = f(acc, ::column::Primitive::widen(x));
+                            Some(acc)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .into()
+            }
+
+            /// Running extreme, where `better(x, best)` if `x` replaces
+            /// the best so far. NaN rows stay NaN, and are skipped.
+            fn running<F>(&self, better: F) -> $name
+                where F: Fn($t, $t) -> bool
+            {
+                let mut best = None;
+                self.values.iter()
+                    .zip(self.mask.iter())
+                    .map(|(&x, valid)| {
+                        if !valid {
+                            return None;
+                        }
+                        if !::column::Primitive::is_comparable(x) {
+                            return Some(x);
+                        }
+                        best = match best {
+                            Some(best) if !better(x, best) => Some(best),
+                            _ => Some(x),
+                        };
+                        best
+                    })
+                    .collect::<Vec<_>>()
+                    .into()
+            }
+
+            /// Applies `f` to each row and the row `n` before it, null
+            /// unless both are valid.
+            fn with_offset<F, U>(&self, n: isize, f: F) -> Vec<Option<U>>
+                where F: Fn($t, $t) -> U
+            {
+                (0..self.values.len())
+                    .map(|i| {
+                        let before = ::column::cumulative::offset(i, n, self.values.len())?;
+                        Some(f(self.valid_at(i)?, self.valid_at(before)?))
+                    })
+                    .collect()
+            }
+
+            pub fn cumsum(&self) -> ::column::$wide {
+                self.accumulate(<$t as ::column::Primitive>::zero(), <$t as ::column::Primitive>::add)
+            }
+
+            pub fn cumprod(&self) -> ::column::$wide {
+                self.accumulate(<$t as ::column::Primitive>::one(), <$t as ::column::Primitive>::mul)
+            }
+
+            pub fn cummin(&self) -> $name {
+                self.running(|x, best| x < best)
+            }
+
+            pub fn cummax(&self) -> $name {
+                self.running(|x, best| x > best)
+            }
+
+            /// Each row moved `n` rows later (earlier for negative `n`),
+            /// with nulls in the rows left behind.
+            pub fn shift(&self, n: isize) -> $name {
+                (0..self.values.len())
+                    .map(|i| {
+                        let from = ::column::cumulative::offset(i, n, self.values.len())?;
+                        self.valid_at(from)
+                    })
+                    .collect::<Vec<_>>()
+                    .into()
+            }
+
+            /// Each row minus the row `n` before it.
+            pub fn diff(&self, n: isize) -> ::column::$wide {
+                self.with_offset(n, |x, before| {
+                    <$t as ::column::Primitive>::sub(
+                        ::column::Primitive::widen(x),
+                        ::column::Primitive::widen(before),
+                    )
+                }).into()
+            }
+
+            /// Fractional change from the row `n` before: 0.5 is 50% up.
+            /// Changes from 0 are infinite (or NaN from 0 to 0).
+            pub fn pct_change(&self, n: isize) -> ::column::Float64Column {
+                self.with_offset(n, |x, before| {
+                    ::column::Primitive::to_f64(x) / ::column::Primitive::to_f64(before) - 1.0
+                }).into()
+            }
+        }
+
+        impl ::column::Cumulative for $name {
+            fn apply(&self, transform: ::column::Transform) -> Box<dyn Column> {
+                match transform {
+                    ::column::Transform::CumSum => Box::new(self.cumsum()),
+                    ::column::Transform::CumProd => Box::new(self.cumprod()),
+                    ::column::Transform::CumMin => Box::new(self.cummin()),
+                    ::column::Transform::CumMax => Box::new(self.cummax()),
+                    ::column::Transform::Shift(n) => Box::new(self.shift(n)),
+                    ::column::Transform::Diff(n) => Box::new(self.diff(n)),
+                    ::column::Transform::PctChange(n) => Box::new(self.pct_change(n)),
+                }
+            }
+        }
+    };
+}
+
+/// A cumulative or offset kernel, for applying to dynamic columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transform {
+    CumSum,
+    CumProd,
+    CumMin,
+    CumMax,
+    /// Works on columns of any dtype.
+    Shift(isize),
+    Diff(isize),
+    PctChange(isize),
+}
+
+impl Transform {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Transform::CumSum => "cumsum",
+            Transform::CumProd => "cumprod",
+            Transform::CumMin => "cummin",
+            Transform::CumMax => "cummax",
+            Transform::Shift(_) => "shift",
+            Transform::Diff(_) => "diff",
+            Transform::PctChange(_) => "pct_change",
+        }
+    }
+}
+
+/// The `Transform` kernels of a numeric column type, boxed.
+pub(crate) trait Cumulative {
+    fn apply(&self, transform: Transform) -> Box<dyn Column>;
+}
+
+impl NumericVisitor for Transform {
+    type Output = Box<dyn Column>;
+
+    fn visit<C>(self, column: &C) -> Box<dyn Column>
+        where C: Numeric + Cumulative,
+              C::Item: Primitive
+    {
+        column.apply(self)
+    }
+}
+
+/// Applies `transform` to `column`, which must have a numeric dtype
+/// for all but `Shift`. Chunked columns come back as one chunk.
+pub(crate) fn transform(column: &dyn Column, transform: Transform) -> Result<Box<dyn Column>> {
+    // decimals aren't numeric here, so shifting them keeps their dtype
+    let res = if column.dtype().is_numeric() { visit_numeric(column, transform) } else { None };
+    match (res, transform) {
+        (Some(res), _) => Ok(res),
+        (None, Transform::Shift(n)) => {
+            let rows: Vec<_> = (0..column.len()).map(|i| offset(i, n, column.len())).collect();
+            Ok(column.take(&rows))
+        },
+        (None, _) => Err(Error::TypeMismatch {
+            expected: format!("numeric column for {}", transform.name()),
+            found: column.dtype().to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{
+        ChunkedColumn, Float32Column, Float64Column, Int8Column, Int64Column, StringColumn, Value,
+    };
+
+    #[test]
+    fn cumulative() {
+        let col = Int8Column::from(vec![Some(100), None, Some(100), Some(-5)]);
+        assert_eq!(col.cumsum(), Int64Column::from(vec![Some(100), None, Some(200), Some(195)]));
+        assert_eq!(col.cumprod(), Int64Column::from(vec![Some(100), None, Some(10_000), Some(-50_000)]));
+        assert_eq!(col.cummin(), Int8Column::from(vec![Some(100), None, Some(100), Some(-5)]));
+        assert_eq!(col.cummax(), Int8Column::from(vec![Some(100), None, Some(100), Some(100)]));
+
+        let floats = Float32Column::from(vec![Some(2.0), Some(f32::NAN), Some(1.0)]);
+        let mins = floats.cummin();
+        assert_eq!(mins.get_value(0), Value::Float(2.0));
+        assert!(mins.get_value(1).as_f64().unwrap().is_nan());
+        assert_eq!(mins.get_value(2), Value::Float(1.0));
+        assert_eq!(floats.cumsum().get_value(0), Value::Float(2.0));
+    }
+
+    #[test]
+    fn offsets() {
+        let col = Int8Column::from(vec![Some(1), Some(2), None, Some(8), Some(-128)]);
+        assert_eq!(col.shift(1), Int8Column::from(vec![None, Some(1), Some(2), None, Some(8)]));
+        assert_eq!(col.shift(-2), Int8Column::from(vec![None, Some(8), Some(-128), None, None]));
+        assert_eq!(col.shift(9), Int8Column::from(vec![None; 5]));
+        assert_eq!(col.shift(isize::MIN), Int8Column::from(vec![None; 5]));
+        assert_eq!(col.shift(isize::MAX), Int8Column::from(vec![None; 5]));
+        // widened, so 127 - -128 doesn't wrap
+        assert_eq!(
+            Int8Column::from(vec![-128, 127]).diff(-1),
+            Int64Column::from(vec![Some(-255), None])
+        );
+        assert_eq!(col.diff(1), Int64Column::from(vec![None, Some(1), None, None, Some(-136)]));
+        assert_eq!(
+            col.pct_change(1),
+            Float64Column::from(vec![None, Some(1.0), None, None, Some(-17.0)])
+        );
+    }
+
+    #[test]
+    fn dynamic() {
+        let col = Float64Column::from(vec![1.0, 3.0]);
+        let res = transform(&col, Transform::Diff(1)).unwrap();
+        assert_eq!(res.as_any().downcast_ref::<Float64Column>().unwrap(), &Float64Column::from(vec![None, Some(2.0)]));
+
+        let strings = StringColumn::from(vec!["a", "b"]);
+        let shifted = transform(&strings, Transform::Shift(1)).unwrap();
+        assert_eq!(shifted.as_any().downcast_ref::<StringColumn>().unwrap(), &StringColumn::from(vec![None, Some("a")]));
+        assert!(transform(&strings, Transform::CumSum).is_err());
+
+        let chunked = ChunkedColumn::from_chunks(vec![Int8Column::from(vec![1]), Int8Column::from(vec![2])]);
+        let res = transform(&chunked, Transform::CumSum).unwrap();
+        assert_eq!(res.as_any().downcast_ref::<Int64Column>().unwrap(), &Int64Column::from(vec![1, 3]));
+    }
+}
@@ -0,0 +1,1056 @@
+//! Timestamp, Date and Timedelta dtype columns
+//!
+//! Physically these are just ints with a null mask:
+//! - Timestamp: i64 count of `Unit`s since the unix epoch, in UTC.
+//!   The time zone is only a name carried along for the logical
+//!   type; values are always stored (and components extracted) as UTC.
+//! - Date: i32 count of days since the unix epoch.
+//! - Timedelta: i64 count of `Unit`s.
+//!
+//! Calendar math uses the proleptic gregorian calendar, via
+//! Howard Hinnant's days <-> civil date algorithms, so there's
+//! no need to pull in a datetime library.
+
+use bit_vec::BitVec;
+use std::any::Any;
+use std::ops::{Add, Sub};
+
+use error::{Error, Result};
+use super::{Column, DataType, DataTypeMut, Dtype, Int32Column, Series, StringColumn, Value};
+
+pub(crate) const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Resolution of a timestamp or timedelta. Ordered from
+/// coarsest to finest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Unit {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+impl Unit {
+    /// Number of units in one second.
+    pub fn per_second(self) -> i64 {
+        match self {
+            Unit::Seconds => 1,
+            Unit::Milliseconds => 1_000,
+            Unit::Microseconds => 1_000_000,
+            Unit::Nanoseconds => 1_000_000_000,
+        }
+    }
+
+    /// Converts `value` from `self` to `to`. Converting to a coarser unit
+    /// floors; converting to a finer one is `None` on overflow.
+    pub fn convert(self, value: i64, to: Unit) -> Option<i64> {
+        if to >= self {
+            value.checked_mul(to.per_second() / self.per_second())
+        } else {
+            Some(value.div_euclid(self.per_second() / to.per_second()))
+        }
+    }
+
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Unit::Seconds => "s",
+            Unit::Milliseconds => "ms",
+            Unit::Microseconds => "us",
+            Unit::Nanoseconds => "ns",
+        }
+    }
+
+    fn finest(self, other: Unit) -> Unit {
+        if self >= other { self } else { other }
+    }
+}
+
+// Implements the interface shared by all the time columns,
+// which only differ in their physical int type and extra metadata.
+macro_rules! time_column {
+    ($name:ident, $t:ty) => {
+        impl $name {
+            pub fn len(&self) -> usize {
+                self.values.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.values.is_empty()
+            }
+
+            pub fn is_null(&self, i: usize) -> bool {
+                !self.mask.get(i).unwrap_or(true)
+            }
+
+            fn valid(&self, i: usize) -> Option<$t> {
+                if self.mask[i] { Some(self.values[i]) } else { None }
+            }
+
+            fn type_mismatch(&self, value: Value) -> Error {
+                Error::TypeMismatch {
+                    expected: Column::dtype(self).to_string(),
+                    found: value.kind().to_owned(),
+                }
+            }
+        }
+
+        impl DataType for $name {
+            type Item = $t;
+
+            fn get(&self, index: usize) -> Option<Option<&$t>> {
+                if let Some(mask) = self.mask.get(index) {
+                    if !mask {
+                        return Some(None);
+                    }
+                } else {
+                    return None;
+                }
+                Some(self.values.get(index))
+            }
+
+            fn values(&self) -> Series<'_, Self::Item> {
+                Series::new(&self.values, &self.mask)
+            }
+        }
+
+        impl DataTypeMut for $name {
+            fn push(&mut self, item: Option<$t>) {
+                self.values.push(item.unwrap_or(0));
+                self.mask.push(item.is_some());
+            }
+
+            fn apply<F>(&mut self, f: F)
+                where F: Fn($t) -> $t + ::std::marker::Sync
+            {
+                let mask = &self.mask;
+                for (i, x) in self.values.iter_mut().enumerate() {
+                    if mask[i] {
+                        *x = f(*x);
+                    }
+                }
+            }
+        }
+
+        impl<'a> IntoIterator for &'a $name {
+            type Item = Option<&'a $t>;
+            type IntoIter = Series<'a, $t>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                Series::new(&self.values, &self.mask)
+            }
+        }
+    };
+}
+
+/// Instants in time, as `unit`s since the unix epoch (UTC).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampColumn {
+    values: Vec<i64>,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+    unit: Unit,
+    tz: Option<String>,
+}
+
+time_column!(TimestampColumn, i64);
+
+impl Column for TimestampColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::Timestamp(self.unit)
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        self.valid(i).map_or(Value::Null, |x| Value::Timestamp(x, self.unit))
+    }
+
+    /// Timestamps in other units are converted; ints are taken
+    /// to already be in the column's unit.
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push(None),
+            Value::Int(x) => self.push(Some(x)),
+            Value::Timestamp(x, unit) => match unit.convert(x, self.unit) {
+                Some(x) => self.push(Some(x)),
+                None => return Err(self.type_mismatch(Value::Timestamp(x, unit))),
+            },
+            value => return Err(self.type_mismatch(value)),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(TimestampColumn {
+            tz: self.tz.clone(),
+            ..TimestampColumn::new(self.unit)
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl TimestampColumn {
+    pub fn new(unit: Unit) -> Self {
+        TimestampColumn {
+            values: Vec::new(),
+            mask: BitVec::new(),
+            unit,
+            tz: None,
+        }
+    }
+
+    pub fn from_values(v: Vec<Option<i64>>, unit: Unit) -> Self {
+        TimestampColumn {
+            mask: v.iter().map(|x| x.is_some()).collect(),
+            values: v.into_iter().map(|x| x.unwrap_or(0)).collect(),
+            unit,
+            tz: None,
+        }
+    }
+
+    /// Parses ISO-8601 strings, like `2017-06-01`, `2017-06-01T12:30:00`
+    /// or `2017-06-01 12:30:00.250+02:00`. Values with an offset are
+    /// converted to UTC; values without one are taken to be UTC.
+    ///
+    /// Each distinct string is parsed once. Errors on the first row
+    /// which isn't a valid timestamp.
+    pub fn parse(col: &StringColumn, unit: Unit) -> Result<Self> {
+        let parsed = col.per_category(|s| parse_timestamp(s, unit));
+        let mut res = TimestampColumn::new(unit);
+        for i in 0..col.len() {
+            match col.row_code(i) {
+                Some(code) => match parsed[code] {
+                    Some(ts) => res.push(Some(ts)),
+                    None => return Err(Error::Parse {
+                        row: i,
+                        value: col[i].to_owned(),
+                        target: "timestamp",
+                    }),
+                },
+                None => res.push(None),
+            }
+        }
+        Ok(res)
+    }
+
+    /// Timestamps from `start` to `end` (both ISO-8601, both included)
+    /// spaced by a fixed `freq` like `1h` (see `parse_freq`).
+    pub fn date_range(start: &str, end: &str, freq: &str, unit: Unit) -> Result<Self> {
+        let parse = |s: &str, row| parse_timestamp(s, unit).ok_or_else(|| Error::Parse {
+            row,
+            value: s.to_owned(),
+            target: "timestamp",
+        });
+        let start = parse(start, 0)?;
+        let end = parse(end, 1)?;
+        let step = parse_freq(freq, unit)?;
+
+        let mut res = TimestampColumn::new(unit);
+        let mut ts = start;
+        while ts <= end {
+            res.push(Some(ts));
+            ts = match ts.checked_add(step) {
+                Some(ts) => ts,
+                None => break,
+            };
+        }
+        Ok(res)
+    }
+
+    /// Rounds each timestamp down to a multiple of a fixed `freq`,
+    /// counting from the epoch.
+    pub fn floor(&self, freq: &str) -> Result<Self> {
+        let step = parse_freq(freq, self.unit)?;
+        Ok(TimestampColumn {
+            tz: self.tz.clone(),
+            ..self.map_values(self.unit, |x| Some(x - x.rem_euclid(step)))
+        })
+    }
+
+    /// Attaches a time zone name. Values stay in UTC.
+    pub fn with_tz(mut self, tz: &str) -> Self {
+        self.tz = Some(tz.to_owned());
+        self
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    pub fn tz(&self) -> Option<&str> {
+        self.tz.as_deref()
+    }
+
+    /// Converts to another unit, flooring when going coarser. Values which
+    /// overflow when going finer become null.
+    pub fn to_unit(&self, unit: Unit) -> Self {
+        TimestampColumn {
+            tz: self.tz.clone(),
+            ..self.map_values(unit, |x| self.unit.convert(x, unit))
+        }
+    }
+
+    pub fn year(&self) -> Int32Column {
+        self.component(|days, _| civil_from_days(days).0)
+    }
+
+    pub fn month(&self) -> Int32Column {
+        self.component(|days, _| civil_from_days(days).1 as i32)
+    }
+
+    pub fn day(&self) -> Int32Column {
+        self.component(|days, _| civil_from_days(days).2 as i32)
+    }
+
+    pub fn hour(&self) -> Int32Column {
+        self.component(|_, secs| (secs / 3600) as i32)
+    }
+
+    pub fn minute(&self) -> Int32Column {
+        self.component(|_, secs| (secs % 3600 / 60) as i32)
+    }
+
+    pub fn second(&self) -> Int32Column {
+        self.component(|_, secs| (secs % 60) as i32)
+    }
+
+    /// Day of the week, with Monday as 0 and Sunday as 6.
+    pub fn weekday(&self) -> Int32Column {
+        self.component(|days, _| weekday(days))
+    }
+
+    /// Calendar date of each timestamp.
+    pub fn date(&self) -> DateColumn {
+        let per_day = self.unit.per_second() * SECONDS_PER_DAY;
+        DateColumn::from_values(
+            (0..self.len())
+                .map(|i| self.valid(i).map(|x| x.div_euclid(per_day) as i32))
+                .collect()
+        )
+    }
+
+    /// Splits each value into days since the epoch and seconds into that
+    /// day, then evaluates `f`.
+    fn component<F>(&self, f: F) -> Int32Column
+        where F: Fn(i64, i64) -> i32
+    {
+        let per_second = self.unit.per_second();
+        (0..self.len())
+            .map(|i| {
+                self.valid(i).map(|x| {
+                    let secs = x.div_euclid(per_second);
+                    f(secs.div_euclid(SECONDS_PER_DAY), secs.rem_euclid(SECONDS_PER_DAY))
+                })
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn map_values<F>(&self, unit: Unit, f: F) -> Self
+        where F: Fn(i64) -> Option<i64>
+    {
+        TimestampColumn::from_values(
+            (0..self.len()).map(|i| self.valid(i).and_then(&f)).collect(),
+            unit,
+        )
+    }
+}
+
+/// Calendar dates, as days since the unix epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateColumn {
+    values: Vec<i32>,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+}
+
+time_column!(DateColumn, i32);
+
+impl Column for DateColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::Date
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        self.valid(i).map_or(Value::Null, Value::Date)
+    }
+
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push(None),
+            Value::Date(x) => self.push(Some(x)),
+            value => return Err(self.type_mismatch(value)),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(DateColumn::new())
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl DateColumn {
+    pub fn new() -> Self {
+        DateColumn {
+            values: Vec::new(),
+            mask: BitVec::new(),
+        }
+    }
+
+    pub fn from_values(v: Vec<Option<i32>>) -> Self {
+        DateColumn {
+            mask: v.iter().map(|x| x.is_some()).collect(),
+            values: v.into_iter().map(|x| x.unwrap_or(0)).collect(),
+        }
+    }
+
+    /// Parses `YYYY-MM-DD` strings, once per distinct string. Errors on
+    /// the first row which isn't a valid date.
+    pub fn parse(col: &StringColumn) -> Result<Self> {
+        let parsed = col.per_category(parse_date);
+        let mut res = DateColumn::new();
+        for i in 0..col.len() {
+            match col.row_code(i) {
+                Some(code) => match parsed[code] {
+                    Some(days) => res.push(Some(days)),
+                    None => return Err(Error::Parse {
+                        row: i,
+                        value: col[i].to_owned(),
+                        target: "date",
+                    }),
+                },
+                None => res.push(None),
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn year(&self) -> Int32Column {
+        self.component(|days| civil_from_days(days).0)
+    }
+
+    pub fn month(&self) -> Int32Column {
+        self.component(|days| civil_from_days(days).1 as i32)
+    }
+
+    pub fn day(&self) -> Int32Column {
+        self.component(|days| civil_from_days(days).2 as i32)
+    }
+
+    /// Day of the week, with Monday as 0 and Sunday as 6.
+    pub fn weekday(&self) -> Int32Column {
+        self.component(weekday)
+    }
+
+    /// Midnight UTC of each date.
+    pub fn to_timestamp(&self, unit: Unit) -> TimestampColumn {
+        let per_day = unit.per_second() * SECONDS_PER_DAY;
+        TimestampColumn::from_values(
+            (0..self.len())
+                .map(|i| self.valid(i).and_then(|days| (days as i64).checked_mul(per_day)))
+                .collect(),
+            unit,
+        )
+    }
+
+    fn component<F>(&self, f: F) -> Int32Column
+        where F: Fn(i64) -> i32
+    {
+        (0..self.len())
+            .map(|i| self.valid(i).map(|days| f(days as i64)))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl Default for DateColumn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Durations, as a count of `unit`s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedeltaColumn {
+    values: Vec<i64>,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+    unit: Unit,
+}
+
+time_column!(TimedeltaColumn, i64);
+
+impl Column for TimedeltaColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::Timedelta(self.unit)
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        self.valid(i).map_or(Value::Null, |x| Value::Timedelta(x, self.unit))
+    }
+
+    /// Timedeltas in other units are converted; ints are taken
+    /// to already be in the column's unit.
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push(None),
+            Value::Int(x) => self.push(Some(x)),
+            Value::Timedelta(x, unit) => match unit.convert(x, self.unit) {
+                Some(x) => self.push(Some(x)),
+                None => return Err(self.type_mismatch(Value::Timedelta(x, unit))),
+            },
+            value => return Err(self.type_mismatch(value)),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(TimedeltaColumn::new(self.unit))
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl TimedeltaColumn {
+    pub fn new(unit: Unit) -> Self {
+        TimedeltaColumn {
+            values: Vec::new(),
+            mask: BitVec::new(),
+            unit,
+        }
+    }
+
+    pub fn from_values(v: Vec<Option<i64>>, unit: Unit) -> Self {
+        TimedeltaColumn {
+            mask: v.iter().map(|x| x.is_some()).collect(),
+            values: v.into_iter().map(|x| x.unwrap_or(0)).collect(),
+            unit,
+        }
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+}
+
+// Arithmetic between timestamps and timedeltas.
+//
+// Results are in the finer of the two units, so nothing is lost. A
+// null on either side, or overflow, gives a null.
+
+fn zip_values<F>(
+    len: usize,
+    left: &dyn Fn(usize) -> Option<i64>,
+    right: &dyn Fn(usize) -> Option<i64>,
+    f: F
+) -> Vec<Option<i64>>
+    where F: Fn(i64, i64) -> Option<i64>
+{
+    (0..len)
+        .map(|i| match (left(i), right(i)) {
+            (Some(a), Some(b)) => f(a, b),
+            _ => None,
+        })
+        .collect()
+}
+
+impl<'a> Add<&'a TimedeltaColumn> for &'a TimestampColumn {
+    type Output = TimestampColumn;
+
+    fn add(self, rhs: &'a TimedeltaColumn) -> TimestampColumn {
+        assert_eq!(self.len(), rhs.len());
+        let unit = self.unit.finest(rhs.unit);
+        let values = zip_values(
+            self.len(),
+            &|i| self.valid(i).and_then(|x| self.unit.convert(x, unit)),
+            &|i| rhs.valid(i).and_then(|x| rhs.unit.convert(x, unit)),
+            |a, b| a.checked_add(b),
+        );
+        TimestampColumn {
+            tz: self.tz.clone(),
+            ..TimestampColumn::from_values(values, unit)
+        }
+    }
+}
+
+impl<'a> Sub<&'a TimedeltaColumn> for &'a TimestampColumn {
+    type Output = TimestampColumn;
+
+    fn sub(self, rhs: &'a TimedeltaColumn) -> TimestampColumn {
+        assert_eq!(self.len(), rhs.len());
+        let unit = self.unit.finest(rhs.unit);
+        let values = zip_values(
+            self.len(),
+            &|i| self.valid(i).and_then(|x| self.unit.convert(x, unit)),
+            &|i| rhs.valid(i).and_then(|x| rhs.unit.convert(x, unit)),
+            |a, b| a.checked_sub(b),
+        );
+        TimestampColumn {
+            tz: self.tz.clone(),
+            ..TimestampColumn::from_values(values, unit)
+        }
+    }
+}
+
+/// Panics if the columns have different lengths or time zones (a
+/// column without one only matches another without one).
+impl<'a> Sub<&'a TimestampColumn> for &'a TimestampColumn {
+    type Output = TimedeltaColumn;
+
+    fn sub(self, rhs: &'a TimestampColumn) -> TimedeltaColumn {
+        assert_eq!(self.len(), rhs.len());
+        assert_eq!(self.tz, rhs.tz, "can't subtract timestamps in different time zones");
+        let unit = self.unit.finest(rhs.unit);
+        let values = zip_values(
+            self.len(),
+            &|i| self.valid(i).and_then(|x| self.unit.convert(x, unit)),
+            &|i| rhs.valid(i).and_then(|x| rhs.unit.convert(x, unit)),
+            |a, b| a.checked_sub(b),
+        );
+        TimedeltaColumn::from_values(values, unit)
+    }
+}
+
+impl<'a> Add<&'a TimedeltaColumn> for &'a TimedeltaColumn {
+    type Output = TimedeltaColumn;
+
+    fn add(self, rhs: &'a TimedeltaColumn) -> TimedeltaColumn {
+        assert_eq!(self.len(), rhs.len());
+        let unit = self.unit.finest(rhs.unit);
+        let values = zip_values(
+            self.len(),
+            &|i| self.valid(i).and_then(|x| self.unit.convert(x, unit)),
+            &|i| rhs.valid(i).and_then(|x| rhs.unit.convert(x, unit)),
+            |a, b| a.checked_add(b),
+        );
+        TimedeltaColumn::from_values(values, unit)
+    }
+}
+
+// Calendar helpers, shared with the other time-based dtypes.
+
+/// Days since the unix epoch of a proleptic gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// (year, month, day) of a count of days since the unix epoch.
+pub(crate) fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as i32, month, day)
+}
+
+pub(crate) fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ => {
+            let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+            if leap { 29 } else { 28 }
+        },
+    }
+}
+
+/// `YYYY-MM-DD`
+pub(crate) fn format_date(days: i32) -> String {
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `YYYY-MM-DDTHH:MM:SS`, with as many fractional digits as `unit` has.
+pub(crate) fn format_timestamp(value: i64, unit: Unit) -> String {
+    let per_second = unit.per_second();
+    let secs = value.div_euclid(per_second);
+    let frac = value.rem_euclid(per_second);
+    let days = secs.div_euclid(SECONDS_PER_DAY);
+    let secs = secs.rem_euclid(SECONDS_PER_DAY);
+    let mut res = format!(
+        "{}T{:02}:{:02}:{:02}",
+        format_date(days as i32),
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    );
+    if unit != Unit::Seconds {
+        let digits = (per_second as f64).log10() as usize;
+        res.push_str(&format!(".{:0width$}", frac, width = digits));
+    }
+    res
+}
+
+/// Monday is 0; the epoch was a Thursday.
+fn weekday(days: i64) -> i32 {
+    (days + 3).rem_euclid(7) as i32
+}
+
+/// Parses a fixed frequency, like `30s`, `15min`, `1h` or `1D`, into a
+/// count of `unit`s. The count defaults to 1, so `h` is `1h`. Accepted
+/// suffixes are `ns`, `us`, `ms`, `s`, `min`/`T`, `h`/`H`, `D`/`d` and
+/// `W`. Months and years aren't a fixed length, so aren't accepted.
+pub fn parse_freq(freq: &str, unit: Unit) -> Result<i64> {
+    let invalid = || Error::InvalidFreq(freq.to_owned());
+
+    let split = freq.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (count, suffix) = freq.split_at(split);
+    let count: i64 = if count.is_empty() { 1 } else { count.parse().map_err(|_| invalid())? };
+
+    let (secs, sub_unit) = match suffix {
+        "ns" => (0, Some(Unit::Nanoseconds)),
+        "us" => (0, Some(Unit::Microseconds)),
+        "ms" => (0, Some(Unit::Milliseconds)),
+        "s" => (1, None),
+        "min" | "T" => (60, None),
+        "h" | "H" => (3600, None),
+        "D" | "d" => (SECONDS_PER_DAY, None),
+        "W" => (7 * SECONDS_PER_DAY, None),
+        _ => return Err(invalid()),
+    };
+    let step = match sub_unit {
+        // can't express sub-unit steps, e.g. 1ms in seconds
+        Some(sub_unit) if sub_unit > unit => return Err(invalid()),
+        Some(sub_unit) => sub_unit.convert(count, unit),
+        None => count.checked_mul(secs).and_then(|secs| Unit::Seconds.convert(secs, unit)),
+    };
+    match step {
+        Some(step) if step > 0 => Ok(step),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date into days since the epoch.
+pub(crate) fn parse_date(s: &str) -> Option<i32> {
+    let mut cursor = Cursor::new(s);
+    let days = cursor.date()?;
+    if cursor.done() { Some(days as i32) } else { None }
+}
+
+/// Parses an ISO-8601 date or datetime into `unit`s since the epoch (UTC).
+pub(crate) fn parse_timestamp(s: &str, unit: Unit) -> Option<i64> {
+    let mut cursor = Cursor::new(s.trim());
+    let days = cursor.date()?;
+
+    let mut secs = 0;
+    let mut frac = 0;
+    if cursor.eat(b'T') || cursor.eat(b' ') {
+        let hour = cursor.digits(2)?;
+        cursor.expect(b':')?;
+        let minute = cursor.digits(2)?;
+        let mut second = 0;
+        if cursor.eat(b':') {
+            second = cursor.digits(2)?;
+            if cursor.eat(b'.') || cursor.eat(b',') {
+                frac = cursor.fraction(unit)?;
+            }
+        }
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        secs = hour * 3600 + minute * 60 + second;
+
+        // Offsets are subtracted to get back to UTC
+        if cursor.eat(b'Z') {
+        } else if let Some(sign) = cursor.sign() {
+            let off_hour = cursor.digits(2)?;
+            cursor.eat(b':');
+            let off_minute = cursor.digits(2)?;
+            if off_hour > 23 || off_minute > 59 {
+                return None;
+            }
+            secs -= sign * (off_hour * 3600 + off_minute * 60);
+        }
+    }
+    if !cursor.done() {
+        return None;
+    }
+
+    (days * SECONDS_PER_DAY + secs)
+        .checked_mul(unit.per_second())?
+        .checked_add(frac)
+}
+
+/// Minimal byte cursor for the ISO-8601 parsers.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(s: &'a str) -> Self {
+        Cursor {
+            bytes: s.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.bytes.get(self.pos) == Some(&b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        if self.eat(b) { Some(()) } else { None }
+    }
+
+    fn sign(&mut self) -> Option<i64> {
+        if self.eat(b'+') {
+            Some(1)
+        } else if self.eat(b'-') {
+            Some(-1)
+        } else {
+            None
+        }
+    }
+
+    /// Exactly `n` ascii digits.
+    fn digits(&mut self, n: usize) -> Option<i64> {
+        let end = self.pos + n;
+        if end > self.bytes.len() {
+            return None;
+        }
+        let mut res = 0;
+        for &b in &self.bytes[self.pos..end] {
+            if !b.is_ascii_digit() {
+                return None;
+            }
+            res = res * 10 + (b - b'0') as i64;
+        }
+        self.pos = end;
+        Some(res)
+    }
+
+    /// Fractional seconds, as a count of `unit`s. Digits finer
+    /// than `unit` are truncated.
+    fn fraction(&mut self, unit: Unit) -> Option<i64> {
+        let start = self.pos;
+        let mut res = 0;
+        let mut scale = unit.per_second();
+        while let Some(&b) = self.bytes.get(self.pos) {
+            if !b.is_ascii_digit() {
+                break;
+            }
+            scale /= 10;
+            res += (b - b'0') as i64 * scale;
+            self.pos += 1;
+        }
+        if self.pos == start { None } else { Some(res) }
+    }
+
+    /// `YYYY-MM-DD`, as days since the epoch.
+    fn date(&mut self) -> Option<i64> {
+        let year = self.digits(4)?;
+        self.expect(b'-')?;
+        let month = self.digits(2)? as u32;
+        self.expect(b'-')?;
+        let day = self.digits(2)? as u32;
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(days_from_civil(year, month, day))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in -1000..1000 {
+            let (y, m, d) = civil_from_days(days * 37);
+            assert_eq!(days_from_civil(y as i64, m, d), days * 37);
+        }
+    }
+
+    #[test]
+    fn parse_iso() {
+        let col = StringColumn::from(vec![
+            Some("2017-06-01"),
+            Some("2017-06-01T12:30:15"),
+            None,
+            Some("2017-06-01 12:30:15.25+02:00"),
+            Some("1969-12-31T23:59:59Z"),
+        ]);
+        let ts = TimestampColumn::parse(&col, Unit::Milliseconds).unwrap();
+        let day = 1_496_275_200_000;
+        assert_eq!(ts, TimestampColumn::from_values(vec![
+            Some(day),
+            Some(day + 45_015_000),
+            None,
+            Some(day + 45_015_250 - 7_200_000),
+            Some(-1000),
+        ], Unit::Milliseconds));
+
+        let bad = StringColumn::from(vec!["2017-06-01", "2017-02-30"]);
+        match TimestampColumn::parse(&bad, Unit::Seconds) {
+            Err(Error::Parse { row, value, .. }) => {
+                assert_eq!(row, 1);
+                assert_eq!(value, "2017-02-30");
+            },
+            res => panic!("expected parse error, got {:?}", res),
+        }
+        assert!(parse_timestamp("2017-06-01T12:30+99:99", Unit::Seconds).is_none());
+        assert!(parse_timestamp("2017-06-01T12:30+02:60", Unit::Seconds).is_none());
+        assert_eq!(parse_timestamp("2017-06-01T12:30-23:59", Unit::Seconds), Some(1_496_320_200 + 86_340));
+    }
+
+    #[test]
+    fn components() {
+        let col = StringColumn::from(vec![Some("2016-02-29T23:05:09"), None]);
+        let ts = TimestampColumn::parse(&col, Unit::Nanoseconds).unwrap().with_tz("UTC");
+        assert_eq!(ts.tz(), Some("UTC"));
+        assert_eq!(ts.year(), Int32Column::from(vec![Some(2016), None]));
+        assert_eq!(ts.month(), Int32Column::from(vec![Some(2), None]));
+        assert_eq!(ts.day(), Int32Column::from(vec![Some(29), None]));
+        assert_eq!(ts.hour(), Int32Column::from(vec![Some(23), None]));
+        assert_eq!(ts.minute(), Int32Column::from(vec![Some(5), None]));
+        assert_eq!(ts.second(), Int32Column::from(vec![Some(9), None]));
+        // a monday
+        assert_eq!(ts.weekday(), Int32Column::from(vec![Some(0), None]));
+        assert_eq!(ts.date().to_timestamp(Unit::Seconds).hour(), Int32Column::from(vec![Some(0), None]));
+    }
+
+    #[test]
+    fn dates() {
+        let col = StringColumn::from(vec![Some("1970-01-02"), None, Some("1969-12-28")]);
+        let dates = DateColumn::parse(&col).unwrap();
+        assert_eq!(dates, DateColumn::from_values(vec![Some(1), None, Some(-4)]));
+        assert_eq!(dates.weekday(), Int32Column::from(vec![Some(4), None, Some(6)]));
+        assert_eq!(dates.year(), Int32Column::from(vec![Some(1970), None, Some(1969)]));
+        assert!(DateColumn::parse(&StringColumn::from(vec!["1970-01-02T00:00"])).is_err());
+    }
+
+    #[test]
+    fn freqs() {
+        assert_eq!(parse_freq("1h", Unit::Seconds).unwrap(), 3600);
+        assert_eq!(parse_freq("D", Unit::Milliseconds).unwrap(), 86_400_000);
+        assert_eq!(parse_freq("15min", Unit::Seconds).unwrap(), 900);
+        assert_eq!(parse_freq("250ms", Unit::Microseconds).unwrap(), 250_000);
+        assert!(parse_freq("1ms", Unit::Seconds).is_err());
+        assert!(parse_freq("1M", Unit::Seconds).is_err());
+        assert!(parse_freq("0h", Unit::Seconds).is_err());
+        assert!(parse_freq("10", Unit::Seconds).is_err());
+    }
+
+    #[test]
+    fn date_range_and_floor() {
+        let range = TimestampColumn::date_range(
+            "2017-01-01", "2017-01-01T03:00:00", "1h", Unit::Seconds
+        ).unwrap();
+        assert_eq!(range.len(), 4);
+        assert_eq!(range.hour(), Int32Column::from(vec![0, 1, 2, 3]));
+        assert_eq!(Column::get_value(&range, 3).to_string(), "2017-01-01T03:00:00");
+
+        let ts = TimestampColumn::from_values(vec![Some(3599), None, Some(-1)], Unit::Seconds);
+        assert_eq!(
+            ts.floor("1h").unwrap(),
+            TimestampColumn::from_values(vec![Some(0), None, Some(-3600)], Unit::Seconds)
+        );
+        assert!(TimestampColumn::date_range("2017-01-01", "bad", "1h", Unit::Seconds).is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let ts = TimestampColumn::from_values(vec![Some(10), Some(20), None], Unit::Seconds)
+            .with_tz("Europe/Paris");
+        let delta = TimedeltaColumn::from_values(vec![Some(500), None, Some(1)], Unit::Milliseconds);
+
+        let shifted = &ts + &delta;
+        assert_eq!(shifted.unit(), Unit::Milliseconds);
+        assert_eq!(shifted.tz(), Some("Europe/Paris"));
+        assert_eq!(
+            shifted,
+            TimestampColumn::from_values(vec![Some(10_500), None, None], Unit::Milliseconds)
+                .with_tz("Europe/Paris")
+        );
+        assert_eq!(
+            &shifted - &ts,
+            TimedeltaColumn::from_values(vec![Some(500), None, None], Unit::Milliseconds)
+        );
+        assert_eq!(
+            (&shifted - &delta).to_unit(Unit::Seconds),
+            TimestampColumn::from_values(vec![Some(10), None, None], Unit::Seconds)
+                .with_tz("Europe/Paris")
+        );
+        assert_eq!(
+            &delta + &delta,
+            TimedeltaColumn::from_values(vec![Some(1000), None, Some(2)], Unit::Milliseconds)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "different time zones")]
+    fn subtract_mismatched_tz() {
+        let paris = TimestampColumn::from_values(vec![Some(10)], Unit::Seconds).with_tz("Europe/Paris");
+        let naive = TimestampColumn::from_values(vec![Some(10)], Unit::Seconds);
+        let _ = &paris - &naive;
+    }
+}
@@ -0,0 +1,579 @@
+//! Decimal dtype column
+//!
+//! Fixed-point numbers, for values like money which floats can't
+//! represent exactly. Each value is stored as an unscaled i128: with a
+//! scale of 2, 12.50 is stored as 1250. Precision is the max number of
+//! significant digits, up to 38 (which is what fits in an i128), same
+//! as arrow's decimal128.
+//!
+//! Arithmetic on the unscaled ints is exact. Wherever digits have to
+//! be dropped (parsing, rescaling, mean, casting to ints), the caller
+//! picks how to round with `Rounding`.
+
+use bit_vec::BitVec;
+use std::any::Any;
+use std::convert::TryFrom;
+use std::fmt;
+
+use error::{Error, Result};
+use super::{Column, DataType, Dtype, Float64Column, Int64Column, Series, StringColumn, Value};
+
+/// Max precision of a decimal, in digits.
+pub const MAX_PRECISION: u8 = 38;
+
+/// How to round when digits are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rounding {
+    /// Ties go to the even neighbour (banker's rounding).
+    HalfEven,
+    /// Ties go away from zero.
+    HalfUp,
+    /// Toward zero (truncation).
+    Down,
+    /// Away from zero.
+    Up,
+    /// Toward negative infinity.
+    Floor,
+    /// Toward positive infinity.
+    Ceiling,
+}
+
+impl Rounding {
+    /// `n / d`, rounded.
+    fn div(self, n: i128, d: i128) -> i128 {
+        let q = n / d;
+        let r = n % d;
+        if r == 0 {
+            return q;
+        }
+        // direction away from zero
+        let away = if (n < 0) != (d < 0) { -1 } else { 1 };
+        // compare the remainder to half of d without overflowing
+        let (r, rest) = (r.abs(), d.abs() - r.abs());
+        let round_away = match self {
+            Rounding::HalfEven => r > rest || (r == rest && q % 2 != 0),
+            Rounding::HalfUp => r >= rest,
+            Rounding::Down => false,
+            Rounding::Up => true,
+            Rounding::Floor => away < 0,
+            Rounding::Ceiling => away > 0,
+        };
+        if round_away { q + away } else { q }
+    }
+}
+
+/// A single decimal: `value` scaled down by `10^scale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    pub value: i128,
+    pub scale: u8,
+}
+
+impl Decimal {
+    pub fn new(value: i128, scale: u8) -> Self {
+        Decimal {
+            value,
+            scale,
+        }
+    }
+
+    /// Same number at another scale, rounding if digits are dropped.
+    /// `None` on overflow.
+    pub fn rescale(&self, scale: u8, rounding: Rounding) -> Option<Decimal> {
+        let value = if scale >= self.scale {
+            self.value.checked_mul(pow10(scale - self.scale)?)?
+        } else {
+            rounding.div(self.value, pow10(self.scale - scale)?)
+        };
+        Some(Decimal::new(value, scale))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.value as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.value < 0 { "-" } else { "" };
+        let digits = self.value.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecimalColumn {
+    values: Vec<i128>,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+    precision: u8,
+    scale: u8,
+}
+
+impl Column for DecimalColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::Decimal { precision: self.precision, scale: self.scale }
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        if self.mask[i] { Value::Decimal(self.values[i], self.scale) } else { Value::Null }
+    }
+
+    /// Decimals at other scales are rescaled, but only if that's
+    /// exact. Ints are converted.
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        let decimal = match value {
+            Value::Null => return self.push(None),
+            Value::Int(x) => Decimal::new(x as i128, 0),
+            Value::Decimal(x, scale) => Decimal::new(x, scale),
+            value => return Err(Error::TypeMismatch {
+                expected: self.dtype().to_string(),
+                found: value.kind().to_owned(),
+            }),
+        };
+        match decimal.rescale(self.scale, Rounding::Down) {
+            Some(x) if x.rescale(decimal.scale, Rounding::Down) == Some(decimal) => self.push(Some(x.value)),
+            _ => Err(self.overflow(self.len(), decimal)),
+        }
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(DecimalColumn::new(self.precision, self.scale))
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl DecimalColumn {
+    /// Panics unless `1 <= precision <= 38` and `scale <= precision`.
+    pub fn new(precision: u8, scale: u8) -> Self {
+        assert!(
+            (1..=MAX_PRECISION).contains(&precision),
+            "decimal precision must be between 1 and {}", MAX_PRECISION
+        );
+        assert!(scale <= precision, "decimal scale is greater than precision");
+        DecimalColumn {
+            values: Vec::new(),
+            mask: BitVec::new(),
+            precision,
+            scale,
+        }
+    }
+
+    /// From unscaled values. Errors on the first value with more
+    /// than `precision` digits.
+    pub fn from_values(v: Vec<Option<i128>>, precision: u8, scale: u8) -> Result<Self> {
+        let mut col = DecimalColumn::new(precision, scale);
+        for x in v {
+            col.push(x)?;
+        }
+        Ok(col)
+    }
+
+    /// Parses decimal text, like `-12.345` or `.5` (e.g. a field read
+    /// from a CSV), rounding extra fractional digits. Empty strings
+    /// are null.
+    ///
+    /// Each distinct string is parsed once. Errors on the first row
+    /// which isn't a number, or has too many digits for `precision`.
+    pub fn parse(col: &StringColumn, precision: u8, scale: u8, rounding: Rounding) -> Result<Self> {
+        let parsed = col.per_category(|s| parse_decimal(s, scale, rounding));
+        let mut res = DecimalColumn::new(precision, scale);
+        for i in 0..col.len() {
+            let code = match col.row_code(i) {
+                Some(code) if !col[i].trim().is_empty() => code,
+                _ => {
+                    res.push(None)?;
+                    continue;
+                },
+            };
+            match parsed[code] {
+                Some(x) if res.fits(x) => res.push(Some(x))?,
+                Some(_) => return Err(Error::Overflow {
+                    row: i,
+                    value: col[i].to_owned(),
+                    target: res.dtype().to_string(),
+                }),
+                None => return Err(Error::Parse {
+                    row: i,
+                    value: col[i].to_owned(),
+                    target: "decimal",
+                }),
+            }
+        }
+        Ok(res)
+    }
+
+    /// Converts any int column. Errors on the first value with too
+    /// many digits.
+    pub fn from_ints<C>(col: &C, precision: u8, scale: u8) -> Result<Self>
+        where C: DataType,
+              C::Item: Copy + Into<i128>
+    {
+        let mut res = DecimalColumn::new(precision, scale);
+        for (i, x) in col.values().enumerate() {
+            let x = match x {
+                Some(&x) => Decimal::new(x.into(), 0),
+                None => {
+                    res.push(None)?;
+                    continue;
+                },
+            };
+            // scaling ints up never drops digits, so the mode doesn't matter
+            match x.rescale(scale, Rounding::Down) {
+                Some(x) if res.fits(x.value) => res.push(Some(x.value))?,
+                _ => return Err(res.overflow(i, x)),
+            }
+        }
+        Ok(res)
+    }
+
+    /// Converts any float column, rounding to `scale`. Floats are
+    /// taken as their shortest decimal representation, so `0.1`
+    /// becomes exactly 0.1. NaN becomes null. Errors on the first
+    /// value which is infinite or has too many digits.
+    pub fn from_floats<C>(col: &C, precision: u8, scale: u8, rounding: Rounding) -> Result<Self>
+        where C: DataType,
+              C::Item: Copy + fmt::Display + Into<f64>
+    {
+        let mut res = DecimalColumn::new(precision, scale);
+        for (i, x) in col.values().enumerate() {
+            let x = match x {
+                Some(&x) if !x.into().is_nan() => x,
+                _ => {
+                    res.push(None)?;
+                    continue;
+                },
+            };
+            let text = x.to_string();
+            match parse_decimal(&text, scale, rounding) {
+                Some(value) if x.into().is_finite() && res.fits(value) => res.push(Some(value))?,
+                _ => return Err(Error::Overflow {
+                    row: i,
+                    value: text,
+                    target: res.dtype().to_string(),
+                }),
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        !self.mask.get(i).unwrap_or(true)
+    }
+
+    /// Errors if the unscaled value has more than `precision` digits.
+    pub fn push(&mut self, value: Option<i128>) -> Result<()> {
+        if let Some(x) = value {
+            if !self.fits(x) {
+                return Err(self.overflow(self.len(), Decimal::new(x, self.scale)));
+            }
+        }
+        self.values.push(value.unwrap_or(0));
+        self.mask.push(value.is_some());
+        Ok(())
+    }
+
+    /// Value at row `i`. The outer option is for out of bounds.
+    pub fn get_decimal(&self, i: usize) -> Option<Option<Decimal>> {
+        self.get(i).map(|x| x.map(|&x| Decimal::new(x, self.scale)))
+    }
+
+    /// Exact sum of the non-null values, at the column's scale.
+    /// Errors if it overflows an i128.
+    pub fn sum(&self) -> Result<Decimal> {
+        let mut sum: i128 = 0;
+        for &x in self.values().flatten() {
+            sum = sum.checked_add(x).ok_or_else(|| Error::AggregateOverflow {
+                agg: "sum",
+                target: "i128".to_owned(),
+            })?;
+        }
+        Ok(Decimal::new(sum, self.scale))
+    }
+
+    /// Mean of the non-null values, at the column's scale, rounded
+    /// by `rounding`. `None` if all values are null.
+    pub fn mean(&self, rounding: Rounding) -> Result<Option<Decimal>> {
+        let count = self.mask.iter().filter(|&valid| valid).count() as i128;
+        if count == 0 {
+            return Ok(None);
+        }
+        let sum = self.sum()?;
+        Ok(Some(Decimal::new(rounding.div(sum.value, count), self.scale)))
+    }
+
+    /// Changes precision and scale, rounding if the scale shrinks.
+    /// Errors on the first value which no longer fits.
+    pub fn rescale(&self, precision: u8, scale: u8, rounding: Rounding) -> Result<Self> {
+        let mut res = DecimalColumn::new(precision, scale);
+        for i in 0..self.len() {
+            match self.get_decimal(i).and_then(|x| x) {
+                Some(x) => match x.rescale(scale, rounding) {
+                    Some(y) if res.fits(y.value) => res.push(Some(y.value))?,
+                    _ => return Err(res.overflow(i, x)),
+                },
+                None => res.push(None)?,
+            }
+        }
+        Ok(res)
+    }
+
+    /// Nearest floats; lossy, but never fails.
+    pub fn to_f64(&self) -> Float64Column {
+        (0..self.len())
+            .map(|i| self.get_decimal(i).and_then(|x| x).map(|x| x.to_f64()))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Rounds to ints. Errors on the first value outside i64.
+    pub fn to_i64(&self, rounding: Rounding) -> Result<Int64Column> {
+        let mut res = Vec::with_capacity(self.len());
+        for i in 0..self.len() {
+            match self.get_decimal(i).and_then(|x| x) {
+                Some(x) => {
+                    let int = x.rescale(0, rounding).and_then(|x| i64::try_from(x.value).ok());
+                    match int {
+                        Some(int) => res.push(Some(int)),
+                        None => return Err(Error::Overflow {
+                            row: i,
+                            value: x.to_string(),
+                            target: Dtype::Int64.to_string(),
+                        }),
+                    }
+                },
+                None => res.push(None),
+            }
+        }
+        Ok(res.into())
+    }
+
+    /// Decimal at row `i` as text, like `12.50`.
+    pub fn format(&self, i: usize) -> Option<String> {
+        self.get_decimal(i).and_then(|x| x).map(|x| x.to_string())
+    }
+
+    fn fits(&self, x: i128) -> bool {
+        // precision is at most 38, and 10^38 fits in an i128
+        x.unsigned_abs() < pow10(self.precision).unwrap() as u128
+    }
+
+    fn overflow(&self, row: usize, value: Decimal) -> Error {
+        Error::Overflow {
+            row,
+            value: value.to_string(),
+            target: self.dtype().to_string(),
+        }
+    }
+}
+
+impl DataType for DecimalColumn {
+    type Item = i128;
+
+    fn get(&self, index: usize) -> Option<Option<&i128>> {
+        if let Some(mask) = self.mask.get(index) {
+            if !mask {
+                return Some(None);
+            }
+        } else {
+            return None;
+        }
+        Some(self.values.get(index))
+    }
+
+    fn values(&self) -> Series<'_, Self::Item> {
+        Series::new(&self.values, &self.mask)
+    }
+}
+
+/// `10^n`, `None` if it overflows an i128.
+fn pow10(n: u8) -> Option<i128> {
+    10i128.checked_pow(n as u32)
+}
+
+/// Parses decimal text to an unscaled value at `scale`.
+pub(crate) fn parse_decimal(s: &str, scale: u8, rounding: Rounding) -> Option<i128> {
+    let s = s.trim();
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (int, frac) = match s.find('.') {
+        Some(dot) => (&s[..dot], &s[dot + 1..]),
+        None => (s, ""),
+    };
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if int.len() + frac.len() == 0 || !is_digits(int) || !is_digits(frac) {
+        return None;
+    }
+
+    // Only one digit past the scale matters for rounding, plus whether
+    // anything after it is non-zero (which breaks ties). That gets
+    // folded into a second extra digit, so the result is exact.
+    let keep = (scale as usize + 1).min(frac.len());
+    let sticky = frac[keep..].bytes().any(|b| b != b'0');
+    let mut value: i128 = 0;
+    for b in int.bytes().chain(frac[..keep].bytes()) {
+        value = value.checked_mul(10)?.checked_add((b - b'0') as i128)?;
+    }
+    let mut digits = keep;
+    if sticky {
+        value = value.checked_mul(10)?.checked_add(1)?;
+        digits += 1;
+    }
+    if negative {
+        value = -value;
+    }
+
+    Decimal::new(value, digits as u8)
+        .rescale(scale, rounding)
+        .map(|x| x.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Float32Column, Int8Column};
+
+    #[test]
+    fn rounding() {
+        let round = |n, mode: Rounding| mode.div(n, 10);
+        assert_eq!(round(25, Rounding::HalfEven), 2);
+        assert_eq!(round(35, Rounding::HalfEven), 4);
+        assert_eq!(round(-25, Rounding::HalfEven), -2);
+        assert_eq!(round(25, Rounding::HalfUp), 3);
+        assert_eq!(round(-25, Rounding::HalfUp), -3);
+        assert_eq!(round(-29, Rounding::Down), -2);
+        assert_eq!(round(21, Rounding::Up), 3);
+        assert_eq!(round(-21, Rounding::Floor), -3);
+        assert_eq!(round(-21, Rounding::Ceiling), -2);
+        assert_eq!(round(20, Rounding::Up), 2);
+    }
+
+    #[test]
+    fn parse_and_format() {
+        let col = DecimalColumn::parse(
+            &StringColumn::from(vec![Some("12.5"), None, Some("-0.125"), Some(".5"), Some(""), Some("1.0051")]),
+            5, 2, Rounding::HalfEven,
+        ).unwrap();
+        assert_eq!(col.format(0), Some("12.50".to_owned()));
+        assert_eq!(col.format(1), None);
+        // tie goes to even
+        assert_eq!(col.format(2), Some("-0.12".to_owned()));
+        assert_eq!(col.format(3), Some("0.50".to_owned()));
+        assert!(col.is_null(4));
+        // past the tie, so it rounds up
+        assert_eq!(col.format(5), Some("1.01".to_owned()));
+        assert_eq!(col.dtype(), Dtype::Decimal { precision: 5, scale: 2 });
+
+        match DecimalColumn::parse(&StringColumn::from(vec!["1", "1.2.3"]), 5, 2, Rounding::HalfEven) {
+            Err(Error::Parse { row: 1, .. }) => (),
+            res => panic!("{:?}", res),
+        }
+        match DecimalColumn::parse(&StringColumn::from(vec!["1000"]), 5, 2, Rounding::HalfEven) {
+            Err(Error::Overflow { row: 0, .. }) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    #[test]
+    fn exact_sum_and_mean() {
+        let col = DecimalColumn::from_floats(
+            &Float64Column::from(vec![Some(0.1), Some(0.2), None, Some(0.05)]),
+            10, 2, Rounding::HalfEven,
+        ).unwrap();
+        assert_eq!(col.sum().unwrap(), Decimal::new(35, 2));
+        // 0.35 / 3 = 0.11666...
+        assert_eq!(col.mean(Rounding::HalfEven).unwrap(), Some(Decimal::new(12, 2)));
+        assert_eq!(col.mean(Rounding::Down).unwrap(), Some(Decimal::new(11, 2)));
+        assert_eq!(DecimalColumn::new(10, 2).mean(Rounding::Down).unwrap(), None);
+
+        // 38 nines fit, but two of them overflow an i128
+        let nines = 10i128.pow(38) - 1;
+        let big = DecimalColumn::from_values(vec![Some(nines), Some(-nines)], 38, 0).unwrap();
+        assert_eq!(big.sum().unwrap(), Decimal::new(0, 0));
+        let bigger = DecimalColumn::from_values(vec![Some(nines); 2], 38, 0).unwrap();
+        match bigger.sum() {
+            Err(Error::AggregateOverflow { agg: "sum", .. }) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    #[test]
+    fn casts() {
+        let ints = DecimalColumn::from_ints(&Int8Column::from(vec![Some(-100), None]), 5, 2).unwrap();
+        assert_eq!(ints.format(0), Some("-100.00".to_owned()));
+        assert!(DecimalColumn::from_ints(&Int8Column::from(vec![100]), 4, 2).is_err());
+
+        let floats = DecimalColumn::from_floats(&Float32Column::from(vec![0.1f32, 2.675]), 6, 2, Rounding::HalfUp).unwrap();
+        assert_eq!(floats.format(0), Some("0.10".to_owned()));
+        assert_eq!(floats.format(1), Some("2.68".to_owned()));
+        let nan = DecimalColumn::from_floats(&Float64Column::from(vec![f64::NAN]), 6, 2, Rounding::HalfUp).unwrap();
+        assert!(nan.is_null(0));
+        assert!(DecimalColumn::from_floats(&Float64Column::from(vec![f64::INFINITY]), 6, 2, Rounding::HalfUp).is_err());
+        assert!(DecimalColumn::from_floats(&Float64Column::from(vec![1e10]), 6, 2, Rounding::HalfUp).is_err());
+
+        assert_eq!(floats.to_f64(), Float64Column::from(vec![0.1, 2.68]));
+        assert_eq!(floats.to_i64(Rounding::HalfEven).unwrap(), Int64Column::from(vec![0, 3]));
+        let huge = DecimalColumn::from_values(vec![Some(10i128.pow(30))], 38, 0).unwrap();
+        assert!(huge.to_i64(Rounding::Down).is_err());
+
+        let rescaled = floats.rescale(3, 1, Rounding::Down).unwrap();
+        assert_eq!(rescaled.format(1), Some("2.6".to_owned()));
+        assert!(floats.rescale(3, 2, Rounding::Down).is_ok());
+        assert!(floats.rescale(2, 2, Rounding::Down).is_err());
+    }
+
+    #[test]
+    fn dynamic() {
+        let mut col = DecimalColumn::new(5, 2);
+        col.push_value(Value::Int(3)).unwrap();
+        col.push_value(Value::Decimal(15, 1)).unwrap();
+        col.push_value(Value::Null).unwrap();
+        assert!(col.push_value(Value::Decimal(1234, 3)).is_err());
+        assert!(col.push_value(Value::Float(1.5)).is_err());
+        assert_eq!(col.get_value(0), Value::Decimal(300, 2));
+        assert_eq!(col.get_value(1), Value::Decimal(150, 2));
+        assert_eq!(col.get_value(1).to_string(), "1.50");
+        assert_eq!(col.get_value(2), Value::Null);
+        assert_eq!(col.take(&[Some(1), None]).get_value(0), Value::Decimal(150, 2));
+    }
+}
@@ -1,34 +1,194 @@
+use bit_vec::BitVec;
 use rayon::prelude::*;
+use std::any::Any;
 use std::convert::From;
 
-use super::{Column, Numeric};
+use error::{Error, Result};
+use super::{Chunk, Column, DataType, DataTypeMut, Dtype, Numeric, Series, Value};
 
-#[derive(Debug)]
-pub struct Float32Column(Vec<f32>);
+macro_rules! float_column {
+    ($name:ident, $t:ty, $dtype:expr) => {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name {
+            values: Vec<$t>,
+            // Mask uses a bitvec overlaid onto values to know which indices hold
+            // a null value. false in the bitvec maps to null in values.
+            mask: BitVec,
+        }
 
-impl Float32Column {
-    pub fn new(v: Vec<f32>) -> Self {
-        Float32Column(v)
-    }
-}
+        impl $name {
+            pub fn new() -> Self {
+                $name {
+                    values: Vec::new(),
+                    mask: BitVec::new(),
+                }
+            }
 
-impl Column for Float32Column {
-    type BaseType = f32;
+            pub fn len(&self) -> usize {
+                self.values.len()
+            }
 
-    fn dtype() -> String { "float32".to_owned() }
+            pub fn is_empty(&self) -> bool {
+                self.values.is_empty()
+            }
+        }
 
-    fn apply<F: Fn(f32) -> f32>(&mut self, f: F)
-        where F: ::std::marker::Sync {
-        self.0.par_iter_mut().for_each(|x| *x = f(*x));
-    }
-}
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
 
-impl Numeric for Float32Column {
-    fn sum(&self) -> f32 {
-        self.0.par_iter().cloned().sum()
-    }
+        impl Chunk for $name {
+            fn append(&mut self, other: &$name) {
+                self.values.extend_from_slice(&other.values);
+                self.mask.extend(other.mask.iter());
+            }
+        }
+
+        impl Column for $name {
+            fn dtype(&self) -> Dtype {
+                $dtype
+            }
+
+            fn len(&self) -> usize {
+                self.values.len()
+            }
+
+            fn is_null(&self, i: usize) -> bool {
+                !self.mask[i]
+            }
+
+            fn get_value(&self, i: usize) -> Value {
+                if self.mask[i] { Value::Float(self.values[i] as f64) } else { Value::Null }
+            }
+
+            fn push_value(&mut self, value: Value) -> Result<()> {
+                match value {
+                    Value::Null => self.push(None),
+                    Value::Float(x) => self.push(Some(x as $t)),
+                    Value::Int(x) => self.push(Some(x as $t)),
+                    value => return Err(Error::TypeMismatch {
+                        expected: $dtype.to_string(),
+                        found: value.kind().to_owned(),
+                    }),
+                }
+                Ok(())
+            }
+
+            fn new_empty(&self) -> Box<dyn Column> {
+                Box::new($name::new())
+            }
+
+            fn clone_box(&self) -> Box<dyn Column> {
+                Box::new(self.clone())
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        impl DataType for $name {
+            type Item = $t;
+
+            fn get(&self, index: usize) -> Option<Option<&$t>> {
+                if let Some(mask) = self.mask.get(index) {
+                    if !mask {
+                        return Some(None);
+                    }
+                } else {
+                    return None;
+                }
+                Some(self.values.get(index))
+            }
+
+            fn values(&self) -> Series<'_, Self::Item> {
+                Series::new(&self.values, &self.mask)
+            }
+        }
+
+        impl DataType for &$name {
+            type Item = $t;
+
+            fn get(&self, index: usize) -> Option<Option<&$t>> {
+                (*self).get(index)
+            }
+
+            fn values(&self) -> Series<'_, Self::Item> {
+                Series::new(&self.values, &self.mask)
+            }
+        }
+
+        impl DataTypeMut for $name {
+            fn push(&mut self, item: Option<$t>) {
+                self.values.push(item.unwrap_or(0.0));
+                self.mask.push(item.is_some());
+            }
+
+            fn apply<F>(&mut self, f: F)
+                where F: Fn($t) -> $t + ::std::marker::Sync
+            {
+                let mask = &self.mask;
+                self.values
+                    .par_iter_mut()
+                    .enumerate()
+                    .filter(|&(i,_)| mask[i] )
+                    .for_each(|(_, x)| *x = f(*x));
+            }
+        }
+
+        impl Numeric for $name {
+            fn parts(&self) -> (&[$t], &BitVec) {
+                (&self.values, &self.mask)
+            }
+        }
+
+        impl Numeric for &$name {
+            fn parts(&self) -> (&[$t], &BitVec) {
+                (&self.values, &self.mask)
+            }
+        }
+
+        impl From<Vec<$t>> for $name {
+            fn from(v: Vec<$t>) -> Self {
+                let length = v.len();
+                $name {
+                    values: v,
+                    mask: BitVec::from_elem(length, true),
+                }
+            }
+        }
+
+        impl From<Vec<Option<$t>>> for $name {
+            fn from(v: Vec<Option<$t>>) -> Self {
+                let mask = BitVec::from_fn(v.len(), |i| v[i].is_some());
+                let values = v.into_iter().map(|x| x.unwrap_or(0.0)).collect();
+
+                $name {
+                    values,
+                    mask,
+                }
+            }
+        }
+
+        float_ops!($name, $t);
+        cumulative_ops!($name, $t, Float64Column);
+
+        impl<'a> IntoIterator for &'a $name {
+            type Item = Option<&'a $t>;
+            type IntoIter = Series<'a, $t>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                Series::new(&self.values, &self.mask)
+            }
+        }
+    };
 }
 
+float_column!(Float32Column, f32, Dtype::Float32);
+float_column!(Float64Column, f64, Dtype::Float64);
+
 #[cfg(test)]
 mod tests {
     use std::{f32};
@@ -51,22 +211,27 @@ mod tests {
         }
     }
 
-    fn compare_float_vec(xs: Vec<f32>, ys: Vec<f32>) -> bool {
-        xs.iter().zip(ys.iter()).all(|(&a,&b)| float_nearly_equal(a,b))
-    }
-
     #[test]
     fn impl_column_for_float() {
-        let mut col = Float32Column::new(vec![1.0,2.,3.,4.,5.,6.]);
+        let mut col = Float32Column::from(vec![1.0,2.,3.,4.,5.,6.]);
         col.apply(|x| x*x);
         let res = vec![1.0,4.,9.,16.,25.,36.];
-        assert_eq!(col.0, res);
+        assert_eq!(col.values, res);
     }
 
     #[test]
     fn impl_numeric_column_for_float() {
-        let col = Float32Column::new(vec![1.0,2.,3.,4.,5.,6.]);
+        let col = &Float32Column::from(vec![1.0,2.,3.,4.,5.,6.]);
         let sum = col.sum();
-        assert!(float_nearly_equal(sum, 21.0));
+        assert!(float_nearly_equal(sum as f32, 21.0));
+    }
+
+    #[test]
+    fn float_column_nulls() {
+        let col = Float64Column::from(vec![Some(1.5), None, Some(2.0)]);
+        assert_eq!(col.get(1), Some(None));
+        assert_eq!(col.get_value(2), Value::Float(2.0));
+        assert_eq!(col.get_value(1), Value::Null);
+        assert_eq!(col.sum(), 3.5);
     }
 }
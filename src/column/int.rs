@@ -1,151 +1,223 @@
 use bit_vec::BitVec;
 use rayon::prelude::*;
-use std::convert::From;
+use std::any::Any;
+use std::convert::{From, TryFrom};
 
-use super::{Column, DataType, DataTypeMut, Numeric, Series};
+use error::{Error, Result};
+use super::{Chunk, Column, DataType, DataTypeMut, Dtype, Numeric, Series, Value};
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Int8Column {
-    values: Vec<i8>,
-    // Mask uses a bitvec overlaid onto values to know which indices hold
-    // a null value. false in the bitvec maps to null in values.
-    mask: BitVec,
-}
+macro_rules! int_column {
+    ($name:ident, $t:ty, $dtype:expr) => {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name {
+            values: Vec<$t>,
+            // Mask uses a bitvec overlaid onto values to know which indices hold
+            // a null value. false in the bitvec maps to null in values.
+            mask: BitVec,
+        }
 
-impl Column for Int8Column{}
+        impl Column for $name {
+            fn dtype(&self) -> Dtype {
+                $dtype
+            }
 
-impl Int8Column {
-    pub fn new() -> Self {
-        // TODO later, make sure that I don't have to
-        // do an assert when creating Column that
-        // length of values and mask are the same
-        Int8Column {
-            values: Vec::new(),
-            mask: BitVec::new(),
-        }
-    }
-}
+            fn len(&self) -> usize {
+                self.values.len()
+            }
+
+            fn is_null(&self, i: usize) -> bool {
+                !self.mask[i]
+            }
+
+            fn get_value(&self, i: usize) -> Value {
+                if self.mask[i] { Value::Int(self.values[i] as i64) } else { Value::Null }
+            }
+
+            /// Ints which don't fit in the column's width are an error.
+            fn push_value(&mut self, value: Value) -> Result<()> {
+                match value {
+                    Value::Null => self.push(None),
+                    Value::Int(x) => match <$t>::try_from(x) {
+                        Ok(x) => self.push(Some(x)),
+                        Err(_) => return Err(Error::TypeMismatch {
+                            expected: $dtype.to_string(),
+                            found: x.to_string(),
+                        }),
+                    },
+                    value => return Err(Error::TypeMismatch {
+                        expected: $dtype.to_string(),
+                        found: value.kind().to_owned(),
+                    }),
+                }
+                Ok(())
+            }
 
+            fn new_empty(&self) -> Box<dyn Column> {
+                Box::new($name::new())
+            }
 
-impl DataType for Int8Column {
-    type Item = i8;
+            fn clone_box(&self) -> Box<dyn Column> {
+                Box::new(self.clone())
+            }
 
-    fn get(&self, index: usize) -> Option<Option<&i8>> {
-        if let Some(mask) = self.mask.get(index) {
-            if !mask {
-                return Some(None);
+            fn as_any(&self) -> &dyn Any {
+                self
             }
-        } else {
-            return None;
         }
-        Some(self.values.get(index))
-    }
 
-    fn values(&self) -> Series<Self::Item> {
-        Series::new(self)
-    }
-}
+        impl $name {
+            pub fn new() -> Self {
+                // TODO later, make sure that I don't have to
+                // do an assert when creating Column that
+                // length of values and mask are the same
+                $name {
+                    values: Vec::new(),
+                    mask: BitVec::new(),
+                }
+            }
 
-impl<'a> DataType for &'a Int8Column {
-    type Item = i8;
+            pub fn len(&self) -> usize {
+                self.values.len()
+            }
 
-    fn get(&self, index: usize) -> Option<Option<&i8>> {
-        if let Some(mask) = self.mask.get(index) {
-            if !mask {
-                return Some(None);
+            pub fn is_empty(&self) -> bool {
+                self.values.is_empty()
             }
-        } else {
-            return None;
         }
-        Some(self.values.get(index))
-    }
 
-    fn values(&self) -> Series<Self::Item> {
-        Series::new(self)
-    }
-}
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
 
-impl DataTypeMut for Int8Column {
-    fn push(&mut self, item: Option<i8>) {
-        match item {
-            Some(item) => {
-                self.values.push(item);
-                self.mask.push(true);
-            },
-            None => {
-                self.values.push(0);
-                self.mask.push(false);
-            },
+        impl Chunk for $name {
+            fn append(&mut self, other: &$name) {
+                self.values.extend_from_slice(&other.values);
+                self.mask.extend(other.mask.iter());
+            }
         }
-    }
 
-    fn apply<F>(&mut self, f: F)
-        where F: Fn(i8) -> i8 + ::std::marker::Sync
-    {
-        // TODO best way to apply mask? zip values, or refer to mask by index?
-
-        let mask = &self.mask;
-        self.values
-            .par_iter_mut()
-            .enumerate()
-            .filter(|&(i,_)| mask[i] )
-            .for_each(|(_, x)| *x = f(*x));
-    }
-}
+        impl DataType for $name {
+            type Item = $t;
 
-impl<'a> Numeric for &'a Int8Column {
-}
+            fn get(&self, index: usize) -> Option<Option<&$t>> {
+                if let Some(mask) = self.mask.get(index) {
+                    if !mask {
+                        return Some(None);
+                    }
+                } else {
+                    return None;
+                }
+                Some(self.values.get(index))
+            }
 
-impl<'a> ::std::iter::Sum<i8> for &'a Int8Column {
-    fn sum<I>(iter: I) -> Self
-        where I: Iterator<Item=i8>
-    {
-        iter.sum()
-    }
-}
+            fn values(&self) -> Series<'_, Self::Item> {
+                Series::new(&self.values, &self.mask)
+            }
+        }
+
+        impl DataType for &$name {
+            type Item = $t;
 
+            fn get(&self, index: usize) -> Option<Option<&$t>> {
+                if let Some(mask) = self.mask.get(index) {
+                    if !mask {
+                        return Some(None);
+                    }
+                } else {
+                    return None;
+                }
+                Some(self.values.get(index))
+            }
 
-impl From<Vec<i8>> for Int8Column {
-    fn from(v: Vec<i8>) -> Self {
-        let length = v.len();
-        Int8Column {
-            values: v,
-            mask: BitVec::from_elem(length, true),
+            fn values(&self) -> Series<'_, Self::Item> {
+                Series::new(&self.values, &self.mask)
+            }
         }
-    }
-}
 
-impl From<Vec<Option<i8>>> for Int8Column {
-    fn from(v: Vec<Option<i8>>) -> Self {
-        let mask = BitVec::from_fn(v.len(), |i| {
-            match v[i] {
-                Some(_) => true,
-                _ => false,
+        impl DataTypeMut for $name {
+            fn push(&mut self, item: Option<$t>) {
+                match item {
+                    Some(item) => {
+                        self.values.push(item);
+                        self.mask.push(true);
+                    },
+                    None => {
+                        self.values.push(0);
+                        self.mask.push(false);
+                    },
+                }
             }
-        });
-        let values = v.into_iter().map(|x| {
-            match x {
-                Some(x) => x,
-                _ => 0,
+
+            fn apply<F>(&mut self, f: F)
+                where F: Fn($t) -> $t + ::std::marker::Sync
+            {
+                // TODO best way to apply mask? zip values, or refer to mask by index?
+
+                let mask = &self.mask;
+                self.values
+                    .par_iter_mut()
+                    .enumerate()
+                    .filter(|&(i,_)| mask[i] )
+                    .for_each(|(_, x)| *x = f(*x));
             }
-        }).collect();
+        }
 
-        Int8Column {
-            values,
-            mask,
+        impl Numeric for $name {
+            fn parts(&self) -> (&[$t], &BitVec) {
+                (&self.values, &self.mask)
+            }
+        }
+
+        impl Numeric for &$name {
+            fn parts(&self) -> (&[$t], &BitVec) {
+                (&self.values, &self.mask)
+            }
         }
-    }
-}
 
-impl<'a> IntoIterator for &'a Int8Column {
-    type Item = Option<&'a i8>;
-    type IntoIter = Series<'a, i8>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        Series::new(self)
-    }
+        impl From<Vec<$t>> for $name {
+            fn from(v: Vec<$t>) -> Self {
+                let length = v.len();
+                $name {
+                    values: v,
+                    mask: BitVec::from_elem(length, true),
+                }
+            }
+        }
+
+        impl From<Vec<Option<$t>>> for $name {
+            fn from(v: Vec<Option<$t>>) -> Self {
+                let mask = BitVec::from_fn(v.len(), |i| v[i].is_some());
+                let values = v.into_iter().map(|x| x.unwrap_or_default()).collect();
+
+                $name {
+                    values,
+                    mask,
+                }
+            }
+        }
+
+        int_ops!($name, $t);
+        cumulative_ops!($name, $t, Int64Column);
+
+        impl<'a> IntoIterator for &'a $name {
+            type Item = Option<&'a $t>;
+            type IntoIter = Series<'a, $t>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                Series::new(&self.values, &self.mask)
+            }
+        }
+    };
 }
 
+int_column!(Int8Column, i8, Dtype::Int8);
+int_column!(Int16Column, i16, Dtype::Int16);
+int_column!(Int32Column, i32, Dtype::Int32);
+int_column!(Int64Column, i64, Dtype::Int64);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -179,7 +251,7 @@ mod tests {
         mask.set(4, false);
         let col = &Int8Column {
             values: vec![1,2,3,4,5,6],
-            mask: mask,
+            mask,
         };
         let sum = col.sum();
         assert_eq!(sum, 13);
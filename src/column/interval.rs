@@ -0,0 +1,390 @@
+//! Interval dtype column
+//!
+//! Each row is a span between a left and right bound. Whether the
+//! bounds are included is set for the whole column, same as pandas.
+//! Bounds can be anything ordered: floats for numeric bins, or
+//! i64 timestamps (in a column's unit) for time spans.
+
+use bit_vec::BitVec;
+use std::any::Any;
+use std::cmp::Ordering;
+use std::fmt;
+
+use error::{Error, Result};
+use super::{BooleanColumn, Column, Dtype, Value};
+
+/// Which bounds of an interval are included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Closed {
+    Left,
+    Right,
+    Both,
+    Neither,
+}
+
+impl Closed {
+    fn left(self) -> bool {
+        self == Closed::Left || self == Closed::Both
+    }
+
+    fn right(self) -> bool {
+        self == Closed::Right || self == Closed::Both
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval<T> {
+    pub left: T,
+    pub right: T,
+    pub closed: Closed,
+}
+
+impl<T: PartialOrd + Copy> Interval<T> {
+    pub fn new(left: T, right: T, closed: Closed) -> Self {
+        Interval {
+            left,
+            right,
+            closed,
+        }
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let above_left = if self.closed.left() { value >= self.left } else { value > self.left };
+        let below_right = if self.closed.right() { value <= self.right } else { value < self.right };
+        above_left && below_right
+    }
+
+    /// Whether any value is in both intervals. Touching bounds only
+    /// overlap if both intervals include them.
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        // self ends before other starts, or other ends before self starts
+        let before = |a: &Interval<T>, b: &Interval<T>| {
+            if a.closed.right() && b.closed.left() {
+                a.right < b.left
+            } else {
+                a.right <= b.left
+            }
+        };
+        !before(self, other) && !before(other, self)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let open = if self.closed.left() { "[" } else { "(" };
+        let close = if self.closed.right() { "]" } else { ")" };
+        write!(f, "{}{}, {}{}", open, self.left, self.right, close)
+    }
+}
+
+/// Types which can be the bounds of an interval column, and so go in
+/// and out of `Value`s.
+pub trait IntervalBound: PartialOrd + Copy + Default + fmt::Debug + fmt::Display + 'static {
+    fn to_value(self) -> Value;
+
+    /// `None` unless `value` is a bound of this type.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+macro_rules! int_bound {
+    ($t:ty) => {
+        impl IntervalBound for $t {
+            fn to_value(self) -> Value {
+                Value::Int(self as i64)
+            }
+
+            fn from_value(value: &Value) -> Option<Self> {
+                match *value {
+                    Value::Int(x) => <$t as ::std::convert::TryFrom<i64>>::try_from(x).ok(),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+int_bound!(i8);
+int_bound!(i16);
+int_bound!(i32);
+int_bound!(i64);
+
+impl IntervalBound for f32 {
+    fn to_value(self) -> Value {
+        Value::Float(f64::from(self))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_f64().map(|x| x as f32)
+    }
+}
+
+impl IntervalBound for f64 {
+    fn to_value(self) -> Value {
+        Value::Float(self)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        value.as_f64()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntervalColumn<T> {
+    left: Vec<T>,
+    right: Vec<T>,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+    closed: Closed,
+}
+
+/// Intervals go in and out of the dynamic interface as
+/// `Value::Interval`, which must have the column's closed sides.
+impl<T: IntervalBound> Column for IntervalColumn<T> {
+    fn dtype(&self) -> Dtype {
+        Dtype::Interval
+    }
+
+    fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        match self.get(i) {
+            Some(Some(interval)) => Value::Interval(
+                Box::new(interval.left.to_value()),
+                Box::new(interval.right.to_value()),
+                interval.closed,
+            ),
+            _ => Value::Null,
+        }
+    }
+
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push(None),
+            Value::Interval(ref left, ref right, closed) if closed == self.closed => {
+                match (T::from_value(left), T::from_value(right)) {
+                    (Some(left), Some(right)) => self.push(Some((left, right))),
+                    _ => Err(Error::TypeMismatch {
+                        expected: Dtype::Interval.to_string(),
+                        found: format!("interval of {} and {}", left.kind(), right.kind()),
+                    }),
+                }
+            },
+            Value::Interval(_, _, closed) => Err(Error::TypeMismatch {
+                expected: format!("interval closed {:?}", self.closed),
+                found: format!("interval closed {:?}", closed),
+            }),
+            value => Err(Error::TypeMismatch {
+                expected: Dtype::Interval.to_string(),
+                found: value.kind().to_owned(),
+            }),
+        }
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(IntervalColumn::<T>::new(self.closed))
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn take(&self, indices: &[Option<usize>]) -> Box<dyn Column> {
+        let bounds = indices.iter()
+            .map(|index| {
+                index.and_then(|i| self.get(i).and_then(|x| x))
+                    .map(|interval| (interval.left, interval.right))
+            })
+            .collect();
+        // bounds from intervals already in the column
+        Box::new(IntervalColumn::from_values(bounds, self.closed).unwrap())
+    }
+}
+
+impl<T: PartialOrd + Copy + Default> IntervalColumn<T> {
+    pub fn new(closed: Closed) -> Self {
+        IntervalColumn {
+            left: Vec::new(),
+            right: Vec::new(),
+            mask: BitVec::new(),
+            closed,
+        }
+    }
+
+    /// From (left, right) bounds; see `push`.
+    pub fn from_values(v: Vec<Option<(T, T)>>, closed: Closed) -> Result<Self> {
+        let mut col = IntervalColumn::new(closed);
+        for bounds in v {
+            col.push(bounds)?;
+        }
+        Ok(col)
+    }
+
+    /// Consecutive intervals between sorted `breaks`, for binning:
+    /// `[0, 10, 20]` gives `(0, 10]` and `(10, 20]` when closed right.
+    /// Errors unless the breaks are sorted.
+    pub fn from_breaks(breaks: &[T], closed: Closed) -> Result<Self> {
+        IntervalColumn::from_values(
+            breaks.windows(2).map(|w| Some((w[0], w[1]))).collect(),
+            closed,
+        )
+    }
+
+    /// Errors unless `left <= right`, which a NaN bound never is.
+    pub fn push(&mut self, bounds: Option<(T, T)>) -> Result<()> {
+        let (left, right) = bounds.unwrap_or_default();
+        // NaN bounds compare as None
+        if left.partial_cmp(&right).is_none_or(|ord| ord == Ordering::Greater) {
+            return Err(Error::InvalidInterval { row: self.len() });
+        }
+        self.left.push(left);
+        self.right.push(right);
+        self.mask.push(bounds.is_some());
+        Ok(())
+    }
+
+    pub fn get(&self, i: usize) -> Option<Option<Interval<T>>> {
+        self.mask.get(i).map(|valid| {
+            if valid {
+                Some(Interval::new(self.left[i], self.right[i], self.closed))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn closed(&self) -> Closed {
+        self.closed
+    }
+
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        !self.mask.get(i).unwrap_or(true)
+    }
+
+    /// Whether each interval contains `value`.
+    pub fn contains(&self, value: T) -> BooleanColumn {
+        self.map_bool(|interval| interval.contains(value))
+    }
+
+    /// Whether each interval overlaps `other`.
+    pub fn overlaps(&self, other: &Interval<T>) -> BooleanColumn {
+        self.map_bool(|interval| interval.overlaps(other))
+    }
+
+    /// Row of the first interval containing `value`, which is the
+    /// bin `value` falls into for columns built with `from_breaks`.
+    pub fn find(&self, value: T) -> Option<usize> {
+        (0..self.len()).find(|&i| {
+            self.get(i)
+                .and_then(|interval| interval)
+                .is_some_and(|interval| interval.contains(value))
+        })
+    }
+
+    fn map_bool<F>(&self, f: F) -> BooleanColumn
+        where F: Fn(&Interval<T>) -> bool
+    {
+        (0..self.len())
+            .map(|i| self.get(i).and_then(|interval| interval).map(|interval| f(&interval)))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_contains() {
+        let right = Interval::new(0.0, 1.0, Closed::Right);
+        assert!(!right.contains(0.0));
+        assert!(right.contains(1.0));
+        let both = Interval::new(0, 1, Closed::Both);
+        assert!(both.contains(0) && both.contains(1));
+        let neither = Interval::new(0, 1, Closed::Neither);
+        assert!(!neither.contains(0) && !neither.contains(1));
+    }
+
+    #[test]
+    fn interval_overlaps() {
+        let a = Interval::new(0, 10, Closed::Both);
+        assert!(a.overlaps(&Interval::new(10, 20, Closed::Both)));
+        assert!(!a.overlaps(&Interval::new(10, 20, Closed::Neither)));
+        assert!(a.overlaps(&Interval::new(5, 6, Closed::Neither)));
+        assert!(!a.overlaps(&Interval::new(11, 20, Closed::Both)));
+        assert!(!Interval::new(0, 10, Closed::Left).overlaps(&Interval::new(10, 20, Closed::Left)));
+    }
+
+    #[test]
+    fn column_ops() {
+        let col = IntervalColumn::from_values(
+            vec![Some((0.0, 1.0)), None, Some((1.0, 5.0))],
+            Closed::Right,
+        ).unwrap();
+        assert_eq!(col.contains(1.0), BooleanColumn::from(vec![Some(true), None, Some(false)]));
+        assert_eq!(
+            col.overlaps(&Interval::new(4.0, 8.0, Closed::Both)),
+            BooleanColumn::from(vec![Some(false), None, Some(true)])
+        );
+        assert!(col.is_null(1));
+    }
+
+    #[test]
+    fn values() {
+        let mut col = IntervalColumn::from_values(vec![Some((0, 10)), None], Closed::Right).unwrap();
+        let value = col.get_value(0);
+        assert_eq!(value, Value::Interval(Box::new(Value::Int(0)), Box::new(Value::Int(10)), Closed::Right));
+        assert_eq!(value.to_string(), "(0, 10]");
+        col.push_value(value).unwrap();
+        col.push_value(Value::Null).unwrap();
+        assert_eq!(col.get(2), Some(Some(Interval::new(0, 10, Closed::Right))));
+        assert!(col.is_null(3));
+
+        let left = Value::Interval(Box::new(Value::Int(0)), Box::new(Value::Int(1)), Closed::Left);
+        assert!(col.push_value(left).is_err());
+        let floats = Value::Interval(Box::new(Value::Float(0.5)), Box::new(Value::Int(1)), Closed::Right);
+        assert!(col.push_value(floats).is_err());
+        let backwards = Value::Interval(Box::new(Value::Int(5)), Box::new(Value::Int(1)), Closed::Right);
+        assert!(col.push_value(backwards).is_err());
+        assert!(col.push_value(Value::from("(0, 10]")).is_err());
+        assert_eq!(col.len(), 4);
+    }
+
+    #[test]
+    fn bins() {
+        let bins = IntervalColumn::from_breaks(&[0, 10, 20, 30], Closed::Right).unwrap();
+        assert_eq!(bins.len(), 3);
+        assert_eq!(bins.find(0), None);
+        assert_eq!(bins.find(10), Some(0));
+        assert_eq!(bins.find(11), Some(1));
+        assert_eq!(bins.find(30), Some(2));
+        assert_eq!(bins.find(31), None);
+
+        match IntervalColumn::from_breaks(&[0, 20, 10], Closed::Right) {
+            Err(Error::InvalidInterval { row: 1 }) => (),
+            res => panic!("{:?}", res),
+        }
+        let mut col = IntervalColumn::new(Closed::Left);
+        assert!(col.push(Some((f64::NAN, 1.0))).is_err());
+        assert!(col.push(Some((0.0, f64::NAN))).is_err());
+        assert!(col.is_empty());
+    }
+}
@@ -21,39 +21,238 @@
 // I'll just use the traits more for organizing logic and reducing
 // boilerplate, rather than for placing constraints (esp. on user)
 
-//#[derive(Debug, Clone, PartialEq)]
-//pub enum Dtype {
-//    //Float16,
-//    Float32(Vec<f32>),
-//    Float64(Vec<f64>),
-//    Int8,
-//    Int16,
-//    Int32,
-//    Int64,
-//    Boolean,
-//    Categorical,
-//    String,
-//    Binary,
-//    Timestamp(Unit),
-//    Timedelta(Unit),
-//    Period(Unit),
-//    Interval(Unit),
-//}
-
-//mod float;
+/// Logical type of a column.
+///
+/// Strings have one logical type, whether backed by a dictionary
+/// (StringColumn) or plain offsets (Utf8Column).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dtype {
+    //Float16,
+    Float32,
+    Float64,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Boolean,
+    String,
+    Binary,
+    Timestamp(Unit),
+    Date,
+    Timedelta(Unit),
+    Period(Freq),
+    Interval,
+    Decimal { precision: u8, scale: u8 },
+    /// Lists of values of one (child) dtype.
+    List,
+    /// Named fields, each with its own dtype.
+    Struct,
+}
+
+impl Dtype {
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            *self,
+            Dtype::Float32 | Dtype::Float64 |
+            Dtype::Int8 | Dtype::Int16 | Dtype::Int32 | Dtype::Int64
+        )
+    }
+}
+
+impl fmt::Display for Dtype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Dtype::Float32 => write!(f, "float32"),
+            Dtype::Float64 => write!(f, "float64"),
+            Dtype::Int8 => write!(f, "int8"),
+            Dtype::Int16 => write!(f, "int16"),
+            Dtype::Int32 => write!(f, "int32"),
+            Dtype::Int64 => write!(f, "int64"),
+            Dtype::Boolean => write!(f, "boolean"),
+            Dtype::String => write!(f, "string"),
+            Dtype::Binary => write!(f, "binary"),
+            Dtype::Timestamp(unit) => write!(f, "timestamp[{:?}]", unit),
+            Dtype::Date => write!(f, "date"),
+            Dtype::Timedelta(unit) => write!(f, "timedelta[{:?}]", unit),
+            Dtype::Period(freq) => write!(f, "period[{:?}]", freq),
+            Dtype::Interval => write!(f, "interval"),
+            Dtype::Decimal { precision, scale } => write!(f, "decimal({}, {})", precision, scale),
+            Dtype::List => write!(f, "list"),
+            Dtype::Struct => write!(f, "struct"),
+        }
+    }
+}
+
+// before the column modules, which use its macros
+#[macro_use]
+mod ops;
+#[macro_use]
+mod cumulative;
+
+mod binary;
+mod boolean;
+mod cast;
+mod chunked;
+mod datetime;
+mod decimal;
+mod float;
 mod int;
-//mod string;
+mod interval;
+mod nested;
+mod operator;
+mod period;
+mod rank;
+mod reduce;
+mod series;
+mod string;
+mod unique;
+mod utf8;
+mod value;
+mod window;
 
-use std::ops::Add;
-use std::iter::Sum;
+use std::any::Any;
+use std::fmt;
+use bit_vec::BitVec;
 
-//pub use self::float::{Float32Column};
-pub use self::int::{Int8Column};
-//pub use self::string::{StringColumn};
+use dataframe::DataFrame;
+use error::{Error, Result};
+
+pub use self::binary::{BinaryColumn};
+pub use self::boolean::{BooleanColumn};
+pub use self::chunked::{Chunk, ChunkedColumn, CHUNK_SIZE};
+pub use self::cumulative::Transform;
+pub(crate) use self::cumulative::{transform, Cumulative};
+pub use self::datetime::{parse_freq, DateColumn, TimedeltaColumn, TimestampColumn, Unit};
+pub use self::decimal::{Decimal, DecimalColumn, Rounding, MAX_PRECISION};
+pub use self::float::{Float32Column, Float64Column};
+pub use self::int::{Int8Column, Int16Column, Int32Column, Int64Column};
+pub use self::interval::{Closed, Interval, IntervalBound, IntervalColumn};
+pub use self::nested::{ListColumn, StructColumn};
+pub use self::operator::{combine, Operator};
+pub use self::period::{Freq, PeriodColumn};
+pub use self::rank::{NaOption, RankMethod};
+pub(crate) use self::cast::empty_column;
+pub use self::reduce::{Interpolation, Primitive, PARALLEL_CHUNK};
+pub use self::series::{BitSlice, Series, SeriesChunks};
+pub use self::string::{StringColumn};
+pub use self::utf8::{StringStorage, Utf8Column, DICTIONARY_MAX_RATIO};
+pub use self::value::{Value};
+pub use self::window::{Ewm, Rolling};
 
 /// A Column. It's the logical interface to
 /// to an array(1D collection, column, logical store) of dtypes.
-pub trait Column {}
+///
+/// This is the dynamic interface used by the DataFrame, which only
+/// sees `Box<dyn Column>`. Values go in and out as `Value`s, which
+/// is slow-ish, but lets whole-frame operations (group-by, take, etc.)
+/// be written once instead of per dtype. Anything performance
+/// sensitive should downcast (`as_any`) and work on the struct.
+pub trait Column: fmt::Debug + AsColumn {
+    fn dtype(&self) -> Dtype;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_null(&self, i: usize) -> bool;
+
+    /// Value at row `i`, `Value::Null` if masked. Panics if out of bounds.
+    fn get_value(&self, i: usize) -> Value;
+
+    /// Appends a value, erroring if it doesn't fit the dtype.
+    fn push_value(&mut self, value: Value) -> Result<()>;
+
+    /// Column of the same dtype (and unit, tz, etc.) with no rows.
+    fn new_empty(&self) -> Box<dyn Column>;
+
+    fn clone_box(&self) -> Box<dyn Column>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    /// For columns stored as several others (like ChunkedColumn), the
+    /// rows copied into one plain column. `None` for plain columns.
+    fn flatten(&self) -> Option<Box<dyn Column>> {
+        None
+    }
+
+    /// Gathers rows by index; `None` gives a null row.
+    fn take(&self, indices: &[Option<usize>]) -> Box<dyn Column> {
+        let mut res = self.new_empty();
+        for index in indices {
+            let value = index.map_or(Value::Null, |i| self.get_value(i));
+            // values came from a column of the same dtype
+            res.push_value(value).unwrap();
+        }
+        res
+    }
+
+    /// Converts to another dtype, erroring on the first row which
+    /// can't be converted. See `cast_lenient` to get nulls instead.
+    fn cast(&self, dtype: Dtype) -> Result<Box<dyn Column>> {
+        cast::cast(self.as_column(), dtype, true)
+    }
+
+    /// Converts to another dtype, with nulls for the rows which can't
+    /// be converted. Errors only if `dtype` can't be cast to at all.
+    fn cast_lenient(&self, dtype: Dtype) -> Result<Box<dyn Column>> {
+        cast::cast(self.as_column(), dtype, false)
+    }
+
+    /// Rank of each row's value, 1 for the first in order; see the
+    /// rank module.
+    fn rank(&self, method: RankMethod, ascending: bool, na_option: NaOption) -> Float64Column {
+        rank::rank(self.as_column(), method, ascending, na_option)
+    }
+
+    /// Whether each row's value is one of `values`; null rows stay null.
+    fn is_in(&self, values: &[Value]) -> BooleanColumn {
+        rank::is_in(self.as_column(), values)
+    }
+
+    /// First row and number of rows of each distinct value (null
+    /// counting as one), in order of first appearance.
+    fn distinct_rows(&self) -> Vec<(usize, usize)> {
+        unique::distinct_rows(self.as_column())
+    }
+
+    /// Distinct values in order of first appearance, null included.
+    fn unique(&self) -> Box<dyn Column> {
+        unique::unique(self.as_column())
+    }
+
+    /// Number of distinct values, not counting null.
+    fn n_unique(&self) -> usize {
+        unique::n_unique(self.as_column())
+    }
+
+    /// Frame of the distinct non-null values (`value`) and how many
+    /// rows have each (`count`, or with `normalize` the fraction of
+    /// non-null rows, `proportion`). With `sort`, most common first;
+    /// otherwise in order of first appearance.
+    fn value_counts(&self, sort: bool, normalize: bool) -> DataFrame {
+        unique::value_counts(self.as_column(), sort, normalize)
+    }
+}
+
+/// Upcasts any column to `&dyn Column`, so that the trait's default
+/// methods can hand `self` to code written against the trait object.
+pub trait AsColumn {
+    fn as_column(&self) -> &dyn Column;
+}
+
+impl<T: Column> AsColumn for T {
+    fn as_column(&self) -> &dyn Column {
+        self
+    }
+}
+
+impl Clone for Box<dyn Column> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
 
 // This trait should be everything that has to work
 // directly with the backing data;
@@ -63,7 +262,7 @@ pub trait Column {}
 pub trait DataType {
     type Item;
 
-    fn values(&self) -> Series<Self::Item>
+    fn values(&self) -> Series<'_, Self::Item>
         where Self::Item: Clone;
 
     fn get(&self, index: usize) -> Option<Option<&Self::Item>>;
@@ -80,17 +279,214 @@ pub trait DataType {
 //    fn is_empty
 }
 
+/// Reductions over numeric columns, all skipping nulls and NaN. See
+/// the reduce module for how they accumulate and parallelize.
 pub trait Numeric: DataType {
-    fn sum(&self) -> Self::Item
-        where Self::Item : Sum + Clone
+    /// Raw values and mask (false is null) of the column.
+    fn parts(&self) -> (&[Self::Item], &BitVec);
+
+    /// Sum in a type it can't overflow (i128 for ints, f64 for
+    /// floats); 0 if every value is missing.
+    fn sum(&self) -> <Self::Item as Primitive>::Sum
+        where Self::Item: Primitive
     {
-        self.values()
-            .filter_map(|x| x)
-            .cloned()
-            .sum()
+        let (values, mask) = self.parts();
+        reduce::fold_valid(
+            values,
+            mask,
+            Default::default(),
+            |acc, _, x| acc + x.into(),
+            |a, b| a + b,
+        )
+    }
+
+    /// Product in a wider type (i64 for ints, or f64); 1 if every
+    /// value is missing. Errors if an int product overflows the i64.
+    fn product(&self) -> Result<<Self::Item as Primitive>::Acc>
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        let mul = |a: Option<_>, b: Option<_>| Self::Item::checked_mul(a?, b?);
+        reduce::fold_valid(
+            values,
+            mask,
+            Some(Self::Item::one()),
+            |acc, _, x| mul(acc, Some(x.widen())),
+            mul,
+        ).ok_or_else(|| Error::AggregateOverflow {
+            agg: "product",
+            target: "i64".to_owned(),
+        })
+    }
+
+    /// Number of values which are neither null nor NaN.
+    fn count(&self) -> usize
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        reduce::fold_valid(values, mask, 0, |count, _, _| count + 1, |a, b| a + b)
+    }
+
+    /// Number of null or NaN values.
+    fn null_count(&self) -> usize
+        where Self::Item: Primitive
+    {
+        let (values, _) = self.parts();
+        values.len() - self.count()
+    }
+
+    /// Smallest value, skipping NaN.
+    fn min(&self) -> Option<Self::Item>
+        where Self::Item: Primitive
+    {
+        self.argmin().map(|i| self.parts().0[i])
+    }
+
+    /// Largest value, skipping NaN.
+    fn max(&self) -> Option<Self::Item>
+        where Self::Item: Primitive
+    {
+        self.argmax().map(|i| self.parts().0[i])
+    }
+
+    /// Row of the first smallest value, skipping NaN.
+    fn argmin(&self) -> Option<usize>
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        reduce::arg_extreme(values, mask, |a, b| a < b).map(|(i, _)| i)
+    }
+
+    /// Row of the first largest value, skipping NaN.
+    fn argmax(&self) -> Option<usize>
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        reduce::arg_extreme(values, mask, |a, b| a > b).map(|(i, _)| i)
+    }
+
+    fn mean(&self) -> Option<f64>
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        let (sum, count) = reduce::fold_valid(
+            values,
+            mask,
+            (0.0, 0),
+            |(sum, count), _, x| (sum + x.to_f64(), count + 1),
+            |a, b| (a.0 + b.0, a.1 + b.1),
+        );
+        if count == 0 { None } else { Some(sum / count as f64) }
+    }
+
+    /// Variance with `ddof` delta degrees of freedom: 1 for the
+    /// sample variance (pandas' default), 0 for the population.
+    /// `None` if there are no more than `ddof` values.
+    fn var(&self, ddof: usize) -> Option<f64>
+        where Self::Item: Primitive
+    {
+        let mean = self.mean()?;
+        let count = self.count();
+        if count <= ddof {
+            return None;
+        }
+        // second pass over the deviations, which is more stable
+        // than summing squares
+        let (values, mask) = self.parts();
+        let squares = reduce::fold_valid(
+            values,
+            mask,
+            0.0,
+            |acc, _, x| acc + (x.to_f64() - mean).powi(2),
+            |a, b| a + b,
+        );
+        Some(squares / (count - ddof) as f64)
+    }
+
+    /// Standard deviation; see `var` for `ddof`.
+    fn std(&self, ddof: usize) -> Option<f64>
+        where Self::Item: Primitive
+    {
+        self.var(ddof).map(f64::sqrt)
+    }
+
+    fn median(&self) -> Option<f64>
+        where Self::Item: Primitive
+    {
+        self.quantile(0.5, Interpolation::Linear)
+    }
+
+    /// Value below which a fraction `q` of the values fall, skipping
+    /// NaN. Uses partial selection, not a sort. Panics unless
+    /// `0 <= q <= 1`.
+    fn quantile(&self, q: f64, interpolation: Interpolation) -> Option<f64>
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        reduce::quantile(values, mask, q, interpolation)
+    }
+
+    /// Windows of the last `window` rows, which need `min_periods`
+    /// valid values; see the window module. Panics if `window` is 0.
+    fn rolling(&self, window: usize, min_periods: usize) -> Rolling<'_, Self::Item>
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        Rolling::new(values, mask, window, min_periods)
+    }
+
+    /// Windows of every row so far.
+    fn expanding(&self) -> Rolling<'_, Self::Item>
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        Rolling::expanding(values, mask)
+    }
+
+    /// Exponentially weighted windows, with smoothing factor `alpha`.
+    /// Panics unless `0 < alpha <= 1`.
+    fn ewm(&self, alpha: f64) -> Ewm<'_, Self::Item>
+        where Self::Item: Primitive
+    {
+        let (values, mask) = self.parts();
+        Ewm::new(values, mask, alpha)
     }
 }
 
+/// Code generic over the numeric column types, run on a dynamic
+/// column by `visit_numeric`.
+pub(crate) trait NumericVisitor {
+    type Output;
+
+    fn visit<C>(self, column: &C) -> Self::Output
+        where C: Numeric + Cumulative,
+              C::Item: Primitive;
+}
+
+/// Runs `visitor` on `column` as its concrete numeric type, or `None`
+/// if it isn't numeric. Chunked columns are flattened first, and
+/// decimals are read as Float64.
+pub(crate) fn visit_numeric<V: NumericVisitor>(column: &dyn Column, visitor: V) -> Option<V::Output> {
+    if let Some(flat) = column.flatten() {
+        return visit_numeric(&*flat, visitor);
+    }
+    let any = column.as_any();
+    if let Some(decimals) = any.downcast_ref::<DecimalColumn>() {
+        return Some(visitor.visit(&decimals.to_f64()));
+    }
+    macro_rules! try_numeric {
+        ($($name:ident),*) => {
+            $(
+                if let Some(column) = any.downcast_ref::<$name>() {
+                    return Some(visitor.visit(column));
+                }
+            )*
+        };
+    }
+    try_numeric!(Int8Column, Int16Column, Int32Column, Int64Column, Float32Column, Float64Column);
+    None
+}
+
 /// For DataType methods that use &mut, which means that they
 /// can't be implemented on &Column types, only Column and &mut
 /// Column
@@ -100,33 +496,3 @@ pub trait DataTypeMut: DataType {
         Self: Sized,
         F: Fn(Self::Item) -> Self::Item + ::std::marker::Sync;
 }
-
-// TODO make sure Series works for other data types.
-/// Iterator for column types.
-pub struct Series<'a, T: 'a + Clone> {
-    values: &'a DataType<Item=T>,
-    index: usize,
-}
-
-impl<'a, T> Series<'a, T>
-    where T: Clone
-{
-    pub fn new(values: &'a DataType<Item=T>) -> Self {
-        Series {
-            values: values,
-            index: 0,
-        }
-    }
-}
-
-impl<'a, T> Iterator for Series<'a, T>
-    where T: Clone
-{
-    type Item = Option<&'a T>;
-
-    fn next(&mut self) -> Option<Option<&'a T>> {
-        let res = self.values.get(self.index);
-        self.index += 1;
-        res
-    }
-}
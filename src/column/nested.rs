@@ -0,0 +1,437 @@
+//! Nested dtype columns: lists and structs
+//!
+//! Same layout as arrow. A list column is one child column holding
+//! the elements of every list back to back, plus offsets marking
+//! where each row's list starts and ends. A struct column is a set
+//! of named child columns, all as long as the struct column.
+//!
+//! Both have their own validity mask, separate from their children:
+//! a null list is different from an empty list or a list of nulls.
+//!
+//! The children are `Box<dyn Column>`, so they can be any column,
+//! including other lists and structs. Since `Dtype` is a plain
+//! Copy enum, it doesn't describe the children; look at them directly.
+
+use bit_vec::BitVec;
+use std::any::Any;
+
+use error::{Error, Result};
+use super::{Column, Dtype, Int64Column, Value};
+
+#[derive(Debug, Clone)]
+pub struct ListColumn {
+    // row i is values[offsets[i]..offsets[i + 1]]
+    offsets: Vec<usize>,
+    values: Box<dyn Column>,
+    // false in the mask maps to null, which has an empty range in values.
+    mask: BitVec,
+}
+
+impl Column for ListColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::List
+    }
+
+    fn len(&self) -> usize {
+        self.mask.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        if !self.mask[i] {
+            return Value::Null;
+        }
+        Value::List(
+            (self.offsets[i]..self.offsets[i + 1])
+                .map(|j| self.values.get_value(j))
+                .collect()
+        )
+    }
+
+    /// Lists have each element pushed to the child column.
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push_null(),
+            Value::List(items) => {
+                // check every element fits before pushing any, so a
+                // bad list doesn't leave a partial one in the child
+                let mut scratch = self.values.new_empty();
+                for item in &items {
+                    scratch.push_value(item.clone())?;
+                }
+                for item in items {
+                    self.values.push_value(item)?;
+                }
+                self.offsets.push(self.values.len());
+                self.mask.push(true);
+            },
+            value => return Err(Error::TypeMismatch {
+                expected: Dtype::List.to_string(),
+                found: value.kind().to_owned(),
+            }),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(ListColumn::new(self.values.new_empty()))
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl ListColumn {
+    /// Empty list column, with elements of the same dtype as `values`
+    /// (which only serves as a template; its rows are ignored).
+    pub fn new(values: Box<dyn Column>) -> Self {
+        ListColumn {
+            offsets: vec![0],
+            values: values.new_empty(),
+            mask: BitVec::new(),
+        }
+    }
+
+    /// From the child column and offsets, which must start at 0, never
+    /// decrease, and end at the length of `values`. Rows with `false`
+    /// in `mask` are null and must be empty; `None` means no nulls.
+    pub fn from_parts(
+        offsets: Vec<usize>,
+        values: Box<dyn Column>,
+        mask: Option<BitVec>
+    ) -> Result<Self> {
+        let len = offsets.len().saturating_sub(1);
+        let mask = mask.unwrap_or_else(|| BitVec::from_elem(len, true));
+        if mask.len() != len {
+            return Err(Error::LengthMismatch { expected: len, found: mask.len() });
+        }
+        if offsets.last() != Some(&values.len()) {
+            return Err(Error::LengthMismatch {
+                expected: offsets.last().cloned().unwrap_or(0),
+                found: values.len(),
+            });
+        }
+        if offsets[0] != 0 {
+            return Err(Error::InvalidOffsets { row: 0 });
+        }
+        let bad_row = offsets.windows(2)
+            .zip(mask.iter())
+            .position(|(w, valid)| w[0] > w[1] || (!valid && w[0] != w[1]));
+        if let Some(row) = bad_row {
+            return Err(Error::InvalidOffsets { row });
+        }
+        Ok(ListColumn {
+            offsets,
+            values,
+            mask,
+        })
+    }
+
+    pub fn push_null(&mut self) {
+        self.offsets.push(self.values.len());
+        self.mask.push(false);
+    }
+
+    pub fn len(&self) -> usize {
+        self.mask.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mask.is_empty()
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        !self.mask.get(i).unwrap_or(true)
+    }
+
+    /// The child column, with the elements of all lists.
+    pub fn values(&self) -> &dyn Column {
+        &*self.values
+    }
+
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// Elements of the list at row `i`, as a column. The outer option
+    /// is for out of bounds, the inner for a null list.
+    pub fn get(&self, i: usize) -> Option<Option<Box<dyn Column>>> {
+        self.mask.get(i).map(|valid| {
+            if valid {
+                let rows: Vec<_> = (self.offsets[i]..self.offsets[i + 1]).map(Some).collect();
+                Some(self.values.take(&rows))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Number of elements in each list.
+    pub fn lengths(&self) -> Int64Column {
+        self.offsets.windows(2)
+            .zip(self.mask.iter())
+            .map(|(w, valid)| if valid { Some((w[1] - w[0]) as i64) } else { None })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StructColumn {
+    names: Vec<String>,
+    fields: Vec<Box<dyn Column>>,
+    // false in the mask maps to null. Fields still have a row
+    // for null structs, which is ignored.
+    mask: BitVec,
+}
+
+impl Column for StructColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::Struct
+    }
+
+    fn len(&self) -> usize {
+        self.mask.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        if !self.mask[i] {
+            return Value::Null;
+        }
+        Value::Struct(
+            self.names.iter()
+                .zip(&self.fields)
+                .map(|(name, field)| (name.clone(), field.get_value(i)))
+                .collect()
+        )
+    }
+
+    /// Fields are matched by name; missing fields are null, and
+    /// unknown fields are an error.
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        let mut row = match value {
+            Value::Null => {
+                self.push_null();
+                return Ok(());
+            },
+            Value::Struct(row) => row,
+            value => return Err(Error::TypeMismatch {
+                expected: Dtype::Struct.to_string(),
+                found: value.kind().to_owned(),
+            }),
+        };
+        if let Some((name, _)) = row.iter().find(|(name, _)| !self.names.contains(name)) {
+            return Err(Error::ColumnNotFound(name.clone()));
+        }
+
+        let mut values = Vec::with_capacity(self.fields.len());
+        for name in &self.names {
+            let value = row.iter()
+                .position(|(n, _)| n == name)
+                .map_or(Value::Null, |i| row.swap_remove(i).1);
+            values.push(value);
+        }
+        // check every value fits before pushing any, so the fields
+        // stay the same length on error
+        for (field, value) in self.fields.iter().zip(&values) {
+            field.new_empty().push_value(value.clone())?;
+        }
+        for (field, value) in self.fields.iter_mut().zip(values) {
+            field.push_value(value)?;
+        }
+        self.mask.push(true);
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(StructColumn {
+            names: self.names.clone(),
+            fields: self.fields.iter().map(|field| field.new_empty()).collect(),
+            mask: BitVec::new(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn take(&self, indices: &[Option<usize>]) -> Box<dyn Column> {
+        Box::new(StructColumn {
+            names: self.names.clone(),
+            fields: self.fields.iter().map(|field| field.take(indices)).collect(),
+            mask: indices.iter().map(|i| i.is_some_and(|i| self.mask[i])).collect(),
+        })
+    }
+}
+
+impl StructColumn {
+    /// From (name, field) pairs, which must all be the same length.
+    /// `None` for `mask` means no nulls.
+    pub fn new(fields: Vec<(&str, Box<dyn Column>)>, mask: Option<BitVec>) -> Result<Self> {
+        let len = fields.first().map_or(0, |(_, field)| field.len());
+        let mask = mask.unwrap_or_else(|| BitVec::from_elem(len, true));
+        for (_, field) in &fields {
+            if field.len() != mask.len() {
+                return Err(Error::LengthMismatch { expected: mask.len(), found: field.len() });
+            }
+        }
+        Ok(StructColumn {
+            names: fields.iter().map(|&(name, _)| name.to_owned()).collect(),
+            fields: fields.into_iter().map(|(_, field)| field).collect(),
+            mask,
+        })
+    }
+
+    pub fn push_null(&mut self) {
+        for field in &mut self.fields {
+            // any column takes a null
+            field.push_value(Value::Null).unwrap();
+        }
+        self.mask.push(false);
+    }
+
+    pub fn len(&self) -> usize {
+        self.mask.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mask.is_empty()
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        !self.mask.get(i).unwrap_or(true)
+    }
+
+    pub fn field_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn field(&self, name: &str) -> Result<&dyn Column> {
+        self.names.iter()
+            .position(|n| n == name)
+            .map(|i| &*self.fields[i])
+            .ok_or_else(|| Error::ColumnNotFound(name.to_owned()))
+    }
+
+    /// Iterates over (name, field) pairs, in order. Fields don't
+    /// know about nulls in the struct; see `unnest` for that.
+    pub fn fields(&self) -> impl Iterator<Item=(&str, &dyn Column)> {
+        self.names.iter()
+            .map(|name| name.as_str())
+            .zip(self.fields.iter().map(|field| &**field))
+    }
+
+    /// The fields, with rows null wherever the struct is null.
+    pub fn unnest(&self) -> Vec<(String, Box<dyn Column>)> {
+        let rows: Vec<_> = (0..self.len())
+            .map(|i| if self.mask[i] { Some(i) } else { None })
+            .collect();
+        self.names.iter()
+            .cloned()
+            .zip(self.fields.iter().map(|field| field.take(&rows)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Int8Column, StringColumn};
+
+    fn lists() -> ListColumn {
+        ListColumn::from_parts(
+            vec![0, 2, 2, 2, 3],
+            Box::new(Int8Column::from(vec![Some(1), None, Some(3)])),
+            Some(vec![true, true, false, true].into_iter().collect()),
+        ).unwrap()
+    }
+
+    #[test]
+    fn list() {
+        let col = lists();
+        assert_eq!(col.len(), 4);
+        assert_eq!(col.get_value(0), Value::List(vec![Value::Int(1), Value::Null]));
+        assert_eq!(col.get_value(1), Value::List(vec![]));
+        assert_eq!(col.get_value(2), Value::Null);
+        assert_eq!(col.lengths(), Int64Column::from(vec![Some(2), Some(0), None, Some(1)]));
+        assert_eq!(col.get(3).unwrap().unwrap().get_value(0), Value::Int(3));
+        assert!(col.get(2).unwrap().is_none());
+
+        let taken = col.take(&[Some(3), None, Some(0)]);
+        assert_eq!(taken.get_value(0), Value::List(vec![Value::Int(3)]));
+        assert!(taken.is_null(1));
+        assert_eq!(taken.get_value(2), col.get_value(0));
+
+        assert!(ListColumn::from_parts(vec![0, 2], Box::new(Int8Column::from(vec![1])), None).is_err());
+        let values = || Box::new(Int8Column::from(vec![1, 2, 3]));
+        match ListColumn::from_parts(vec![0, 2, 1, 3], values(), None) {
+            Err(Error::InvalidOffsets { row: 1 }) => (),
+            res => panic!("{:?}", res),
+        }
+        let mut mask = BitVec::from_elem(2, true);
+        mask.set(0, false);
+        match ListColumn::from_parts(vec![0, 1, 3], values(), Some(mask)) {
+            Err(Error::InvalidOffsets { row: 0 }) => (),
+            res => panic!("{:?}", res),
+        }
+        assert!(ListColumn::from_parts(vec![1, 3], values(), None).is_err());
+    }
+
+    #[test]
+    fn list_push() {
+        let mut col = ListColumn::new(Box::new(StringColumn::new()));
+        col.push_value(Value::List(vec!["a".into(), "b".into()])).unwrap();
+        assert!(col.push_value(Value::List(vec!["c".into(), Value::Int(1)])).is_err());
+        col.push_null();
+        assert_eq!(col.len(), 2);
+        assert_eq!(col.values().len(), 2);
+        assert_eq!(col.offsets(), &[0, 2, 2]);
+    }
+
+    #[test]
+    fn structs() {
+        let mut col = StructColumn::new(vec![
+            ("a", Box::new(Int8Column::from(vec![1, 2])) as Box<dyn Column>),
+            ("b", lists().take(&[Some(0), Some(3)])),
+        ], Some(vec![true, false].into_iter().collect())).unwrap();
+        assert_eq!(col.get_value(0), Value::Struct(vec![
+            ("a".to_owned(), Value::Int(1)),
+            ("b".to_owned(), Value::List(vec![Value::Int(1), Value::Null])),
+        ]));
+        assert_eq!(col.get_value(1), Value::Null);
+
+        col.push_value(Value::Struct(vec![("b".to_owned(), Value::List(vec![]))])).unwrap();
+        assert_eq!(col.field("a").unwrap().get_value(2), Value::Null);
+        assert!(col.push_value(Value::Struct(vec![("c".to_owned(), Value::Null)])).is_err());
+        assert!(col.push_value(Value::Struct(vec![("a".to_owned(), Value::from("x"))])).is_err());
+        assert_eq!(col.len(), 3);
+        assert_eq!(col.field("a").unwrap().len(), 3);
+
+        let fields = col.unnest();
+        assert_eq!(fields[0].0, "a");
+        // masked by the struct's nulls
+        assert_eq!(fields[0].1.get_value(1), Value::Null);
+        assert_eq!(fields[0].1.get_value(0), Value::Int(1));
+
+        assert!(StructColumn::new(vec![
+            ("a", Box::new(Int8Column::from(vec![1, 2])) as Box<dyn Column>),
+            ("b", Box::new(Int8Column::from(vec![1]))),
+        ], None).is_err());
+    }
+}
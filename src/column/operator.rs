@@ -0,0 +1,193 @@
+//! Binary operators between dynamic columns
+//!
+//! The typed operators in `ops` need both sides to be the same column
+//! type. `combine` works between any two columns through the dynamic
+//! interface (`Value`s), for frames and expressions: arithmetic is on
+//! ints and floats, ints giving an Int64 column (wrapping on overflow,
+//! null when dividing by zero, like the int column operators) and
+//! anything with a float a Float64 column. Comparisons give boolean
+//! columns, and `&` and `|` need boolean columns. Nulls propagate
+//! through every operator.
+
+use error::{Error, Result};
+use super::{empty_column, Column, Dtype, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+}
+
+impl Operator {
+    pub fn symbol(&self) -> &'static str {
+        match *self {
+            Operator::Add => "+",
+            Operator::Sub => "-",
+            Operator::Mul => "*",
+            Operator::Div => "/",
+            Operator::Rem => "%",
+            Operator::Eq => "==",
+            Operator::NotEq => "!=",
+            Operator::Lt => "<",
+            Operator::LtEq => "<=",
+            Operator::Gt => ">",
+            Operator::GtEq => ">=",
+            Operator::And => "&",
+            Operator::Or => "|",
+        }
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(*self, Operator::Add | Operator::Sub | Operator::Mul | Operator::Div | Operator::Rem)
+    }
+
+    pub fn is_logical(&self) -> bool {
+        matches!(*self, Operator::And | Operator::Or)
+    }
+
+    /// Dtype of `left op right`, or an error if `op` doesn't apply.
+    pub(crate) fn result_dtype(&self, left: Dtype, right: Dtype) -> Result<Dtype> {
+        if self.is_arithmetic() {
+            if !left.is_numeric() || !right.is_numeric() {
+                return Err(Error::TypeMismatch {
+                    expected: format!("numeric columns for {}", self.symbol()),
+                    found: format!("{} and {}", left, right),
+                });
+            }
+            return Ok(if is_int(left) && is_int(right) { Dtype::Int64 } else { Dtype::Float64 });
+        }
+        if self.is_logical() {
+            for &found in &[left, right] {
+                if found != Dtype::Boolean {
+                    return Err(Error::TypeMismatch {
+                        expected: Dtype::Boolean.to_string(),
+                        found: found.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(Dtype::Boolean)
+    }
+
+    /// Applies the operator to one pair of values, for a `dtype` result
+    /// from `result_dtype`.
+    pub(crate) fn apply(&self, a: Value, b: Value, dtype: Dtype) -> Result<Value> {
+        let op = *self;
+        if a.is_null() || b.is_null() {
+            return Ok(Value::Null);
+        }
+        let res = match op {
+            Operator::And | Operator::Or => match (a, b) {
+                (Value::Bool(a), Value::Bool(b)) => Value::Bool(if op == Operator::And { a && b } else { a || b }),
+                _ => Value::Null,
+            },
+            _ if dtype == Dtype::Int64 => {
+                // both sides are ints
+                let (a, b) = (a.as_i64().unwrap(), b.as_i64().unwrap());
+                match op {
+                    Operator::Add => Value::Int(a.wrapping_add(b)),
+                    Operator::Sub => Value::Int(a.wrapping_sub(b)),
+                    Operator::Mul => Value::Int(a.wrapping_mul(b)),
+                    Operator::Div if b == 0 => Value::Null,
+                    Operator::Div => Value::Int(a.wrapping_div(b)),
+                    Operator::Rem if b == 0 => Value::Null,
+                    _ => Value::Int(a.wrapping_rem(b)),
+                }
+            },
+            _ if dtype == Dtype::Float64 => {
+                let (a, b) = (a.as_f64().unwrap(), b.as_f64().unwrap());
+                match op {
+                    Operator::Add => Value::Float(a + b),
+                    Operator::Sub => Value::Float(a - b),
+                    Operator::Mul => Value::Float(a * b),
+                    Operator::Div => Value::Float(a / b),
+                    _ => Value::Float(a % b),
+                }
+            },
+            _ => {
+                let ord = match (a.as_f64(), b.as_f64()) {
+                    // NaN compares false, rather than being an error
+                    (Some(x), Some(y)) => x.partial_cmp(&y),
+                    _ => Some(a.partial_cmp(&b).ok_or_else(|| Error::TypeMismatch {
+                        expected: format!("comparable values for {}", op.symbol()),
+                        found: format!("{} and {}", a.kind(), b.kind()),
+                    })?),
+                };
+                Value::Bool(match ord {
+                    Some(ord) => match op {
+                        Operator::Eq => ord.is_eq(),
+                        Operator::NotEq => ord.is_ne(),
+                        Operator::Lt => ord.is_lt(),
+                        Operator::LtEq => ord.is_le(),
+                        Operator::Gt => ord.is_gt(),
+                        _ => ord.is_ge(),
+                    },
+                    None => op == Operator::NotEq,
+                })
+            },
+        };
+        Ok(res)
+    }
+}
+
+fn is_int(dtype: Dtype) -> bool {
+    matches!(dtype, Dtype::Int8 | Dtype::Int16 | Dtype::Int32 | Dtype::Int64)
+}
+
+/// `left op right`, row by row. The columns must be the same length.
+pub fn combine(left: &dyn Column, op: Operator, right: &dyn Column) -> Result<Box<dyn Column>> {
+    let dtype = op.result_dtype(left.dtype(), right.dtype())?;
+    if left.len() != right.len() {
+        return Err(Error::LengthMismatch { expected: left.len(), found: right.len() });
+    }
+    // Int64, Float64 and Boolean columns can all be built from scratch
+    let mut res = empty_column(dtype).unwrap();
+    for i in 0..left.len() {
+        res.push_value(op.apply(left.get_value(i), right.get_value(i), dtype)?)?;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{BooleanColumn, Float64Column, Int8Column, Int64Column, StringColumn};
+
+    #[test]
+    fn arithmetic() {
+        let a = Int8Column::from(vec![Some(100), None, Some(7)]);
+        let b = Int64Column::from(vec![100, 1, 0]);
+        let res = combine(&a, Operator::Add, &b).unwrap();
+        assert_eq!(res.as_any().downcast_ref::<Int64Column>().unwrap(), &Int64Column::from(vec![Some(200), None, Some(7)]));
+        let res = combine(&a, Operator::Div, &b).unwrap();
+        assert_eq!(res.as_any().downcast_ref::<Int64Column>().unwrap(), &Int64Column::from(vec![Some(1), None, None]));
+
+        let f = Float64Column::from(vec![0.5, 0.5, 0.5]);
+        let res = combine(&a, Operator::Mul, &f).unwrap();
+        assert_eq!(res.as_any().downcast_ref::<Float64Column>().unwrap(), &Float64Column::from(vec![Some(50.0), None, Some(3.5)]));
+    }
+
+    #[test]
+    fn comparisons_and_errors() {
+        let a = Int8Column::from(vec![1, 2]);
+        let f = Float64Column::from(vec![1.0, f64::NAN]);
+        let res = combine(&a, Operator::LtEq, &f).unwrap();
+        assert_eq!(res.as_any().downcast_ref::<BooleanColumn>().unwrap(), &BooleanColumn::from(vec![true, false]));
+
+        let s = StringColumn::from(vec!["a", "b"]);
+        assert!(combine(&a, Operator::Add, &s).is_err());
+        assert!(combine(&a, Operator::And, &a).is_err());
+        assert!(combine(&a, Operator::Eq, &Int8Column::from(vec![1])).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! Arithmetic and comparison kernels
+//!
+//! Binary operators work between two columns of the same type, or a
+//! column and a scalar, through `std::ops` on references:
+//! `&a + &b`, `&a * 2`. Nulls propagate: the result is null wherever
+//! either input is, so the result mask is the AND of the input masks.
+//! Comparisons (`a.lt(&b)`, `a.gt_scalar(0)`) give boolean columns,
+//! which combine with `&`, `|` and `!`.
+//!
+//! Operators between two columns panic if the lengths differ, same
+//! as indexing out of bounds; the operators can't return a Result.
+//!
+//! Int operators wrap on overflow, like numpy, and division or
+//! remainder by zero gives null. The `checked_*` methods give null on
+//! overflow instead, and there are `wrapping_*` and `saturating_*`
+//! methods to be explicit.
+//!
+//! These are macros, invoked from the int and float column macros,
+//! so that the kernels work directly on the private values and mask.
+
+use bit_vec::BitVec;
+
+/// Rows which are valid in both masks.
+pub(crate) fn and_masks(a: &BitVec, b: &BitVec) -> BitVec {
+    assert_eq!(a.len(), b.len(), "columns must be the same length");
+    let mut mask = a.clone();
+    mask.intersect(b);
+    mask
+}
+
+/// Comparisons and the kernels shared by ints and floats.
+macro_rules! numeric_ops {
+    ($name:ident, $t:ty) => {
+        impl $name {
+            /// Applies `f` to each pair of valid rows. Result is null
+            /// where either row is null, or where `f` gives `None`.
+            fn zip_with<F>(&self, other: &$name, f: F) -> $name
+                where F: Fn($t, $t) -> Option<$t>
+            {
+                let mask = ::column::ops::and_masks(&self.mask, &other.mask);
+                self.values.iter()
+                    .zip(&other.values)
+                    .zip(mask.iter())
+                    .map(|((&a, &b), valid)| if valid { f(a, b) } else { None })
+                    .collect::<Vec<_>>()
+                    .into()
+            }
+
+            /// Applies `f` to each valid row and a scalar.
+            fn map_scalar<F>(&self, x: $t, f: F) -> $name
+                where F: Fn($t, $t) -> Option<$t>
+            {
+                self.values.iter()
+                    .zip(self.mask.iter())
+                    .map(|(&a, valid)| if valid { f(a, x) } else { None })
+                    .collect::<Vec<_>>()
+                    .into()
+            }
+
+            fn compare<F>(&self, other: &$name, f: F) -> ::column::BooleanColumn
+                where F: Fn(&$t, &$t) -> bool
+            {
+                let mask = ::column::ops::and_masks(&self.mask, &other.mask);
+                // false under nulls, so equal columns have equal bits
+                let values = self.values.iter()
+                    .zip(&other.values)
+                    .zip(mask.iter())
+                    .map(|((a, b), valid)| valid && f(a, b))
+                    .collect();
+                ::column::BooleanColumn::from_parts(values, mask)
+            }
+
+            fn compare_scalar<F>(&self, x: $t, f: F) -> ::column::BooleanColumn
+                where F: Fn(&$t, &$t) -> bool
+            {
+                let values = self.values.iter()
+                    .zip(self.mask.iter())
+                    .map(|(a, valid)| valid && f(a, &x))
+                    .collect();
+                ::column::BooleanColumn::from_parts(values, self.mask.clone())
+            }
+
+            pub fn equal(&self, other: &$name) -> ::column::BooleanColumn {
+                self.compare(other, PartialEq::eq)
+            }
+
+            pub fn not_equal(&self, other: &$name) -> ::column::BooleanColumn {
+                self.compare(other, PartialEq::ne)
+            }
+
+            pub fn lt(&self, other: &$name) -> ::column::BooleanColumn {
+                self.compare(other, PartialOrd::lt)
+            }
+
+            pub fn lt_eq(&self, other: &$name) -> ::column::BooleanColumn {
+                self.compare(other, PartialOrd::le)
+            }
+
+            pub fn gt(&self, other: &$name) -> ::column::BooleanColumn {
+                self.compare(other, PartialOrd::gt)
+            }
+
+            pub fn gt_eq(&self, other: &$name) -> ::column::BooleanColumn {
+                self.compare(other, PartialOrd::ge)
+            }
+
+            pub fn equal_scalar(&self, x: $t) -> ::column::BooleanColumn {
+                self.compare_scalar(x, PartialEq::eq)
+            }
+
+            pub fn not_equal_scalar(&self, x: $t) -> ::column::BooleanColumn {
+                self.compare_scalar(x, PartialEq::ne)
+            }
+
+            pub fn lt_scalar(&self, x: $t) -> ::column::BooleanColumn {
+                self.compare_scalar(x, PartialOrd::lt)
+            }
+
+            pub fn lt_eq_scalar(&self, x: $t) -> ::column::BooleanColumn {
+                self.compare_scalar(x, PartialOrd::le)
+            }
+
+            pub fn gt_scalar(&self, x: $t) -> ::column::BooleanColumn {
+                self.compare_scalar(x, PartialOrd::gt)
+            }
+
+            pub fn gt_eq_scalar(&self, x: $t) -> ::column::BooleanColumn {
+                self.compare_scalar(x, PartialOrd::ge)
+            }
+        }
+    };
+}
+
+/// One `std::ops` trait, for column op column and column op scalar.
+macro_rules! binary_op {
+    ($name:ident, $t:ty, $trait:ident, $method:ident, $f:expr) => {
+        impl<'a> ::std::ops::$trait<&'a $name> for &'a $name {
+            type Output = $name;
+
+            fn $method(self, other: &'a $name) -> $name {
+                self.zip_with(other, $f)
+            }
+        }
+
+        impl<'a> ::std::ops::$trait<$t> for &'a $name {
+            type Output = $name;
+
+            fn $method(self, x: $t) -> $name {
+                self.map_scalar(x, $f)
+            }
+        }
+    };
+}
+
+macro_rules! float_ops {
+    ($name:ident, $t:ty) => {
+        numeric_ops!($name, $t);
+        binary_op!($name, $t, Add, add, |a: $t, b: $t| Some(a + b));
+        binary_op!($name, $t, Sub, sub, |a: $t, b: $t| Some(a - b));
+        binary_op!($name, $t, Mul, mul, |a: $t, b: $t| Some(a * b));
+        binary_op!($name, $t, Div, div, |a: $t, b: $t| Some(a / b));
+        binary_op!($name, $t, Rem, rem, |a: $t, b: $t| Some(a % b));
+    };
+}
+
+macro_rules! int_ops {
+    ($name:ident, $t:ty) => {
+        numeric_ops!($name, $t);
+        binary_op!($name, $t, Add, add, |a: $t, b: $t| Some(a.wrapping_add(b)));
+        binary_op!($name, $t, Sub, sub, |a: $t, b: $t| Some(a.wrapping_sub(b)));
+        binary_op!($name, $t, Mul, mul, |a: $t, b: $t| Some(a.wrapping_mul(b)));
+        binary_op!($name, $t, Div, div, |a: $t, b: $t| if b == 0 { None } else { Some(a.wrapping_div(b)) });
+        binary_op!($name, $t, Rem, rem, |a: $t, b: $t| if b == 0 { None } else { Some(a.wrapping_rem(b)) });
+
+        impl $name {
+            /// Null where the sum overflows.
+            pub fn checked_add(&self, other: &$name) -> $name {
+                self.zip_with(other, <$t>::checked_add)
+            }
+
+            /// Null where the difference overflows.
+            pub fn checked_sub(&self, other: &$name) -> $name {
+                self.zip_with(other, <$t>::checked_sub)
+            }
+
+            /// Null where the product overflows.
+            pub fn checked_mul(&self, other: &$name) -> $name {
+                self.zip_with(other, <$t>::checked_mul)
+            }
+
+            /// Null where dividing by zero, or the quotient overflows.
+            pub fn checked_div(&self, other: &$name) -> $name {
+                self.zip_with(other, <$t>::checked_div)
+            }
+
+            /// Null where dividing by zero, or the remainder overflows.
+            pub fn checked_rem(&self, other: &$name) -> $name {
+                self.zip_with(other, <$t>::checked_rem)
+            }
+
+            pub fn wrapping_add(&self, other: &$name) -> $name {
+                self + other
+            }
+
+            pub fn wrapping_sub(&self, other: &$name) -> $name {
+                self - other
+            }
+
+            pub fn wrapping_mul(&self, other: &$name) -> $name {
+                self * other
+            }
+
+            pub fn saturating_add(&self, other: &$name) -> $name {
+                self.zip_with(other, |a, b| Some(a.saturating_add(b)))
+            }
+
+            pub fn saturating_sub(&self, other: &$name) -> $name {
+                self.zip_with(other, |a, b| Some(a.saturating_sub(b)))
+            }
+
+            pub fn saturating_mul(&self, other: &$name) -> $name {
+                self.zip_with(other, |a, b| Some(a.saturating_mul(b)))
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use column::{BooleanColumn, Float64Column, Int8Column, Int64Column};
+
+    #[test]
+    fn int_arithmetic() {
+        let a = Int8Column::from(vec![Some(100), Some(7), None, Some(-128)]);
+        let b = Int8Column::from(vec![Some(100), Some(0), Some(1), Some(-1)]);
+        assert_eq!(&a + &b, Int8Column::from(vec![Some(-56), Some(7), None, Some(127)]));
+        assert_eq!(&a / &b, Int8Column::from(vec![Some(1), None, None, Some(-128)]));
+        assert_eq!(&a % &b, Int8Column::from(vec![Some(0), None, None, Some(0)]));
+        assert_eq!(&a * 2, Int8Column::from(vec![Some(-56), Some(14), None, Some(0)]));
+        assert_eq!(&a - 1, Int8Column::from(vec![Some(99), Some(6), None, Some(127)]));
+
+        assert_eq!(a.checked_add(&b), Int8Column::from(vec![None, Some(7), None, None]));
+        assert_eq!(a.checked_div(&b), Int8Column::from(vec![Some(1), None, None, None]));
+        assert_eq!(a.saturating_add(&b), Int8Column::from(vec![Some(127), Some(7), None, Some(-128)]));
+        assert_eq!(a.saturating_mul(&b), Int8Column::from(vec![Some(127), Some(0), None, Some(127)]));
+        assert_eq!(a.wrapping_add(&b), &a + &b);
+    }
+
+    #[test]
+    fn float_arithmetic() {
+        let a = Float64Column::from(vec![Some(1.5), None, Some(3.0)]);
+        let b = Float64Column::from(vec![Some(0.5), Some(1.0), Some(0.0)]);
+        assert_eq!(&a * &b, Float64Column::from(vec![Some(0.75), None, Some(0.0)]));
+        assert_eq!(&a / &b, Float64Column::from(vec![Some(3.0), None, Some(f64::INFINITY)]));
+        assert_eq!(&a + 1.0, Float64Column::from(vec![Some(2.5), None, Some(4.0)]));
+    }
+
+    #[test]
+    fn comparisons() {
+        let a = Int64Column::from(vec![Some(1), Some(2), None]);
+        let b = Int64Column::from(vec![Some(2), Some(2), Some(2)]);
+        assert_eq!(a.lt(&b), BooleanColumn::from(vec![Some(true), Some(false), None]));
+        assert_eq!(a.equal(&b), BooleanColumn::from(vec![Some(false), Some(true), None]));
+        assert_eq!(a.gt_eq_scalar(2), BooleanColumn::from(vec![Some(false), Some(true), None]));
+        assert_eq!(a.not_equal_scalar(2), BooleanColumn::from(vec![Some(true), Some(false), None]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn length_mismatch() {
+        let _ = &Int8Column::from(vec![1]) + &Int8Column::from(vec![1, 2]);
+    }
+}
@@ -0,0 +1,351 @@
+//! Period dtype column
+//!
+//! A period is a span of time at some frequency, like the month
+//! 2017-06 or the quarter 2017Q2. Physically it's an i64 ordinal: the
+//! number of whole periods between the unix epoch and the start of
+//! the period, so periods at the same frequency sort and compare as ints.
+//!
+//! Quarters and years can start on any month, for fiscal calendars.
+//! Fiscal periods are named by the calendar year they start in.
+
+use bit_vec::BitVec;
+use std::any::Any;
+
+use error::{Error, Result};
+use super::datetime::{civil_from_days, days_from_civil, SECONDS_PER_DAY};
+use super::{Column, DataType, DataTypeMut, Dtype, Series, TimestampColumn, Unit, Value};
+
+/// Frequency of a period. `start_month` is 1 for calendar quarters
+/// and years, 4 for a fiscal year starting in April, etc., and
+/// columns reject a freq with a `start_month` outside 1 to 12.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Freq {
+    Day,
+    /// Weeks start on Monday.
+    Week,
+    Month,
+    Quarter { start_month: u32 },
+    Year { start_month: u32 },
+}
+
+impl Freq {
+    /// Calendar quarters.
+    pub fn quarter() -> Freq {
+        Freq::Quarter { start_month: 1 }
+    }
+
+    /// Calendar years.
+    pub fn year() -> Freq {
+        Freq::Year { start_month: 1 }
+    }
+
+    /// `self`, or an error if `start_month` isn't a month.
+    pub fn check(self) -> Result<Freq> {
+        match self {
+            Freq::Quarter { start_month } | Freq::Year { start_month }
+                if !(1..=12).contains(&start_month) =>
+            {
+                Err(Error::InvalidFreq(format!("{:?}", self)))
+            },
+            _ => Ok(self),
+        }
+    }
+
+    /// (months per period, months to shift by) for month-based freqs.
+    fn months(self) -> Option<(i64, i64)> {
+        match self {
+            Freq::Day | Freq::Week => None,
+            Freq::Month => Some((1, 0)),
+            Freq::Quarter { start_month } => Some((3, (start_month as i64 - 1) % 3)),
+            Freq::Year { start_month } => Some((12, start_month as i64 - 1)),
+        }
+    }
+
+    /// Ordinal of the period containing `days` since the epoch.
+    pub(crate) fn ordinal(self, days: i64) -> i64 {
+        match self.months() {
+            None => match self {
+                Freq::Week => (days + 3).div_euclid(7),
+                _ => days,
+            },
+            Some((step, shift)) => {
+                let (year, month, _) = civil_from_days(days);
+                let months = (year as i64 - 1970) * 12 + month as i64 - 1;
+                (months - shift).div_euclid(step)
+            },
+        }
+    }
+
+    /// Days since the epoch of the first day of period `ordinal`.
+    fn start_day(self, ordinal: i64) -> i64 {
+        match self.months() {
+            None => match self {
+                Freq::Week => ordinal * 7 - 3,
+                _ => ordinal,
+            },
+            Some((step, shift)) => {
+                let months = ordinal * step + shift;
+                let year = 1970 + months.div_euclid(12);
+                let month = months.rem_euclid(12) as u32 + 1;
+                days_from_civil(year, month, 1)
+            },
+        }
+    }
+
+    /// Label of period `ordinal`, like `2017-06` or `2017Q2`.
+    pub fn format(self, ordinal: i64) -> String {
+        let (year, month, day) = civil_from_days(self.start_day(ordinal));
+        match self {
+            Freq::Day | Freq::Week => format!("{:04}-{:02}-{:02}", year, month, day),
+            Freq::Month => format!("{:04}-{:02}", year, month),
+            Freq::Quarter { start_month } => {
+                // position of this quarter in its (fiscal) year
+                let months_in = (month as i64 - start_month as i64).rem_euclid(12);
+                let fiscal_year = if month < start_month { year - 1 } else { year };
+                format!("{:04}Q{}", fiscal_year, months_in / 3 + 1)
+            },
+            Freq::Year { .. } => format!("{:04}", year),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodColumn {
+    values: Vec<i64>,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+    freq: Freq,
+}
+
+impl Column for PeriodColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::Period(self.freq)
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        !self.mask[i]
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        if self.mask[i] { Value::Period(self.values[i], self.freq) } else { Value::Null }
+    }
+
+    /// Ints are taken to be ordinals at the column's freq.
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push(None),
+            Value::Int(x) => self.push(Some(x)),
+            Value::Period(x, freq) if freq == self.freq => self.push(Some(x)),
+            value => return Err(Error::TypeMismatch {
+                expected: Dtype::Period(self.freq).to_string(),
+                found: value.kind().to_owned(),
+            }),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        // the freq was checked when self was built
+        Box::new(PeriodColumn::from_parts(Vec::new(), BitVec::new(), self.freq))
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl PeriodColumn {
+    /// Errors if `freq` isn't valid; see `Freq::check`.
+    pub fn new(freq: Freq) -> Result<Self> {
+        Ok(PeriodColumn::from_parts(Vec::new(), BitVec::new(), freq.check()?))
+    }
+
+    /// From period ordinals, counted from the period containing the epoch.
+    pub fn from_values(v: Vec<Option<i64>>, freq: Freq) -> Result<Self> {
+        Ok(PeriodColumn::from_parts(
+            v.iter().map(|x| x.unwrap_or(0)).collect(),
+            v.iter().map(|x| x.is_some()).collect(),
+            freq.check()?,
+        ))
+    }
+
+    /// The period containing each timestamp.
+    pub fn from_timestamps(ts: &TimestampColumn, freq: Freq) -> Result<Self> {
+        let per_day = ts.unit().per_second() * SECONDS_PER_DAY;
+        PeriodColumn::from_values(
+            ts.values()
+                .map(|x| x.map(|&x| freq.ordinal(x.div_euclid(per_day))))
+                .collect(),
+            freq,
+        )
+    }
+
+    fn from_parts(values: Vec<i64>, mask: BitVec, freq: Freq) -> Self {
+        PeriodColumn {
+            values,
+            mask,
+            freq,
+        }
+    }
+
+    pub fn freq(&self) -> Freq {
+        self.freq
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        !self.mask.get(i).unwrap_or(true)
+    }
+
+    /// First instant of each period.
+    pub fn start_time(&self, unit: Unit) -> TimestampColumn {
+        self.to_timestamps(unit, 0, 0)
+    }
+
+    /// Last instant (at `unit` resolution) of each period.
+    pub fn end_time(&self, unit: Unit) -> TimestampColumn {
+        self.to_timestamps(unit, 1, -1)
+    }
+
+    /// Label of the period at row `i`, like `2017-06` or `2017Q2`.
+    pub fn format(&self, i: usize) -> Option<String> {
+        self.get(i)
+            .and_then(|x| x)
+            .map(|&ordinal| self.freq.format(ordinal))
+    }
+
+    /// Start of period `ordinal + skip`, plus `adjust` units.
+    fn to_timestamps(&self, unit: Unit, skip: i64, adjust: i64) -> TimestampColumn {
+        let per_day = unit.per_second() * SECONDS_PER_DAY;
+        TimestampColumn::from_values(
+            self.values()
+                .map(|x| {
+                    x.and_then(|&ordinal| {
+                        self.freq.start_day(ordinal + skip)
+                            .checked_mul(per_day)
+                            .map(|ts| ts + adjust)
+                    })
+                })
+                .collect(),
+            unit,
+        )
+    }
+}
+
+impl DataType for PeriodColumn {
+    type Item = i64;
+
+    fn get(&self, index: usize) -> Option<Option<&i64>> {
+        if let Some(mask) = self.mask.get(index) {
+            if !mask {
+                return Some(None);
+            }
+        } else {
+            return None;
+        }
+        Some(self.values.get(index))
+    }
+
+    fn values(&self) -> Series<'_, Self::Item> {
+        Series::new(&self.values, &self.mask)
+    }
+}
+
+impl DataTypeMut for PeriodColumn {
+    fn push(&mut self, item: Option<i64>) {
+        self.values.push(item.unwrap_or(0));
+        self.mask.push(item.is_some());
+    }
+
+    /// Shifts periods, e.g. `apply(|p| p + 1)` for the next period.
+    fn apply<F>(&mut self, f: F)
+        where F: Fn(i64) -> i64 + ::std::marker::Sync
+    {
+        let mask = &self.mask;
+        for (i, x) in self.values.iter_mut().enumerate() {
+            if mask[i] {
+                *x = f(*x);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::StringColumn;
+
+    fn timestamps(v: Vec<Option<&str>>) -> TimestampColumn {
+        TimestampColumn::parse(&StringColumn::from(v), Unit::Seconds).unwrap()
+    }
+
+    #[test]
+    fn months_and_quarters() {
+        let ts = timestamps(vec![Some("2017-06-15T10:00:00"), None, Some("1969-12-31")]);
+
+        let months = PeriodColumn::from_timestamps(&ts, Freq::Month).unwrap();
+        assert_eq!(months, PeriodColumn::from_values(vec![Some(569), None, Some(-1)], Freq::Month).unwrap());
+        assert_eq!(months.format(0), Some("2017-06".to_owned()));
+        assert_eq!(months.format(1), None);
+        assert_eq!(months.format(2), Some("1969-12".to_owned()));
+
+        let quarters = PeriodColumn::from_timestamps(&ts, Freq::quarter()).unwrap();
+        assert_eq!(quarters.format(0), Some("2017Q2".to_owned()));
+        assert_eq!(quarters.start_time(Unit::Seconds), timestamps(vec![Some("2017-04-01"), None, Some("1969-10-01")]));
+        assert_eq!(
+            quarters.end_time(Unit::Seconds),
+            timestamps(vec![Some("2017-06-30T23:59:59"), None, Some("1969-12-31T23:59:59")])
+        );
+    }
+
+    #[test]
+    fn fiscal() {
+        // fiscal year starting in april
+        let ts = timestamps(vec![Some("2017-03-31"), Some("2017-04-01"), Some("2017-12-01")]);
+        let quarters = PeriodColumn::from_timestamps(&ts, Freq::Quarter { start_month: 4 }).unwrap();
+        assert_eq!(quarters.format(0), Some("2016Q4".to_owned()));
+        assert_eq!(quarters.format(1), Some("2017Q1".to_owned()));
+        assert_eq!(quarters.format(2), Some("2017Q3".to_owned()));
+
+        let years = PeriodColumn::from_timestamps(&ts, Freq::Year { start_month: 4 }).unwrap();
+        assert_eq!(
+            years.start_time(Unit::Seconds),
+            timestamps(vec![Some("2016-04-01"), Some("2017-04-01"), Some("2017-04-01")])
+        );
+    }
+
+    #[test]
+    fn days_and_weeks() {
+        // a sunday and the following monday
+        let ts = timestamps(vec![Some("2017-06-18T23:00:00"), Some("2017-06-19")]);
+        let weeks = PeriodColumn::from_timestamps(&ts, Freq::Week).unwrap();
+        assert_eq!(weeks.format(0), Some("2017-06-12".to_owned()));
+        assert_eq!(weeks.format(1), Some("2017-06-19".to_owned()));
+
+        let mut days = PeriodColumn::from_timestamps(&ts, Freq::Day).unwrap();
+        days.apply(|p| p + 1);
+        assert_eq!(days.format(0), Some("2017-06-19".to_owned()));
+    }
+
+    #[test]
+    fn bad_start_month() {
+        assert!(PeriodColumn::new(Freq::Quarter { start_month: 0 }).is_err());
+        assert!(PeriodColumn::new(Freq::Year { start_month: 13 }).is_err());
+        assert!(PeriodColumn::new(Freq::Year { start_month: 12 }).is_ok());
+    }
+}
@@ -0,0 +1,186 @@
+//! Ranking and membership
+//!
+//! `rank` numbers the rows of a column by their order, 1 for the first,
+//! with a `RankMethod` choosing what ties get. Like sorting, it's written
+//! once against `Value`s, so works on any column whose values compare;
+//! nulls and NaN are placed by `NaOption`.
+//!
+//! `is_in` tests each row against a list of values. Ints and floats
+//! holding the same number match each other. String columns override it
+//! to look the values up once in their dictionary, then only compare
+//! codes row by row.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use super::{BooleanColumn, Column, Float64Column, Value};
+
+/// Ranks given to tied values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RankMethod {
+    /// Mean of the ranks the ties span.
+    Average,
+    /// Lowest of the ranks the ties span.
+    Min,
+    /// Highest of the ranks the ties span.
+    Max,
+    /// Like `Min`, but the next value ranks one higher, leaving no gaps.
+    Dense,
+    /// Ties broken by row order.
+    Ordinal,
+}
+
+/// Where nulls and NaN go when ranking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NaOption {
+    /// Left null.
+    Keep,
+    /// Ranked before every value, tied with each other.
+    Top,
+    /// Ranked after every value, tied with each other.
+    Bottom,
+}
+
+pub(crate) fn rank(column: &dyn Column, method: RankMethod, ascending: bool, na_option: NaOption) -> Float64Column {
+    let values: Vec<Value> = (0..column.len()).map(|i| column.get_value(i)).collect();
+    let compare = |a: &Value, b: &Value| match (a.is_missing(), b.is_missing()) {
+        (false, false) => {
+            let ord = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+            if ascending { ord } else { ord.reverse() }
+        },
+        (false, true) => if na_option == NaOption::Top { Ordering::Greater } else { Ordering::Less },
+        (true, false) => if na_option == NaOption::Top { Ordering::Less } else { Ordering::Greater },
+        (true, true) => Ordering::Equal,
+    };
+
+    let mut order: Vec<usize> = (0..values.len())
+        .filter(|&i| na_option != NaOption::Keep || !values[i].is_missing())
+        .collect();
+    // stable, so ties stay in row order for `Ordinal`
+    order.sort_by(|&a, &b| compare(&values[a], &values[b]));
+
+    let mut ranks = vec![None; values.len()];
+    let mut start = 0;
+    let mut dense = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && compare(&values[order[start]], &values[order[end]]) == Ordering::Equal {
+            end += 1;
+        }
+        dense += 1;
+        for (k, &row) in order[start..end].iter().enumerate() {
+            // ranks start + 1 to end
+            ranks[row] = Some(match method {
+                RankMethod::Average => (start + 1 + end) as f64 / 2.0,
+                RankMethod::Min => (start + 1) as f64,
+                RankMethod::Max => end as f64,
+                RankMethod::Dense => dense as f64,
+                RankMethod::Ordinal => (start + 1 + k) as f64,
+            });
+        }
+        start = end;
+    }
+    Float64Column::from(ranks)
+}
+
+/// `value`, with floats holding an int made ints, so they hash the same.
+fn member_key(value: &Value) -> Value {
+    match *value {
+        Value::Float(x) if x.fract() == 0.0 && x.abs() < i64::MAX as f64 => Value::Int(x as i64),
+        ref value => value.clone(),
+    }
+}
+
+pub(crate) fn is_in(column: &dyn Column, values: &[Value]) -> BooleanColumn {
+    let keys: HashSet<Value> = values.iter().map(member_key).collect();
+    let res: Vec<Option<bool>> = (0..column.len())
+        .map(|i| match column.get_value(i) {
+            Value::Null => None,
+            value => Some(keys.contains(&member_key(&value))),
+        })
+        .collect();
+    BooleanColumn::from(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Float32Column, Int16Column, StringColumn, Utf8Column};
+
+    fn ranks(column: &dyn Column, method: RankMethod, ascending: bool, na_option: NaOption) -> Vec<Option<f64>> {
+        let ranked = column.rank(method, ascending, na_option);
+        (0..ranked.len()).map(|i| ranked.get_value(i).as_f64()).collect()
+    }
+
+    #[test]
+    fn methods() {
+        let col = Int16Column::from(vec![Some(3), Some(1), Some(3), None, Some(2), Some(3)]);
+        let keep = NaOption::Keep;
+        assert_eq!(
+            ranks(&col, RankMethod::Average, true, keep),
+            vec![Some(4.0), Some(1.0), Some(4.0), None, Some(2.0), Some(4.0)]
+        );
+        assert_eq!(
+            ranks(&col, RankMethod::Min, true, keep),
+            vec![Some(3.0), Some(1.0), Some(3.0), None, Some(2.0), Some(3.0)]
+        );
+        assert_eq!(
+            ranks(&col, RankMethod::Max, true, keep),
+            vec![Some(5.0), Some(1.0), Some(5.0), None, Some(2.0), Some(5.0)]
+        );
+        assert_eq!(
+            ranks(&col, RankMethod::Dense, true, keep),
+            vec![Some(3.0), Some(1.0), Some(3.0), None, Some(2.0), Some(3.0)]
+        );
+        assert_eq!(
+            ranks(&col, RankMethod::Ordinal, true, keep),
+            vec![Some(3.0), Some(1.0), Some(4.0), None, Some(2.0), Some(5.0)]
+        );
+        assert_eq!(
+            ranks(&col, RankMethod::Ordinal, false, keep),
+            vec![Some(1.0), Some(5.0), Some(2.0), None, Some(4.0), Some(3.0)]
+        );
+    }
+
+    #[test]
+    fn nulls_and_nan() {
+        let col = Float32Column::from(vec![Some(f32::NAN), Some(2.0), None, Some(1.0)]);
+        assert_eq!(
+            ranks(&col, RankMethod::Average, true, NaOption::Top),
+            vec![Some(1.5), Some(4.0), Some(1.5), Some(3.0)]
+        );
+        // descending doesn't move the nulls
+        assert_eq!(
+            ranks(&col, RankMethod::Min, false, NaOption::Bottom),
+            vec![Some(3.0), Some(1.0), Some(3.0), Some(2.0)]
+        );
+        assert_eq!(
+            ranks(&col, RankMethod::Dense, true, NaOption::Keep),
+            vec![None, Some(2.0), None, Some(1.0)]
+        );
+
+        let strings = StringColumn::from(vec![Some("b"), None, Some("a")]);
+        assert_eq!(
+            ranks(&strings, RankMethod::Min, true, NaOption::Keep),
+            vec![Some(2.0), None, Some(1.0)]
+        );
+    }
+
+    #[test]
+    fn membership() {
+        let col = Int16Column::from(vec![Some(1), None, Some(3)]);
+        let res = col.is_in(&[Value::Float(3.0), Value::Str("1".to_owned())]);
+        assert_eq!(res, BooleanColumn::from(vec![Some(false), None, Some(true)]));
+
+        let floats = Float32Column::from(vec![1.5, 2.0]);
+        assert_eq!(floats.is_in(&[Value::Int(2)]), BooleanColumn::from(vec![false, true]));
+
+        let values = [Value::Str("b".to_owned()), Value::Str("z".to_owned()), Value::Int(1)];
+        let expected = BooleanColumn::from(vec![Some(false), Some(true), None, Some(true)]);
+        let strings = StringColumn::from(vec![Some("a"), Some("b"), None, Some("b")]);
+        assert_eq!(strings.is_in(&values), expected);
+        let utf8 = Utf8Column::from(vec![Some("a"), Some("b"), None, Some("b")]);
+        assert_eq!(utf8.is_in(&values), expected);
+        assert_eq!(strings.is_in(&[]), BooleanColumn::from(vec![Some(false), Some(false), None, Some(false)]));
+    }
+}
@@ -0,0 +1,347 @@
+//! Reductions for numeric columns
+//!
+//! The kernels behind the `Numeric` trait. They work on the raw values
+//! and mask of a column, and all treat NaN as missing, like null: it's
+//! skipped by every reduction and not included in `count`, same as
+//! pandas' default `skipna`.
+//!
+//! Sums accumulate in a type no column can overflow (i128 for ints,
+//! f64 for floats). Products accumulate in a wider type (i64 for ints,
+//! or f64), so an Int8Column can multiply past 127; an int product
+//! which overflows the i64 is an error. Means and variances accumulate
+//! in f64.
+//!
+//! Columns longer than `PARALLEL_CHUNK` are split into chunks of that
+//! size which are reduced in parallel with rayon, then combined.
+
+use bit_vec::BitVec;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Add;
+
+/// Rows per chunk when reducing in parallel. Shorter columns are
+/// reduced on the calling thread, since spawning isn't worth it.
+pub const PARALLEL_CHUNK: usize = 1 << 16;
+
+/// Element types of numeric columns.
+pub trait Primitive: Copy + PartialOrd + Send + Sync + fmt::Debug {
+    /// Wider type that products and cumulative ops accumulate in.
+    type Acc: Copy + Send + Sync + fmt::Debug + PartialEq;
+
+    /// Type that sums accumulate in; its default is 0.
+    type Sum: Copy + Send + Sync + fmt::Debug + PartialEq + Default + Add<Output = Self::Sum> + From<Self>;
+
+    fn zero() -> Self::Acc;
+
+    fn one() -> Self::Acc;
+
+    /// Ints wrap on overflow of the accumulator.
+    fn add(acc: Self::Acc, x: Self::Acc) -> Self::Acc;
+
+    fn sub(acc: Self::Acc, x: Self::Acc) -> Self::Acc;
+
+    fn mul(acc: Self::Acc, x: Self::Acc) -> Self::Acc;
+
+    /// `None` if an int product overflows the accumulator.
+    fn checked_mul(acc: Self::Acc, x: Self::Acc) -> Option<Self::Acc>;
+
+    fn widen(self) -> Self::Acc;
+
+    fn to_f64(self) -> f64;
+
+    /// False for NaN, which reductions skip.
+    fn is_comparable(self) -> bool {
+        self.partial_cmp(&self).is_some()
+    }
+}
+
+macro_rules! int_primitive {
+    ($t:ty) => {
+        impl Primitive for $t {
+            type Acc = i64;
+            type Sum = i128;
+
+            fn zero() -> i64 {
+                0
+            }
+
+            fn one() -> i64 {
+                1
+            }
+
+            fn add(acc: i64, x: i64) -> i64 {
+                acc.wrapping_add(x)
+            }
+
+            fn sub(acc: i64, x: i64) -> i64 {
+                acc.wrapping_sub(x)
+            }
+
+            fn mul(acc: i64, x: i64) -> i64 {
+                acc.wrapping_mul(x)
+            }
+
+            fn checked_mul(acc: i64, x: i64) -> Option<i64> {
+                acc.checked_mul(x)
+            }
+
+            fn widen(self) -> i64 {
+                self as i64
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    };
+}
+
+macro_rules! float_primitive {
+    ($t:ty) => {
+        impl Primitive for $t {
+            type Acc = f64;
+            type Sum = f64;
+
+            fn zero() -> f64 {
+                0.0
+            }
+
+            fn one() -> f64 {
+                1.0
+            }
+
+            fn add(acc: f64, x: f64) -> f64 {
+                acc + x
+            }
+
+            fn sub(acc: f64, x: f64) -> f64 {
+                acc - x
+            }
+
+            fn mul(acc: f64, x: f64) -> f64 {
+                acc * x
+            }
+
+            fn checked_mul(acc: f64, x: f64) -> Option<f64> {
+                Some(acc * x)
+            }
+
+            fn widen(self) -> f64 {
+                self as f64
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    };
+}
+
+int_primitive!(i8);
+int_primitive!(i16);
+int_primitive!(i32);
+int_primitive!(i64);
+float_primitive!(f32);
+float_primitive!(f64);
+
+/// How `quantile` picks a value when the quantile falls between two
+/// values `i < j`, same as pandas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interpolation {
+    /// `i + (j - i) * fraction`
+    Linear,
+    Lower,
+    Higher,
+    /// `(i + j) / 2`
+    Midpoint,
+    /// Whichever of `i` and `j` is closer.
+    Nearest,
+}
+
+/// Folds `f` over the valid rows, which are neither null nor NaN
+/// (passing the row index too), in parallel chunks for long columns,
+/// combining chunks with `combine`.
+pub(crate) fn fold_valid<T, A, F, C>(
+    values: &[T],
+    mask: &BitVec,
+    identity: A,
+    f: F,
+    combine: C
+) -> A
+    where T: Primitive,
+          A: Copy + Send + Sync,
+          F: Fn(A, usize, T) -> A + Sync,
+          C: Fn(A, A) -> A + Sync
+{
+    let fold_chunk = |start: usize| {
+        let end = (start + PARALLEL_CHUNK).min(values.len());
+        (start..end)
+            .filter(|&i| mask[i] && values[i].is_comparable())
+            .fold(identity, |acc, i| f(acc, i, values[i]))
+    };
+
+    if values.len() <= PARALLEL_CHUNK {
+        fold_chunk(0)
+    } else {
+        let chunks = values.len().div_ceil(PARALLEL_CHUNK);
+        (0..chunks).into_par_iter()
+            .map(|chunk| fold_chunk(chunk * PARALLEL_CHUNK))
+            .reduce(|| identity, &combine)
+    }
+}
+
+/// Row and value of the first extreme value, where `better(a, b)`
+/// says whether `a` should replace `b`.
+pub(crate) fn arg_extreme<T, F>(values: &[T], mask: &BitVec, better: F) -> Option<(usize, T)>
+    where T: Primitive,
+          F: Fn(T, T) -> bool + Sync
+{
+    let pick = |a: Option<(usize, T)>, b: Option<(usize, T)>| match (a, b) {
+        (Some(a), Some(b)) => {
+            // ties go to the earlier row
+            if better(b.1, a.1) || (!better(a.1, b.1) && b.0 < a.0) { Some(b) } else { Some(a) }
+        },
+        (a, None) => a,
+        (None, b) => b,
+    };
+    fold_valid(
+        values,
+        mask,
+        None,
+        |acc, i, x| pick(acc, Some((i, x))),
+        pick,
+    )
+}
+
+/// `q`th quantile of the valid, comparable values.
+pub(crate) fn quantile<T>(
+    values: &[T],
+    mask: &BitVec,
+    q: f64,
+    interpolation: Interpolation
+) -> Option<f64>
+    where T: Primitive
+{
+    assert!((0.0..=1.0).contains(&q), "quantile must be between 0 and 1");
+    let mut valid: Vec<f64> = values.iter()
+        .zip(mask.iter())
+        .filter(|&(x, valid)| valid && x.is_comparable())
+        .map(|(x, _)| x.to_f64())
+        .collect();
+    if valid.is_empty() {
+        return None;
+    }
+
+    let cmp = |a: &f64, b: &f64| a.partial_cmp(b).unwrap_or(Ordering::Equal);
+    let position = q * (valid.len() - 1) as f64;
+    let lower_index = position.floor() as usize;
+    let fraction = position - lower_index as f64;
+
+    // partial selection rather than a full sort: everything after the
+    // lower value is at least as large, so the higher one is its min
+    let (_, &mut lower, rest) = valid.select_nth_unstable_by(lower_index, cmp);
+    let higher = if fraction > 0.0 {
+        rest.iter().cloned().min_by(cmp).unwrap_or(lower)
+    } else {
+        lower
+    };
+
+    Some(match interpolation {
+        Interpolation::Linear => lower + (higher - lower) * fraction,
+        Interpolation::Lower => lower,
+        Interpolation::Higher => higher,
+        Interpolation::Midpoint => (lower + higher) / 2.0,
+        // ties go to the even position, like numpy
+        Interpolation::Nearest => {
+            if fraction > 0.5 || (fraction == 0.5 && lower_index % 2 == 1) { higher } else { lower }
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use column::{Float32Column, Float64Column, Int8Column, Int64Column, Interpolation, Numeric};
+    use error::Error;
+
+    #[test]
+    fn wide_sums() {
+        let col = Int8Column::from(vec![Some(100), Some(100), None, Some(-128)]);
+        assert_eq!(col.sum(), 72);
+        assert_eq!(col.product().unwrap(), -1_280_000);
+        assert_eq!(col.count(), 3);
+        assert_eq!(col.null_count(), 1);
+        assert_eq!(Int8Column::from(vec![None]).sum(), 0);
+        assert_eq!(Int8Column::from(vec![None]).product().unwrap(), 1);
+        assert_eq!(Int64Column::from(vec![i64::MAX, i64::MAX]).sum(), 2 * i64::MAX as i128);
+        match Int64Column::from(vec![i64::MAX, 2]).product() {
+            Err(Error::AggregateOverflow { agg: "product", .. }) => (),
+            res => panic!("{:?}", res),
+        }
+        assert_eq!(Int64Column::from(vec![Some(i64::MAX), None, Some(-1)]).product().unwrap(), -i64::MAX);
+    }
+
+    #[test]
+    fn nan_is_missing() {
+        let col = Float64Column::from(vec![Some(1.0), Some(f64::NAN), None, Some(3.0)]);
+        assert_eq!(col.sum(), 4.0);
+        assert_eq!(col.product().unwrap(), 3.0);
+        assert_eq!(col.count(), 2);
+        assert_eq!(col.null_count(), 2);
+        assert_eq!(col.mean(), Some(2.0));
+        assert_eq!(col.var(1), Some(2.0));
+        assert_eq!(col.max(), Some(3.0));
+        assert_eq!(col.median(), Some(2.0));
+        assert_eq!(Float64Column::from(vec![f64::NAN]).mean(), None);
+    }
+
+    #[test]
+    fn extremes() {
+        let col = Float64Column::from(vec![Some(f64::NAN), Some(3.0), None, Some(-1.0), Some(3.0)]);
+        assert_eq!(col.min(), Some(-1.0));
+        assert_eq!(col.max(), Some(3.0));
+        assert_eq!(col.argmin(), Some(3));
+        assert_eq!(col.argmax(), Some(1));
+        assert_eq!(Int64Column::from(vec![None]).argmax(), None);
+    }
+
+    #[test]
+    fn moments() {
+        let col = Int64Column::from(vec![Some(2), Some(4), None, Some(4), Some(4), Some(5), Some(5), Some(7), Some(9)]);
+        assert_eq!(col.mean(), Some(5.0));
+        assert_eq!(col.var(0), Some(4.0));
+        assert_eq!(col.std(0), Some(2.0));
+        assert_eq!(col.var(1), Some(32.0 / 7.0));
+        assert_eq!(Int64Column::from(vec![Some(1)]).var(1), None);
+        assert_eq!(Float32Column::from(vec![None]).mean(), None);
+    }
+
+    #[test]
+    fn quantiles() {
+        let col = Int64Column::from(vec![Some(4), None, Some(1), Some(3), Some(2)]);
+        assert_eq!(col.median(), Some(2.5));
+        assert_eq!(col.quantile(0.0, Interpolation::Linear), Some(1.0));
+        assert_eq!(col.quantile(1.0, Interpolation::Linear), Some(4.0));
+        assert_eq!(col.quantile(0.25, Interpolation::Linear), Some(1.75));
+        assert_eq!(col.quantile(0.25, Interpolation::Lower), Some(1.0));
+        assert_eq!(col.quantile(0.25, Interpolation::Higher), Some(2.0));
+        assert_eq!(col.quantile(0.25, Interpolation::Midpoint), Some(1.5));
+        assert_eq!(col.quantile(0.25, Interpolation::Nearest), Some(2.0));
+        assert_eq!(col.quantile(0.5, Interpolation::Nearest), Some(3.0));
+        assert_eq!(Float64Column::from(vec![f64::NAN]).median(), None);
+    }
+
+    #[test]
+    fn parallel() {
+        let n = 3 * super::PARALLEL_CHUNK + 7;
+        let col: Int64Column = (0..n as i64)
+            .map(|x| if x % 10 == 0 { None } else { Some(x) })
+            .collect::<Vec<_>>()
+            .into();
+        let expected: i128 = (0..n as i128).filter(|x| x % 10 != 0).sum();
+        assert_eq!(col.sum(), expected);
+        assert_eq!(col.count(), n - n.div_ceil(10));
+        assert_eq!(col.argmin(), Some(1));
+        assert_eq!(col.max(), Some(n as i64 - 1));
+    }
+}
@@ -0,0 +1,270 @@
+//! Iterating over columns
+//!
+//! Series iterates over the rows of a column as `Option<&T>`. Columns
+//! with a contiguous Vec of values (every numeric and time column) read
+//! it directly; bit-packed booleans go through `DataType::get`.
+//!
+//! For kernels that work better on batches, `chunks(n)` gives slices
+//! of up to `n` values with the matching part of the mask, and
+//! `par_iter` spreads the rows over rayon's threads.
+
+use bit_vec::BitVec;
+use rayon::prelude::*;
+
+use super::DataType;
+
+/// A view of part of a BitVec.
+#[derive(Debug, Clone, Copy)]
+pub struct BitSlice<'a> {
+    bits: &'a BitVec,
+    start: usize,
+    len: usize,
+}
+
+impl<'a> BitSlice<'a> {
+    pub fn new(bits: &'a BitVec, start: usize, len: usize) -> Self {
+        assert!(start + len <= bits.len(), "slice out of bounds");
+        BitSlice {
+            bits,
+            start,
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<bool> {
+        if i < self.len { self.bits.get(self.start + i) } else { None }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=bool> + 'a {
+        let bits = self.bits;
+        (self.start..self.start + self.len).map(move |i| bits[i])
+    }
+
+    /// Number of set bits; for a mask, the number of valid rows.
+    pub fn count_ones(&self) -> usize {
+        self.iter().filter(|&bit| bit).count()
+    }
+
+    /// Whether every bit is set; for a mask, whether there are no
+    /// nulls, so a kernel can skip checking it.
+    pub fn all(&self) -> bool {
+        self.iter().all(|bit| bit)
+    }
+}
+
+enum Source<'a, T: 'a> {
+    Slice(&'a [T], &'a BitVec),
+    Dynamic(&'a (dyn DataType<Item=T> + Sync)),
+}
+
+// not derived, since that would need T: Copy
+impl<'a, T> Clone for Source<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Source<'a, T> {}
+
+impl<'a, T> Source<'a, T> {
+    fn get(self, i: usize) -> Option<&'a T> {
+        match self {
+            Source::Slice(values, mask) => if mask[i] { Some(&values[i]) } else { None },
+            // only called in bounds
+            Source::Dynamic(values) => values.get(i).unwrap(),
+        }
+    }
+}
+
+/// Iterator for column types.
+pub struct Series<'a, T: 'a + Clone> {
+    source: Source<'a, T>,
+    // rows not yet yielded, from either end
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Series<'a, T>
+    where T: Clone
+{
+    /// Over contiguous `values`, null where `mask` is false.
+    pub fn new(values: &'a [T], mask: &'a BitVec) -> Self {
+        assert_eq!(values.len(), mask.len());
+        Series {
+            source: Source::Slice(values, mask),
+            front: 0,
+            back: values.len(),
+        }
+    }
+
+    /// Over the first `len` rows of any DataType, a row at a time.
+    pub fn from_dyn(values: &'a (dyn DataType<Item=T> + Sync), len: usize) -> Self {
+        Series {
+            source: Source::Dynamic(values),
+            front: 0,
+            back: len,
+        }
+    }
+
+    /// Batches of up to `n` of the remaining rows, as values and the
+    /// matching mask (false is null). Values under nulls are
+    /// unspecified. `None` if the column doesn't have contiguous
+    /// values (booleans, which are bit-packed).
+    ///
+    /// Panics if `n` is 0.
+    pub fn chunks(&self, n: usize) -> Option<SeriesChunks<'a, T>> {
+        assert!(n > 0, "chunk size must be positive");
+        match self.source {
+            Source::Slice(values, mask) => Some(SeriesChunks {
+                values,
+                mask,
+                front: self.front,
+                back: self.back,
+                size: n,
+            }),
+            Source::Dynamic(_) => None,
+        }
+    }
+}
+
+impl<'a, T> Series<'a, T>
+    where T: Clone + Sync
+{
+    /// The remaining rows, in parallel.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item=Option<&'a T>> + 'a {
+        let source = self.source;
+        (self.front..self.back).into_par_iter().map(move |i| source.get(i))
+    }
+}
+
+impl<'a, T> Iterator for Series<'a, T>
+    where T: Clone
+{
+    type Item = Option<&'a T>;
+
+    fn next(&mut self) -> Option<Option<&'a T>> {
+        if self.front == self.back {
+            return None;
+        }
+        let res = self.source.get(self.front);
+        self.front += 1;
+        Some(res)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Option<&'a T>> {
+        self.front = (self.front + n).min(self.back);
+        self.next()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Series<'a, T>
+    where T: Clone
+{
+    fn next_back(&mut self) -> Option<Option<&'a T>> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.source.get(self.back))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Series<'a, T>
+    where T: Clone
+{
+}
+
+/// Iterator from `Series::chunks`.
+pub struct SeriesChunks<'a, T: 'a> {
+    values: &'a [T],
+    mask: &'a BitVec,
+    front: usize,
+    back: usize,
+    size: usize,
+}
+
+impl<'a, T> Iterator for SeriesChunks<'a, T> {
+    type Item = (&'a [T], BitSlice<'a>);
+
+    fn next(&mut self) -> Option<(&'a [T], BitSlice<'a>)> {
+        if self.front == self.back {
+            return None;
+        }
+        let start = self.front;
+        self.front = (start + self.size).min(self.back);
+        Some((&self.values[start..self.front], BitSlice::new(self.mask, start, self.front - start)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front).div_ceil(self.size);
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for SeriesChunks<'a, T> {
+}
+
+#[cfg(test)]
+mod tests {
+    use column::{BooleanColumn, DataType, Int32Column};
+    use rayon::prelude::*;
+
+    #[test]
+    fn iterate() {
+        let col = Int32Column::from(vec![Some(1), None, Some(3), Some(4), Some(5)]);
+        let mut values = col.values();
+        assert_eq!(values.len(), 5);
+        assert_eq!(values.next(), Some(Some(&1)));
+        assert_eq!(values.next_back(), Some(Some(&5)));
+        assert_eq!(values.nth(1), Some(Some(&3)));
+        assert_eq!(values.len(), 1);
+        assert_eq!(values.nth(5), None);
+        assert_eq!(col.values().rev().flatten().cloned().collect::<Vec<_>>(), vec![5, 4, 3, 1]);
+
+        let bools = BooleanColumn::from(vec![Some(true), None]);
+        assert_eq!(bools.values().collect::<Vec<_>>(), vec![Some(&true), None]);
+        assert_eq!(bools.values().rev().len(), 2);
+    }
+
+    #[test]
+    fn chunks() {
+        let col = Int32Column::from(vec![Some(1), None, Some(3), Some(4), Some(5)]);
+        let mut values = col.values();
+        values.next();
+        let chunks: Vec<_> = values.chunks(3).unwrap().collect();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].0.len(), 3);
+        assert_eq!(&chunks[0].0[1..], &[3, 4]);
+        assert_eq!(chunks[0].1.iter().collect::<Vec<_>>(), vec![false, true, true]);
+        assert_eq!(chunks[0].1.count_ones(), 2);
+        assert!(chunks[1].1.all());
+        assert_eq!(chunks[1].0, &[5]);
+        assert_eq!(values.chunks(2).unwrap().len(), 2);
+        assert!(BooleanColumn::from(vec![true]).values().chunks(1).is_none());
+    }
+
+    #[test]
+    fn par_iter() {
+        let col: Int32Column = (0..100_000).map(Some).collect::<Vec<_>>().into();
+        let sum = col.values()
+            .par_iter()
+            .map(|x| x.map_or(0, |&x| x as i64))
+            .sum();
+        assert_eq!(sum, 4_999_950_000);
+        let bools = BooleanColumn::from(vec![Some(true), None, Some(true)]);
+        assert_eq!(bools.values().par_iter().filter(|x| x.is_some()).count(), 2);
+    }
+}
@@ -11,22 +11,106 @@
 //!
 //! Would this work ok with streaming from disk? (since it's not just a
 //! straightforward list of elements).
+//!
+//! Shares backing with BinaryColumn; every value is guaranteed to be
+//! utf8, either because it was pushed as a &str or because it was
+//! validated when converting from binary.
 
 use bit_vec::BitVec;
 use llamas_categorical::CategoricalVec;
-use std::convert::From;
+use regex::Regex;
+use std::any::Any;
+use std::collections::HashSet;
+use std::convert::{From, TryFrom};
 use std::ops::Index;
 use std::str;
 use std::string::String;
 
-use super::Column;
+use error::{Error, Result};
+use super::{BinaryColumn, BooleanColumn, Chunk, Column, Dtype, Int64Column, Value};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StringColumn {
     values: CategoricalVec,
+    // false in the mask maps to null in values.
     mask: BitVec,
 }
 
+impl Column for StringColumn {
+    fn dtype(&self) -> Dtype {
+        Dtype::String
+    }
+
+    fn len(&self) -> usize {
+        StringColumn::len(self)
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        StringColumn::is_null(self, i)
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        if self.mask[i] { Value::Str(self[i].to_owned()) } else { Value::Null }
+    }
+
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push_null(),
+            Value::Str(ref s) => self.push(s),
+            value => return Err(Error::TypeMismatch {
+                expected: Dtype::String.to_string(),
+                found: value.kind().to_owned(),
+            }),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(StringColumn::new())
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// Looks `values` up in the dictionary once, so rows only need
+    /// their codes checked.
+    fn is_in(&self, values: &[Value]) -> BooleanColumn {
+        let wanted: HashSet<&[u8]> = values.iter()
+            .filter_map(|value| value.as_str())
+            .map(|s| s.as_bytes())
+            .collect();
+        let codes: Vec<bool> = self.values.categories()
+            .map(|bytes| wanted.contains(bytes))
+            .collect();
+        let res: Vec<Option<bool>> = (0..self.len())
+            .map(|i| if self.mask[i] { self.values.code(i).map(|code| codes[code]) } else { None })
+            .collect();
+        BooleanColumn::from(res)
+    }
+
+    /// Counts codes rather than hashing strings.
+    fn distinct_rows(&self) -> Vec<(usize, usize)> {
+        let mut by_code: Vec<Option<(usize, usize)>> = vec![None; self.values.n_categories()];
+        // null rows are stored as "", so are kept apart by the mask
+        let mut null = None;
+        for i in 0..self.len() {
+            let entry = if self.mask[i] { &mut by_code[self.values.code(i).unwrap()] } else { &mut null };
+            match *entry {
+                Some((_, ref mut count)) => *count += 1,
+                None => *entry = Some((i, 1)),
+            }
+        }
+        let mut res: Vec<_> = by_code.into_iter().chain(Some(null)).flatten().collect();
+        res.sort_unstable_by_key(|&(row, _)| row);
+        res
+    }
+}
+
 impl StringColumn {
     pub fn new() -> Self {
         StringColumn {
@@ -35,6 +119,14 @@ impl StringColumn {
         }
     }
 
+    fn from_parts(values: CategoricalVec, mask: BitVec) -> Self {
+        assert_eq!(values.len(), mask.len());
+        StringColumn {
+            values,
+            mask,
+        }
+    }
+
     /// Takes a reference to a string because:
     /// - if string already exists in array, don't need
     ///   to copy.
@@ -50,7 +142,9 @@ impl StringColumn {
         self.mask.push(false);
     }
 
-    pub fn contains(&self, s: &str) -> bool {
+    /// Whether `s` is one of the values in the column. This only
+    /// searches the distinct values, not every row.
+    pub fn contains_value(&self, s: &str) -> bool {
         self.values.contains(s.as_bytes())
     }
 
@@ -60,10 +154,41 @@ impl StringColumn {
             .map(|bytes| str::from_utf8(bytes).unwrap())
     }
 
+    pub fn is_null(&self, i: usize) -> bool {
+        !self.mask.get(i).unwrap_or(true)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.values.is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Number of distinct non-null values, found through the
+    /// codes rather than by comparing strings.
+    pub fn n_unique(&self) -> usize {
+        let mut seen = BitVec::from_elem(self.values.n_categories(), false);
+        for code in (0..self.len()).filter_map(|i| self.row_code(i)) {
+            seen.set(code, true);
+        }
+        seen.iter().filter(|&x| x).count()
+    }
+
+    /// Most common non-null value and how many times it appears,
+    /// counted through the codes. Ties go to the value seen first.
+    pub(crate) fn top(&self) -> Option<(&str, usize)> {
+        let mut counts = vec![0; self.values.n_categories()];
+        for code in (0..self.len()).filter_map(|i| self.row_code(i)) {
+            counts[code] += 1;
+        }
+        let freq = counts.iter().cloned().max().unwrap_or(0);
+        (0..self.len())
+            .find(|&i| self.row_code(i).is_some_and(|code| counts[code] == freq))
+            .map(|i| (&self[i], freq))
+    }
+
     //pub fn split_off(&mut self, at: usize) -> Self {
     //}
 
@@ -77,6 +202,285 @@ impl StringColumn {
     // pop?
 }
 
+// Vectorized string ops.
+//
+// Each op is computed once per distinct value in the CategoricalVec
+// dictionary, then broadcast to rows through the codes, so cost
+// scales with cardinality rather than with the number of rows.
+// Nulls stay null.
+impl StringColumn {
+    /// Length of each value, in chars.
+    pub fn str_len(&self) -> Int64Column {
+        let lens = self.per_category(|s| s.chars().count() as i64);
+        (0..self.len())
+            .map(|i| self.row_code(i).map(|code| lens[code]))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    pub fn to_upper(&self) -> StringColumn {
+        self.map_str(|s| s.to_uppercase())
+    }
+
+    pub fn to_lower(&self) -> StringColumn {
+        self.map_str(|s| s.to_lowercase())
+    }
+
+    /// Strips leading and trailing whitespace.
+    pub fn trim(&self) -> StringColumn {
+        self.map_str(|s| s.trim().to_owned())
+    }
+
+    pub fn starts_with(&self, pat: &str) -> BooleanColumn {
+        self.map_bool(|s| s.starts_with(pat))
+    }
+
+    pub fn ends_with(&self, pat: &str) -> BooleanColumn {
+        self.map_bool(|s| s.ends_with(pat))
+    }
+
+    /// Whether each value contains `pat` as a substring.
+    pub fn contains(&self, pat: &str) -> BooleanColumn {
+        self.map_bool(|s| s.contains(pat))
+    }
+
+    /// Replaces all matches of `from` with `to`.
+    pub fn replace(&self, from: &str, to: &str) -> StringColumn {
+        self.map_str(|s| s.replace(from, to))
+    }
+
+    /// Substring of each value by char position, `start` inclusive and
+    /// `stop` exclusive. A `stop` of `None` slices to the end.
+    pub fn slice(&self, start: usize, stop: Option<usize>) -> StringColumn {
+        self.map_str(|s| {
+            let chars = s.chars().skip(start);
+            match stop {
+                Some(stop) => chars.take(stop.saturating_sub(start)).collect(),
+                None => chars.collect(),
+            }
+        })
+    }
+
+    /// Splits each value on `sep`, returning one column per part.
+    ///
+    /// There are as many columns as parts in the value with the most
+    /// parts; values with fewer parts are null in the trailing columns.
+    pub fn split(&self, sep: &str) -> Vec<StringColumn> {
+        let n_parts = self.per_category(|s| s.split(sep).count());
+        let width = (0..self.len())
+            .filter_map(|i| self.row_code(i))
+            .map(|code| n_parts[code])
+            .max()
+            .unwrap_or(0);
+
+        (0..width)
+            .map(|k| self.map_opt_str(|s| s.split(sep).nth(k).map(|part| part.to_owned())))
+            .collect()
+    }
+
+    /// Code of the value at row `i`, or `None` if null.
+    pub(crate) fn row_code(&self, i: usize) -> Option<usize> {
+        if self.mask[i] {
+            self.values.code(i)
+        } else {
+            None
+        }
+    }
+
+    /// Evaluates `f` on each distinct value, indexed by code.
+    pub(crate) fn per_category<T, F>(&self, mut f: F) -> Vec<T>
+        where F: FnMut(&str) -> T
+    {
+        self.values
+            .categories()
+            .map(|bytes| f(str::from_utf8(bytes).unwrap()))
+            .collect()
+    }
+
+    fn map_bool<F>(&self, f: F) -> BooleanColumn
+        where F: FnMut(&str) -> bool
+    {
+        let per_category = self.per_category(f);
+        let values = (0..self.len())
+            .map(|i| self.row_code(i).is_some_and(|code| per_category[code]))
+            .collect();
+        BooleanColumn::from_parts(values, self.mask.clone())
+    }
+
+    fn map_str<F>(&self, mut f: F) -> StringColumn
+        where F: FnMut(&str) -> String
+    {
+        let values = self.values.map_categories(|bytes| {
+            f(str::from_utf8(bytes).unwrap()).into_bytes()
+        });
+        StringColumn::from_parts(values, self.mask.clone())
+    }
+
+    /// Like `map_str`, but `f` returning `None` makes the row null.
+    fn map_opt_str<F>(&self, f: F) -> StringColumn
+        where F: FnMut(&str) -> Option<String>
+    {
+        let per_category = self.per_category(f);
+
+        // map_categories visits in code order, same as per_category
+        let mut mapped = per_category.iter();
+        let values = self.values.map_categories(|_| {
+            match *mapped.next().unwrap() {
+                Some(ref s) => s.as_bytes().to_vec(),
+                None => Vec::new(),
+            }
+        });
+        let mask = (0..self.len())
+            .map(|i| self.row_code(i).is_some_and(|code| per_category[code].is_some()))
+            .collect();
+        StringColumn::from_parts(values, mask)
+    }
+}
+
+// Regex ops.
+//
+// Patterns are compiled once, and like the other string ops are
+// evaluated once per distinct value. Nulls stay null.
+impl StringColumn {
+    /// Whether each value contains a match for `pattern`.
+    pub fn matches(&self, pattern: &str) -> Result<BooleanColumn> {
+        let re = Regex::new(pattern)?;
+        Ok(self.map_bool(|s| re.is_match(s)))
+    }
+
+    /// Capture group `group` of the first match of `pattern`, where group 0
+    /// is the whole match. Rows without a match, or where the group didn't
+    /// participate in the match, are null.
+    pub fn extract(&self, pattern: &str, group: usize) -> Result<StringColumn> {
+        let re = Regex::new(pattern)?;
+        Ok(self.extract_group(&re, group))
+    }
+
+    /// All capture groups of the first match of `pattern`, one column
+    /// per group (not including group 0, the whole match).
+    pub fn extract_all(&self, pattern: &str) -> Result<Vec<StringColumn>> {
+        let re = Regex::new(pattern)?;
+        Ok((1..re.captures_len())
+            .map(|group| self.extract_group(&re, group))
+            .collect())
+    }
+
+    /// Replaces all matches of `pattern` with `rep`, which can refer to
+    /// capture groups as `$1` or `$name`.
+    pub fn replace_regex(&self, pattern: &str, rep: &str) -> Result<StringColumn> {
+        let re = Regex::new(pattern)?;
+        Ok(self.map_str(|s| re.replace_all(s, rep).into_owned()))
+    }
+
+    fn extract_group(&self, re: &Regex, group: usize) -> StringColumn {
+        self.map_opt_str(|s| {
+            re.captures(s)
+                .and_then(|caps| caps.get(group))
+                .map(|m| m.as_str().to_owned())
+        })
+    }
+}
+
+impl Default for StringColumn {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The dictionaries are merged, so each value is still stored once.
+impl Chunk for StringColumn {
+    fn append(&mut self, other: &StringColumn) {
+        let unified = CategoricalVec::unify(&self.values, &other.values);
+        let mut values = unified.categories;
+        values.append_remapped(&self.values, &unified.left);
+        values.append_remapped(&other.values, &unified.right);
+        self.values = values;
+        self.mask.extend(other.mask.iter());
+    }
+
+    /// Merges every dictionary at once, rather than once per chunk.
+    fn concat(chunks: &[StringColumn]) -> StringColumn {
+        let values: Vec<&CategoricalVec> = chunks.iter().map(|chunk| &chunk.values).collect();
+        let mut mask = BitVec::with_capacity(chunks.iter().map(|chunk| chunk.len()).sum());
+        for chunk in chunks {
+            mask.extend(chunk.mask.iter());
+        }
+        StringColumn {
+            values: CategoricalVec::concat(&values),
+            mask,
+        }
+    }
+}
+
+/// Columns are equal if they have the same rows, regardless
+/// of how the dictionary is laid out.
+impl PartialEq for StringColumn {
+    fn eq(&self, other: &StringColumn) -> bool {
+        self.len() == other.len() &&
+            (0..self.len()).all(|i| {
+                match (self.is_null(i), other.is_null(i)) {
+                    (true, true) => true,
+                    (false, false) => self.get(i) == other.get(i),
+                    _ => false,
+                }
+            })
+    }
+}
+
+/// Validates that every non-null value is utf8, reporting all offending
+/// rows if not. Validation is done once per distinct value.
+impl TryFrom<BinaryColumn> for StringColumn {
+    type Error = Error;
+
+    fn try_from(col: BinaryColumn) -> Result<StringColumn> {
+        let (values, mask) = col.into_parts();
+        let valid: Vec<bool> = values.categories()
+            .map(|bytes| str::from_utf8(bytes).is_ok())
+            .collect();
+
+        let rows: Vec<usize> = (0..values.len())
+            .filter(|&i| mask[i] && !valid[values.code(i).unwrap()])
+            .collect();
+        if !rows.is_empty() {
+            return Err(Error::InvalidUtf8 { rows });
+        }
+
+        // Invalid values can still be sitting under nulls, which
+        // can't be allowed into the string dictionary.
+        let values = if valid.iter().all(|&v| v) {
+            values
+        } else {
+            let mut valid = valid.iter();
+            values.map_categories(|bytes| {
+                if *valid.next().unwrap() { bytes.to_vec() } else { Vec::new() }
+            })
+        };
+        Ok(StringColumn::from_parts(values, mask))
+    }
+}
+
+impl From<StringColumn> for BinaryColumn {
+    fn from(col: StringColumn) -> Self {
+        BinaryColumn::from_parts(col.values, col.mask)
+    }
+}
+
+impl<'a> From<Vec<&'a str>> for StringColumn {
+    fn from(v: Vec<&'a str>) -> Self {
+        let mask = BitVec::from_elem(v.len(), true);
+        let values = v.into_iter().map(|s| s.as_bytes()).collect();
+        StringColumn::from_parts(values, mask)
+    }
+}
+
+impl<'a> From<Vec<Option<&'a str>>> for StringColumn {
+    fn from(v: Vec<Option<&'a str>>) -> Self {
+        let mask = v.iter().map(|s| s.is_some()).collect();
+        let values = v.into_iter().map(|s| s.unwrap_or("").as_bytes()).collect();
+        StringColumn::from_parts(values, mask)
+    }
+}
+
 // don't implement Index.
 // Can only use Get
 // The problem is that [] dereferences
@@ -123,4 +527,116 @@ mod tests {
         sa.push("one");
         sa.push("five");
     }
+
+    #[test]
+    fn case_and_trim() {
+        let col = StringColumn::from(vec![Some(" One"), None, Some("two "), Some(" One")]);
+        assert_eq!(
+            col.to_upper(),
+            StringColumn::from(vec![Some(" ONE"), None, Some("TWO "), Some(" ONE")])
+        );
+        assert_eq!(
+            col.trim().to_lower(),
+            StringColumn::from(vec![Some("one"), None, Some("two"), Some("one")])
+        );
+        assert_eq!(
+            col.str_len(),
+            Int64Column::from(vec![Some(4), None, Some(4), Some(4)])
+        );
+    }
+
+    #[test]
+    fn predicates() {
+        let col = StringColumn::from(vec![Some("apple"), Some("banana"), None]);
+        assert_eq!(
+            col.starts_with("ap"),
+            BooleanColumn::from(vec![Some(true), Some(false), None])
+        );
+        assert_eq!(
+            col.ends_with("na"),
+            BooleanColumn::from(vec![Some(false), Some(true), None])
+        );
+        assert_eq!(
+            col.contains("an"),
+            BooleanColumn::from(vec![Some(false), Some(true), None])
+        );
+        assert!(col.contains_value("apple"));
+        assert!(!col.contains_value("app"));
+    }
+
+    #[test]
+    fn replace_and_slice() {
+        let col = StringColumn::from(vec![Some("a-b-c"), None, Some("dé-f")]);
+        assert_eq!(
+            col.replace("-", "+"),
+            StringColumn::from(vec![Some("a+b+c"), None, Some("dé+f")])
+        );
+        assert_eq!(
+            col.slice(1, Some(3)),
+            StringColumn::from(vec![Some("-b"), None, Some("é-")])
+        );
+        assert_eq!(
+            col.slice(2, None),
+            StringColumn::from(vec![Some("b-c"), None, Some("-f")])
+        );
+    }
+
+    #[test]
+    fn split() {
+        let col = StringColumn::from(vec![Some("a-b-c"), None, Some("d-e"), Some("a-b-c")]);
+        let parts = col.split("-");
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0], StringColumn::from(vec![Some("a"), None, Some("d"), Some("a")]));
+        assert_eq!(parts[1], StringColumn::from(vec![Some("b"), None, Some("e"), Some("b")]));
+        assert_eq!(parts[2], StringColumn::from(vec![Some("c"), None, None, Some("c")]));
+    }
+
+    #[test]
+    fn regex_matches_and_replace() {
+        let col = StringColumn::from(vec![Some("ab12"), None, Some("cd"), Some("ab12")]);
+        assert_eq!(
+            col.matches(r"\d+").unwrap(),
+            BooleanColumn::from(vec![Some(true), None, Some(false), Some(true)])
+        );
+        assert_eq!(
+            col.replace_regex(r"(\w)(\d)", "$2$1").unwrap(),
+            StringColumn::from(vec![Some("a1b2"), None, Some("cd"), Some("a1b2")])
+        );
+        assert!(col.matches("(").is_err());
+    }
+
+    #[test]
+    fn regex_extract() {
+        let col = StringColumn::from(vec![Some("x=1, y=2"), Some("x=3"), None, Some("none")]);
+        assert_eq!(
+            col.extract(r"x=(\d)", 1).unwrap(),
+            StringColumn::from(vec![Some("1"), Some("3"), None, None])
+        );
+
+        let groups = col.extract_all(r"x=(\d)(?:, y=(\d))?").unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0], StringColumn::from(vec![Some("1"), Some("3"), None, None]));
+        assert_eq!(groups[1], StringColumn::from(vec![Some("2"), None, None, None]));
+    }
+
+    #[test]
+    fn try_from_binary() {
+        let bin = BinaryColumn::from(vec![Some(&b"one"[..]), None, Some(&b"two"[..])]);
+        let col = StringColumn::try_from(bin).unwrap();
+        assert_eq!(col, StringColumn::from(vec![Some("one"), None, Some("two")]));
+
+        let bin = BinaryColumn::from(col);
+        assert_eq!(bin.get(2), Some(&b"two"[..]));
+
+        let bin = BinaryColumn::from(vec![
+            Some(&b"ok"[..]),
+            Some(&[0xff, 0xfe][..]),
+            None,
+            Some(&[0xff, 0xfe][..]),
+        ]);
+        match StringColumn::try_from(bin) {
+            Err(Error::InvalidUtf8 { rows }) => assert_eq!(rows, vec![1, 3]),
+            res => panic!("expected invalid utf8, got {:?}", res),
+        }
+    }
 }
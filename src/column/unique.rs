@@ -0,0 +1,100 @@
+//! Distinct values
+//!
+//! `unique`, `n_unique` and `value_counts` are all read off
+//! `distinct_rows`: the first row and number of rows of each distinct
+//! value, in order of first appearance. By default that hashes every
+//! row's `Value`; string columns already know their distinct values, so
+//! just count codes, without hashing or comparing any strings.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use dataframe::DataFrame;
+use super::{Column, Float64Column, Int64Column, Value};
+
+pub(crate) fn distinct_rows(column: &dyn Column) -> Vec<(usize, usize)> {
+    let mut lookup: HashMap<Value, usize> = HashMap::new();
+    let mut res = Vec::new();
+    for i in 0..column.len() {
+        let next = res.len();
+        let position = *lookup.entry(column.get_value(i)).or_insert(next);
+        if position == next {
+            res.push((i, 0));
+        }
+        res[position].1 += 1;
+    }
+    res
+}
+
+pub(crate) fn unique(column: &dyn Column) -> Box<dyn Column> {
+    let rows: Vec<Option<usize>> = column.distinct_rows().into_iter().map(|(row, _)| Some(row)).collect();
+    column.take(&rows)
+}
+
+pub(crate) fn n_unique(column: &dyn Column) -> usize {
+    column.distinct_rows().into_iter().filter(|&(row, _)| !column.is_null(row)).count()
+}
+
+pub(crate) fn value_counts(column: &dyn Column, sort: bool, normalize: bool) -> DataFrame {
+    let mut distinct: Vec<_> = column.distinct_rows()
+        .into_iter()
+        .filter(|&(row, _)| !column.is_null(row))
+        .collect();
+    if sort {
+        // stable, so equal counts stay in order of appearance
+        distinct.sort_by_key(|&(_, count)| Reverse(count));
+    }
+    let rows: Vec<Option<usize>> = distinct.iter().map(|&(row, _)| Some(row)).collect();
+    let counts: Box<dyn Column> = if normalize {
+        let total: usize = distinct.iter().map(|&(_, count)| count).sum();
+        let shares: Vec<f64> = distinct.iter().map(|&(_, count)| count as f64 / total as f64).collect();
+        Box::new(Float64Column::from(shares))
+    } else {
+        Box::new(Int64Column::from(distinct.iter().map(|&(_, count)| count as i64).collect::<Vec<_>>()))
+    };
+    let mut res = DataFrame::new();
+    // same lengths, so can't fail
+    res.add_named_column("value", column.take(&rows)).unwrap();
+    res.add_named_column(if normalize { "proportion" } else { "count" }, counts).unwrap();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use column::{Column, Float64Column, Int64Column, Int8Column, StringColumn, Value};
+
+    #[test]
+    fn unique_and_counts() {
+        let col = Int8Column::from(vec![Some(2), None, Some(1), Some(2), None, Some(1), Some(1)]);
+        assert_eq!(col.distinct_rows(), vec![(0, 2), (1, 2), (2, 3)]);
+        assert_eq!(col.unique().as_any().downcast_ref::<Int8Column>().unwrap(), &Int8Column::from(vec![Some(2), None, Some(1)]));
+        assert_eq!(col.n_unique(), 2);
+
+        let counts = col.value_counts(true, false);
+        assert_eq!(counts.column_names(), &["value", "count"]);
+        assert_eq!(counts.column_as::<Int8Column>("value").unwrap(), &Int8Column::from(vec![1, 2]));
+        assert_eq!(counts.column_as::<Int64Column>("count").unwrap(), &Int64Column::from(vec![3, 2]));
+        let shares = col.value_counts(false, true);
+        assert_eq!(shares.column_as::<Float64Column>("proportion").unwrap(), &Float64Column::from(vec![0.4, 0.6]));
+
+        let nan = Float64Column::from(vec![f64::NAN, -f64::NAN]);
+        assert_eq!(nan.n_unique(), 1);
+        assert_eq!(Float64Column::from(vec![0.0, -0.0]).n_unique(), 1);
+    }
+
+    #[test]
+    fn strings() {
+        let col = StringColumn::from(vec![None, Some("b"), Some("a"), Some("b"), None, Some("")]);
+        assert_eq!(col.distinct_rows(), vec![(0, 2), (1, 2), (2, 1), (5, 1)]);
+        assert_eq!(
+            col.unique().as_any().downcast_ref::<StringColumn>().unwrap(),
+            &StringColumn::from(vec![None, Some("b"), Some("a"), Some("")])
+        );
+        assert_eq!(col.n_unique(), 3);
+
+        let counts = col.value_counts(true, false);
+        assert_eq!(counts.column("value").unwrap().get_value(0), Value::Str("b".to_owned()));
+        assert_eq!(counts.column_as::<Int64Column>("count").unwrap(), &Int64Column::from(vec![2, 1, 1]));
+        assert_eq!(StringColumn::new().n_unique(), 0);
+    }
+}
@@ -0,0 +1,303 @@
+//! Plain (non-dictionary) string dtype column
+//!
+//! Arrow-style layout: every value is appended to one data array,
+//! with offsets marking where each row starts and ends. Unlike
+//! StringColumn, there's no dictionary search on push and no code
+//! per row, so this works better for high cardinality data like ids
+//! or free text, where the dictionary would just duplicate the rows.
+//!
+//! StringStorage picks between the two layouts by looking at how
+//! many distinct values there are.
+
+use bit_vec::BitVec;
+use std::any::Any;
+use std::collections::HashSet;
+use std::convert::From;
+use std::ops::Index;
+use std::str;
+
+use error::{Error, Result};
+use super::{Column, Dtype, StringColumn, Value};
+
+/// If distinct values are at most this fraction of rows,
+/// StringStorage uses a dictionary.
+pub const DICTIONARY_MAX_RATIO: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Utf8Column {
+    // Row i is data[offsets[i]..offsets[i + 1]]
+    offsets: Vec<usize>,
+    data: Vec<u8>,
+    // false in the mask maps to null in values.
+    mask: BitVec,
+}
+
+impl Column for Utf8Column {
+    fn dtype(&self) -> Dtype {
+        Dtype::String
+    }
+
+    fn len(&self) -> usize {
+        Utf8Column::len(self)
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        Utf8Column::is_null(self, i)
+    }
+
+    fn get_value(&self, i: usize) -> Value {
+        if self.mask[i] { Value::Str(self[i].to_owned()) } else { Value::Null }
+    }
+
+    fn push_value(&mut self, value: Value) -> Result<()> {
+        match value {
+            Value::Null => self.push_null(),
+            Value::Str(ref s) => self.push(s),
+            value => return Err(Error::TypeMismatch {
+                expected: Dtype::String.to_string(),
+                found: value.kind().to_owned(),
+            }),
+        }
+        Ok(())
+    }
+
+    fn new_empty(&self) -> Box<dyn Column> {
+        Box::new(Utf8Column::new())
+    }
+
+    fn clone_box(&self) -> Box<dyn Column> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Utf8Column {
+    pub fn new() -> Self {
+        Utf8Column {
+            offsets: vec![0],
+            data: Vec::new(),
+            mask: BitVec::new(),
+        }
+    }
+
+    pub fn push(&mut self, s: &str) {
+        self.data.extend_from_slice(s.as_bytes());
+        self.offsets.push(self.data.len());
+        self.mask.push(true);
+    }
+
+    /// Nulls take no space in data.
+    pub fn push_null(&mut self) {
+        self.offsets.push(self.data.len());
+        self.mask.push(false);
+    }
+
+    pub fn get(&self, i: usize) -> Option<&str> {
+        if i < self.len() {
+            Some(&self[i])
+        } else {
+            None
+        }
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        !self.mask.get(i).unwrap_or(true)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mask.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.mask.len()
+    }
+
+    /// Number of distinct non-null values.
+    pub fn n_unique(&self) -> usize {
+        (0..self.len())
+            .filter(|&i| !self.is_null(i))
+            .map(|i| &self[i])
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+impl Default for Utf8Column {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Index<usize> for Utf8Column {
+    type Output = str;
+
+    fn index(&self, i: usize) -> &str {
+        // only utf8 is ever pushed
+        str::from_utf8(&self.data[self.offsets[i]..self.offsets[i + 1]]).unwrap()
+    }
+}
+
+impl<'a> From<Vec<&'a str>> for Utf8Column {
+    fn from(v: Vec<&'a str>) -> Self {
+        let mut col = Utf8Column::new();
+        for s in v {
+            col.push(s);
+        }
+        col
+    }
+}
+
+impl<'a> From<Vec<Option<&'a str>>> for Utf8Column {
+    fn from(v: Vec<Option<&'a str>>) -> Self {
+        let mut col = Utf8Column::new();
+        for s in v {
+            match s {
+                Some(s) => col.push(s),
+                None => col.push_null(),
+            }
+        }
+        col
+    }
+}
+
+impl From<StringColumn> for Utf8Column {
+    fn from(col: StringColumn) -> Self {
+        let mut res = Utf8Column::new();
+        for i in 0..col.len() {
+            if col.is_null(i) {
+                res.push_null();
+            } else {
+                res.push(&col[i]);
+            }
+        }
+        res
+    }
+}
+
+impl From<Utf8Column> for StringColumn {
+    fn from(col: Utf8Column) -> Self {
+        (0..col.len())
+            .map(|i| if col.is_null(i) { None } else { Some(&col[i]) })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+/// A string column in whichever layout suits its cardinality.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringStorage {
+    /// Dictionary encoded, for low cardinality.
+    Dictionary(StringColumn),
+    /// Offsets and data, for high cardinality.
+    Plain(Utf8Column),
+}
+
+impl StringStorage {
+    pub fn get(&self, i: usize) -> Option<&str> {
+        match *self {
+            StringStorage::Dictionary(ref col) => col.get(i),
+            StringStorage::Plain(ref col) => col.get(i),
+        }
+    }
+
+    pub fn is_null(&self, i: usize) -> bool {
+        match *self {
+            StringStorage::Dictionary(ref col) => col.is_null(i),
+            StringStorage::Plain(ref col) => col.is_null(i),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match *self {
+            StringStorage::Dictionary(ref col) => col.len(),
+            StringStorage::Plain(ref col) => col.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_dictionary(&self) -> bool {
+        match *self {
+            StringStorage::Dictionary(_) => true,
+            StringStorage::Plain(_) => false,
+        }
+    }
+
+    /// Switches layout if the observed cardinality calls for the other one.
+    pub fn optimize(self) -> Self {
+        match self {
+            StringStorage::Dictionary(col) => {
+                if use_dictionary(col.n_unique(), col.len()) {
+                    StringStorage::Dictionary(col)
+                } else {
+                    StringStorage::Plain(col.into())
+                }
+            },
+            StringStorage::Plain(col) => {
+                if use_dictionary(col.n_unique(), col.len()) {
+                    StringStorage::Dictionary(col.into())
+                } else {
+                    StringStorage::Plain(col)
+                }
+            },
+        }
+    }
+}
+
+/// Loads into the plain layout (which is cheap to build), then
+/// converts to a dictionary if there are few enough distinct values.
+impl<'a> From<Vec<Option<&'a str>>> for StringStorage {
+    fn from(v: Vec<Option<&'a str>>) -> Self {
+        StringStorage::Plain(Utf8Column::from(v)).optimize()
+    }
+}
+
+fn use_dictionary(distinct: usize, len: usize) -> bool {
+    len > 0 && distinct as f64 <= len as f64 * DICTIONARY_MAX_RATIO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_get() {
+        let mut col = Utf8Column::new();
+        col.push("one");
+        col.push_null();
+        col.push("two");
+        assert_eq!(col.len(), 3);
+        assert_eq!(col.get(0), Some("one"));
+        assert!(col.is_null(1));
+        assert_eq!(col.get(1), Some(""));
+        assert_eq!(&col[2], "two");
+        assert_eq!(col.get(3), None);
+        assert_eq!(col.n_unique(), 2);
+    }
+
+    #[test]
+    fn convert_layouts() {
+        let plain = Utf8Column::from(vec![Some("a"), None, Some("b"), Some("a")]);
+        let dict = StringColumn::from(plain.clone());
+        assert_eq!(dict, StringColumn::from(vec![Some("a"), None, Some("b"), Some("a")]));
+        assert_eq!(Utf8Column::from(dict), plain);
+    }
+
+    #[test]
+    fn storage_by_cardinality() {
+        let low = StringStorage::from(vec![Some("a"), Some("b"), Some("a"), None, Some("a")]);
+        assert!(low.is_dictionary());
+        assert_eq!(low.get(2), Some("a"));
+        assert!(low.is_null(3));
+
+        let high = StringStorage::from(vec![Some("a"), Some("b"), Some("c"), None]);
+        assert!(!high.is_dictionary());
+        assert_eq!(high.get(2), Some("c"));
+        assert_eq!(high.len(), 4);
+    }
+}
@@ -0,0 +1,343 @@
+//! A single dynamically typed value.
+//!
+//! The README says no Row struct, since it'd have to wrap every
+//! element. That still holds for rows; Value is only the currency
+//! of the dynamic Column interface, for the operations which have
+//! to work across dtypes (group-by keys, aggregation results, etc.).
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use super::datetime::{format_date, format_timestamp};
+use super::{Closed, Decimal, Freq, Interval, Unit};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Timestamp(i64, Unit),
+    Date(i32),
+    Timedelta(i64, Unit),
+    Period(i64, Freq),
+    /// Unscaled value and scale: `Decimal(1250, 2)` is 12.50.
+    Decimal(i128, u8),
+    /// Left and right bounds, and which of them are included.
+    Interval(Box<Value>, Box<Value>, Closed),
+    List(Vec<Value>),
+    /// (field name, value) pairs, in field order.
+    Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(*self, Value::Null)
+    }
+
+    /// Null or NaN, which sorting and ranking keep apart from values.
+    pub(crate) fn is_missing(&self) -> bool {
+        match *self {
+            Value::Null => true,
+            Value::Float(x) => x.is_nan(),
+            _ => false,
+        }
+    }
+
+    /// Ints, floats and decimals (truncated) as an i64.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int(x) => Some(x),
+            Value::Float(x) => Some(x as i64),
+            Value::Decimal(x, scale) => Some((x / 10i128.pow(scale as u32)) as i64),
+            _ => None,
+        }
+    }
+
+    /// Ints, floats and decimals as an f64.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Int(x) => Some(x as f64),
+            Value::Float(x) => Some(x),
+            Value::Decimal(x, scale) => Some(x as f64 / 10f64.powi(scale as i32)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::Str(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Tie-break between numerically equal values of different variants.
+    fn numeric_rank(&self) -> u8 {
+        match *self {
+            Value::Int(_) => 0,
+            Value::Float(_) => 1,
+            _ => 2,
+        }
+    }
+
+    /// Describes the kind of value, for errors.
+    pub(crate) fn kind(&self) -> &'static str {
+        match *self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Str(_) => "str",
+            Value::Bytes(_) => "bytes",
+            Value::Timestamp(..) => "timestamp",
+            Value::Date(_) => "date",
+            Value::Timedelta(..) => "timedelta",
+            Value::Period(..) => "period",
+            Value::Decimal(..) => "decimal",
+            Value::Interval(..) => "interval",
+            Value::List(_) => "list",
+            Value::Struct(_) => "struct",
+        }
+    }
+}
+
+/// Equality is exact, so that values can be used as keys: floats
+/// compare by bits once NaNs and zeros are normalized (NaN equals NaN,
+/// -0.0 equals 0.0), and values of different variants are never equal.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (&Value::Null, &Value::Null) => true,
+            (&Value::Bool(a), &Value::Bool(b)) => a == b,
+            (&Value::Int(a), &Value::Int(b)) => a == b,
+            (&Value::Float(a), &Value::Float(b)) => float_key(a) == float_key(b),
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (&Value::Timestamp(a, ua), &Value::Timestamp(b, ub)) => a == b && ua == ub,
+            (&Value::Date(a), &Value::Date(b)) => a == b,
+            (&Value::Timedelta(a, ua), &Value::Timedelta(b, ub)) => a == b && ua == ub,
+            (&Value::Period(a, fa), &Value::Period(b, fb)) => a == b && fa == fb,
+            (&Value::Decimal(a, sa), &Value::Decimal(b, sb)) => a == b && sa == sb,
+            (Value::Interval(la, ra, ca), Value::Interval(lb, rb, cb)) => la == lb && ra == rb && ca == cb,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Struct(a), Value::Struct(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            Value::Null => 0.hash(state),
+            Value::Bool(x) => x.hash(state),
+            Value::Int(x) => x.hash(state),
+            Value::Float(x) => float_key(x).hash(state),
+            Value::Str(ref x) => x.hash(state),
+            Value::Bytes(ref x) => x.hash(state),
+            Value::Timestamp(x, unit) | Value::Timedelta(x, unit) => {
+                x.hash(state);
+                unit.hash(state);
+            },
+            Value::Date(x) => x.hash(state),
+            Value::Period(x, freq) => {
+                x.hash(state);
+                freq.hash(state);
+            },
+            Value::Decimal(x, scale) => {
+                x.hash(state);
+                scale.hash(state);
+            },
+            Value::Interval(ref left, ref right, closed) => {
+                left.hash(state);
+                right.hash(state);
+                closed.hash(state);
+            },
+            Value::List(ref x) => x.hash(state),
+            Value::Struct(ref x) => x.hash(state),
+        }
+    }
+}
+
+/// Values of the same kind are ordered; ints, floats and decimals compare
+/// numerically with each other. Nulls sort first. Agrees with `==`:
+/// NaN only compares with NaN, and numerically equal values of
+/// different variants (like `1` and `1.0`) order by variant, ints
+/// first, then floats, then decimals by scale. Intervals with the same
+/// closed sides order by left bound, then right.
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (&Value::Null, &Value::Null) => Some(Ordering::Equal),
+            (&Value::Null, _) => Some(Ordering::Less),
+            (_, &Value::Null) => Some(Ordering::Greater),
+            (&Value::Bool(a), &Value::Bool(b)) => a.partial_cmp(&b),
+            (&Value::Int(a), &Value::Int(b)) => a.partial_cmp(&b),
+            (&Value::Float(a), &Value::Float(b)) if a.is_nan() && b.is_nan() => Some(Ordering::Equal),
+            (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+            (Value::Bytes(a), Value::Bytes(b)) => a.partial_cmp(b),
+            (&Value::Timestamp(a, ua), &Value::Timestamp(b, ub)) if ua == ub => a.partial_cmp(&b),
+            (&Value::Date(a), &Value::Date(b)) => a.partial_cmp(&b),
+            (&Value::Timedelta(a, ua), &Value::Timedelta(b, ub)) if ua == ub => a.partial_cmp(&b),
+            (&Value::Period(a, fa), &Value::Period(b, fb)) if fa == fb => a.partial_cmp(&b),
+            (&Value::Decimal(a, sa), &Value::Decimal(b, sb)) => {
+                Some(cmp_decimals(a, sa, b, sb).then(sa.cmp(&sb)))
+            },
+            (Value::Interval(la, ra, ca), Value::Interval(lb, rb, cb)) if ca == cb => {
+                (la, ra).partial_cmp(&(lb, rb))
+            },
+            (Value::List(a), Value::List(b)) => a.partial_cmp(b),
+            (Value::Struct(a), Value::Struct(b)) => a.partial_cmp(b),
+            _ => match (self.as_f64(), other.as_f64()) {
+                (Some(a), Some(b)) => {
+                    a.partial_cmp(&b).map(|ord| ord.then(self.numeric_rank().cmp(&other.numeric_rank())))
+                },
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Bits of `x`, with every NaN and both zeros mapped to one pattern.
+fn float_key(x: f64) -> u64 {
+    if x.is_nan() {
+        f64::NAN.to_bits()
+    } else if x == 0.0 {
+        0
+    } else {
+        x.to_bits()
+    }
+}
+
+/// Compares decimals at different scales, exactly when rescaling
+/// doesn't overflow.
+fn cmp_decimals(a: i128, sa: u8, b: i128, sb: u8) -> Ordering {
+    let rescale = |x: i128, from: u8| {
+        10i128.checked_pow(u32::from(sa.max(sb) - from)).and_then(|p| x.checked_mul(p))
+    };
+    match (rescale(a, sa), rescale(b, sb)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => {
+            let to_f64 = |x: i128, scale: u8| x as f64 / 10f64.powi(i32::from(scale));
+            to_f64(a, sa).partial_cmp(&to_f64(b, sb)).unwrap_or(Ordering::Equal)
+        },
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(x) => write!(f, "{}", x),
+            Value::Int(x) => write!(f, "{}", x),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Str(ref x) => write!(f, "{}", x),
+            Value::Bytes(ref x) => write!(f, "{:?}", x),
+            Value::Timestamp(x, unit) => write!(f, "{}", format_timestamp(x, unit)),
+            Value::Date(x) => write!(f, "{}", format_date(x)),
+            Value::Timedelta(x, unit) => write!(f, "{}{}", x, unit.suffix()),
+            Value::Period(x, freq) => write!(f, "{}", freq.format(x)),
+            Value::Decimal(x, scale) => write!(f, "{}", Decimal::new(x, scale)),
+            Value::Interval(ref left, ref right, closed) => {
+                write!(f, "{}", Interval { left: &**left, right: &**right, closed })
+            },
+            Value::List(ref x) => {
+                write!(f, "[")?;
+                for (i, x) in x.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", x)?;
+                }
+                write!(f, "]")
+            },
+            Value::Struct(ref x) => {
+                write!(f, "{{")?;
+                for (i, (name, x)) in x.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, x)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(x: bool) -> Self {
+        Value::Bool(x)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(x: i64) -> Self {
+        Value::Int(x)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(x: i32) -> Self {
+        Value::Int(x as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Float(x)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(x: &'a str) -> Self {
+        Value::Str(x.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(x: String) -> Self {
+        Value::Str(x)
+    }
+}
+
+impl<T> From<Option<T>> for Value
+    where T: Into<Value>
+{
+    fn from(x: Option<T>) -> Self {
+        x.map_or(Value::Null, |x| x.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eq_and_order() {
+        assert_eq!(Value::Float(f64::NAN), Value::Float(f64::NAN));
+        assert!(Value::Int(1) != Value::Float(1.0));
+        assert!(Value::Int(1) < Value::Float(1.5));
+        assert!(Value::Int(1) < Value::Float(1.0));
+        assert_eq!(Value::Float(0.0), Value::Float(-0.0));
+        assert_eq!(Value::Float(0.0).partial_cmp(&Value::Float(-0.0)), Some(Ordering::Equal));
+        assert_eq!(Value::Float(f64::NAN).partial_cmp(&Value::Float(-f64::NAN)), Some(Ordering::Equal));
+        assert_eq!(Value::Float(f64::NAN).partial_cmp(&Value::Float(1.0)), None);
+        assert!(Value::Decimal(10, 1) < Value::Decimal(100, 2));
+        assert!(Value::Decimal(11, 1) > Value::Decimal(100, 2));
+        assert!(Value::Null < Value::Int(-10));
+        assert!(Value::from("a") < Value::from("b"));
+        assert_eq!(Value::from(None::<i64>), Value::Null);
+        assert_eq!(Value::Int(1).partial_cmp(&Value::from("a")), None);
+        assert!(Value::List(vec![Value::Int(1)]) < Value::List(vec![Value::Int(1), Value::Null]));
+        assert_eq!(
+            Value::Struct(vec![("a".to_owned(), Value::List(vec![Value::Int(1), Value::Null]))]).to_string(),
+            "{a: [1, null]}"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+//! Window functions for numeric columns
+//!
+//! `rolling` reduces the last `window` rows at each row, `expanding`
+//! every row so far, and `ewm` weights earlier rows less and less. Each
+//! slides over the column once, adding the row entering the window and
+//! removing the one leaving it, rather than reducing every window from
+//! scratch: sums and moments are updated in place (Welford's method, for
+//! the variance), and minimums and maximums keep a monotonic deque of
+//! the candidates.
+//!
+//! Nulls and NaN are skipped, and don't count toward `min_periods`;
+//! rows whose window has fewer valid values come out null. Results are
+//! Float64 columns, the same length as the input.
+
+use bit_vec::BitVec;
+use std::collections::VecDeque;
+
+use super::{Float64Column, Primitive};
+
+/// Windows over a column; reduce them with `sum`, `mean` and so on.
+pub struct Rolling<'a, T: 'a> {
+    values: &'a [T],
+    mask: &'a BitVec,
+    // first row of the window ending at each row; never decreasing
+    starts: Vec<usize>,
+    min_periods: usize,
+}
+
+impl<'a, T: Primitive> Rolling<'a, T> {
+    /// The last `window` rows at each row. Panics if `window` is 0.
+    pub fn new(values: &'a [T], mask: &'a BitVec, window: usize, min_periods: usize) -> Self {
+        assert!(window > 0, "window must be positive");
+        let starts = (0..values.len()).map(|i| (i + 1).saturating_sub(window)).collect();
+        Rolling::with_starts(values, mask, starts, min_periods)
+    }
+
+    /// Every row so far, once there's a valid value.
+    pub fn expanding(values: &'a [T], mask: &'a BitVec) -> Self {
+        Rolling::with_starts(values, mask, vec![0; values.len()], 1)
+    }
+
+    /// The window ending at row `i` starts at `starts[i]`, which can't
+    /// decrease from one row to the next.
+    pub(crate) fn with_starts(values: &'a [T], mask: &'a BitVec, starts: Vec<usize>, min_periods: usize) -> Self {
+        assert_eq!(values.len(), mask.len());
+        assert_eq!(values.len(), starts.len());
+        assert!(starts.windows(2).all(|w| w[0] <= w[1]), "window starts must not decrease");
+        Rolling {
+            values,
+            mask,
+            starts,
+            min_periods,
+        }
+    }
+
+    pub fn sum(&self) -> Float64Column {
+        self.slide(Moments::default(), |m| Some(m.sum))
+    }
+
+    pub fn mean(&self) -> Float64Column {
+        self.slide(Moments::default(), |m| if m.count == 0 { None } else { Some(m.mean) })
+    }
+
+    /// Sample standard deviation (ddof 1), null for windows of one value.
+    pub fn std(&self) -> Float64Column {
+        self.slide(Moments::default(), |m| {
+            if m.count < 2 { None } else { Some((m.m2.max(0.0) / (m.count - 1) as f64).sqrt()) }
+        })
+    }
+
+    pub fn min(&self) -> Float64Column {
+        self.slide(Extreme::new(|a, b| a <= b), Extreme::value)
+    }
+
+    pub fn max(&self) -> Float64Column {
+        self.slide(Extreme::new(|a, b| a >= b), Extreme::value)
+    }
+
+    fn valid(&self, i: usize) -> bool {
+        self.mask[i] && self.values[i].is_comparable()
+    }
+
+    /// Slides `acc` over the windows, reading each with `value`.
+    fn slide<A, F>(&self, mut acc: A, value: F) -> Float64Column
+        where A: Accumulator,
+              F: Fn(&A) -> Option<f64>
+    {
+        let mut res = Vec::with_capacity(self.values.len());
+        let mut count = 0;
+        let mut start = 0;
+        for (i, &next_start) in self.starts.iter().enumerate() {
+            if self.valid(i) {
+                acc.push(i, self.values[i].to_f64());
+                count += 1;
+            }
+            while start < next_start {
+                if self.valid(start) {
+                    acc.pop(start, self.values[start].to_f64());
+                    count -= 1;
+                }
+                start += 1;
+            }
+            res.push(if count >= self.min_periods { value(&acc) } else { None });
+        }
+        Float64Column::from(res)
+    }
+}
+
+/// State of a window, as rows enter and leave it in order.
+trait Accumulator {
+    fn push(&mut self, i: usize, x: f64);
+
+    /// Only called with the oldest row still in the window.
+    fn pop(&mut self, i: usize, x: f64);
+}
+
+#[derive(Default)]
+struct Moments {
+    count: usize,
+    sum: f64,
+    mean: f64,
+    // sum of squared deviations from the mean
+    m2: f64,
+}
+
+impl Accumulator for Moments {
+    fn push(&mut self, _: usize, x: f64) {
+        self.count += 1;
+        self.sum += x;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn pop(&mut self, _: usize, x: f64) {
+        self.count -= 1;
+        if self.count == 0 {
+            // start afresh, rather than carrying rounding error
+            *self = Moments::default();
+            return;
+        }
+        self.sum -= x;
+        let delta = x - self.mean;
+        self.mean -= delta / self.count as f64;
+        self.m2 -= delta * (x - self.mean);
+    }
+}
+
+/// Candidates for the extreme of the window: rows which aren't beaten
+/// by a later row, so the front is the extreme.
+struct Extreme<F> {
+    deque: VecDeque<(usize, f64)>,
+    // whether the first value is at least as extreme as the second
+    keeps: F,
+}
+
+impl<F: Fn(f64, f64) -> bool> Extreme<F> {
+    fn new(keeps: F) -> Self {
+        Extreme {
+            deque: VecDeque::new(),
+            keeps,
+        }
+    }
+
+    fn value(&self) -> Option<f64> {
+        self.deque.front().map(|&(_, x)| x)
+    }
+}
+
+impl<F: Fn(f64, f64) -> bool> Accumulator for Extreme<F> {
+    fn push(&mut self, i: usize, x: f64) {
+        while self.deque.back().is_some_and(|&(_, back)| !(self.keeps)(back, x)) {
+            self.deque.pop_back();
+        }
+        self.deque.push_back((i, x));
+    }
+
+    fn pop(&mut self, i: usize, _: f64) {
+        if self.deque.front().is_some_and(|&(front, _)| front == i) {
+            self.deque.pop_front();
+        }
+    }
+}
+
+/// Exponentially weighted windows over a column.
+pub struct Ewm<'a, T: 'a> {
+    values: &'a [T],
+    mask: &'a BitVec,
+    alpha: f64,
+}
+
+impl<'a, T: Primitive> Ewm<'a, T> {
+    /// Panics unless `0 < alpha <= 1`.
+    pub fn new(values: &'a [T], mask: &'a BitVec, alpha: f64) -> Self {
+        assert!(alpha > 0.0 && alpha <= 1.0, "alpha must be in (0, 1]");
+        assert_eq!(values.len(), mask.len());
+        Ewm {
+            values,
+            mask,
+            alpha,
+        }
+    }
+
+    /// Weighted mean of the values so far, the value `k` rows back
+    /// weighted by `(1 - alpha)^k` (pandas' `adjust=True`). Skipped
+    /// rows still age the earlier values, and repeat the last mean.
+    pub fn mean(&self) -> Float64Column {
+        let decay = 1.0 - self.alpha;
+        let (mut total, mut weight) = (0.0, 0.0);
+        let res: Vec<Option<f64>> = self.values.iter()
+            .enumerate()
+            .map(|(i, &x)| {
+                total *= decay;
+                weight *= decay;
+                if self.mask[i] && x.is_comparable() {
+                    total += x.to_f64();
+                    weight += 1.0;
+                }
+                if weight > 0.0 { Some(total / weight) } else { None }
+            })
+            .collect();
+        Float64Column::from(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use column::{Float64Column, Int32Column, Numeric};
+
+    fn assert_close(a: &Float64Column, b: Vec<Option<f64>>) {
+        let a: Vec<Option<f64>> = a.into_iter().map(|x| x.cloned()).collect();
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(&b) {
+            match (*x, *y) {
+                (Some(x), Some(y)) => assert!((x - y).abs() < 1e-9, "{:?} != {:?}", a, b),
+                (x, y) => assert_eq!(x, y, "{:?} != {:?}", a, b),
+            }
+        }
+    }
+
+    #[test]
+    fn rolling() {
+        let col = Int32Column::from(vec![Some(1), Some(3), None, Some(2), Some(6), Some(4)]);
+        let rolling = col.rolling(3, 2);
+        assert_close(&rolling.sum(), vec![None, Some(4.0), Some(4.0), Some(5.0), Some(8.0), Some(12.0)]);
+        assert_close(&rolling.mean(), vec![None, Some(2.0), Some(2.0), Some(2.5), Some(4.0), Some(4.0)]);
+        assert_close(&rolling.min(), vec![None, Some(1.0), Some(1.0), Some(2.0), Some(2.0), Some(2.0)]);
+        assert_close(&rolling.max(), vec![None, Some(3.0), Some(3.0), Some(3.0), Some(6.0), Some(6.0)]);
+        assert_close(&rolling.std(), vec![
+            None, Some(2f64.sqrt()), Some(2f64.sqrt()), Some(0.5f64.sqrt()), Some(8f64.sqrt()), Some(2.0),
+        ]);
+        assert_close(&col.rolling(1, 1).sum(), vec![Some(1.0), Some(3.0), None, Some(2.0), Some(6.0), Some(4.0)]);
+    }
+
+    #[test]
+    fn expanding_and_nan() {
+        let col = Float64Column::from(vec![None, Some(2.0), Some(f64::NAN), Some(1.0), Some(3.0)]);
+        let expanding = col.expanding();
+        assert_close(&expanding.sum(), vec![None, Some(2.0), Some(2.0), Some(3.0), Some(6.0)]);
+        assert_close(&expanding.min(), vec![None, Some(2.0), Some(2.0), Some(1.0), Some(1.0)]);
+        assert_close(&expanding.max(), vec![None, Some(2.0), Some(2.0), Some(2.0), Some(3.0)]);
+        assert_close(&col.rolling(2, 0).sum(), vec![Some(0.0), Some(2.0), Some(2.0), Some(1.0), Some(4.0)]);
+    }
+
+    #[test]
+    fn ewm() {
+        let col = Float64Column::from(vec![Some(1.0), None, Some(3.0)]);
+        // weights 0.25 and 1 for 1 and 3
+        assert_close(&col.ewm(0.5).mean(), vec![Some(1.0), Some(1.0), Some((0.25 + 3.0) / 1.25)]);
+        assert_close(&Float64Column::from(vec![None, Some(2.0)]).ewm(1.0).mean(), vec![None, Some(2.0)]);
+    }
+}
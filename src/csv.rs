@@ -0,0 +1,418 @@
+//! Reading CSV
+//!
+//! A CsvReader holds CSV text, from a file or anything `Read`, and
+//! parses it into a frame. Fields are split on a delimiter (`,` unless
+//! set) and can be quoted with `"`, doubling any quotes inside, so a
+//! quoted field can hold delimiters and newlines. The first record is
+//! the header, unless `with_header(false)`, which names the columns
+//! `column_0` and so on. Blank lines are skipped.
+//!
+//! Each column's dtype is inferred from its fields: Int64 if they're
+//! all ints, Float64 if they're all numbers, Boolean if they're all
+//! `true` or `false`, and String otherwise. Empty fields are null.
+//!
+//! `lazy` makes the reader the source of a LazyFrame, so the optimizer
+//! can push projections and filters into the scan: only the columns
+//! the plan reads are converted, and rows are parsed in batches of
+//! `BATCH_ROWS`, with the predicate evaluated on each batch before the
+//! rest of its columns, so rejected rows are skipped early.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use column::{empty_column, Column, Dtype, Value};
+use dataframe::DataFrame;
+use error::{Error, Result};
+use lazy::{selected_rows, Expr, LazyFrame};
+
+/// Rows parsed at a time.
+pub const BATCH_ROWS: usize = 1 << 16;
+
+#[derive(Debug, Clone)]
+pub struct CsvReader {
+    text: String,
+    delimiter: u8,
+    has_header: bool,
+}
+
+impl CsvReader {
+    /// Reads the whole file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        CsvReader::from_reader(File::open(path)?)
+    }
+
+    /// Reads everything from `reader`, which must be utf8.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(CsvReader {
+            text,
+            delimiter: b',',
+            has_header: true,
+        })
+    }
+
+    /// Splits fields on `delimiter` instead of `,`. It must be an
+    /// ASCII character other than a quote or line break.
+    pub fn with_delimiter(self, delimiter: char) -> Result<Self> {
+        if !delimiter.is_ascii() || delimiter == '"' || delimiter == '\n' || delimiter == '\r' {
+            return Err(Error::TypeMismatch {
+                expected: "an ASCII delimiter other than a quote or line break".to_owned(),
+                found: format!("{:?}", delimiter),
+            });
+        }
+        Ok(CsvReader {
+            delimiter: delimiter as u8,
+            ..self
+        })
+    }
+
+    /// Whether the first record names the columns, which it does by
+    /// default.
+    pub fn with_header(self, has_header: bool) -> Self {
+        CsvReader {
+            has_header,
+            ..self
+        }
+    }
+
+    /// Names of the columns, in file order.
+    pub fn column_names(&self) -> Vec<String> {
+        let first = self.records().next().unwrap_or_default();
+        if self.has_header {
+            first.into_iter().map(Cow::into_owned).collect()
+        } else {
+            (0..first.len()).map(|i| format!("column_{}", i)).collect()
+        }
+    }
+
+    /// Every column and row.
+    pub fn finish(&self) -> Result<DataFrame> {
+        self.read(None, None)
+    }
+
+    /// Defers reading until the LazyFrame is collected.
+    pub fn lazy(self) -> LazyFrame {
+        LazyFrame::from(self)
+    }
+
+    /// The `projection` columns (all if `None`), in that order, of the
+    /// rows where `predicate` is true. Only those columns and the ones
+    /// `predicate` reads are converted.
+    pub(crate) fn read(&self, projection: Option<&[String]>, predicate: Option<&Expr>) -> Result<DataFrame> {
+        let names = self.column_names();
+        let position = |name: &str| {
+            names.iter().position(|n| n == name).ok_or_else(|| Error::ColumnNotFound(name.to_owned()))
+        };
+        let projected = match projection {
+            Some(projection) => projection.iter().map(|name| position(name)).collect::<Result<Vec<_>>>()?,
+            None => (0..names.len()).collect(),
+        };
+        let mut filtered = match predicate {
+            Some(predicate) => predicate.columns().iter().map(|name| position(name)).collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        filtered.sort();
+        // a predicate reading no columns still needs the batch's length
+        if filtered.is_empty() {
+            filtered.extend(projected.first().cloned());
+        }
+        let dtypes = self.infer(&names, projected.iter().chain(&filtered).cloned())?;
+
+        let mut columns: Vec<Box<dyn Column>> = projected.iter().map(|&c| new_column(dtypes[c])).collect();
+        let mut records = self.data_records();
+        let mut first_row = 0;
+        loop {
+            let batch: Vec<_> = records.by_ref().take(BATCH_ROWS).collect();
+            if batch.is_empty() {
+                break;
+            }
+            let keep: Vec<usize> = match predicate {
+                Some(predicate) => {
+                    let mut df = DataFrame::new();
+                    for &c in &filtered {
+                        let mut column = new_column(dtypes[c]);
+                        for (i, fields) in batch.iter().enumerate() {
+                            column.push_value(parse(&fields[c], dtypes[c], first_row + i)?)?;
+                        }
+                        df.add_named_column(&names[c], column)?;
+                    }
+                    selected_rows(&*predicate.evaluate(&df)?)?.into_iter().flatten().collect()
+                },
+                None => (0..batch.len()).collect(),
+            };
+            for (column, &c) in columns.iter_mut().zip(&projected) {
+                for &i in &keep {
+                    column.push_value(parse(&batch[i][c], dtypes[c], first_row + i)?)?;
+                }
+            }
+            first_row += batch.len();
+        }
+
+        let mut res = DataFrame::new();
+        for (column, &c) in columns.into_iter().zip(&projected) {
+            res.add_named_column(&names[c], column)?;
+        }
+        Ok(res)
+    }
+
+    /// Dtype of each of `columns` (others are left as String), checking
+    /// every record has a field per name.
+    fn infer<I>(&self, names: &[String], columns: I) -> Result<Vec<Dtype>>
+        where I: Iterator<Item = usize>
+    {
+        let mut columns: Vec<usize> = columns.collect();
+        columns.sort();
+        columns.dedup();
+        let mut dtypes: Vec<Option<Dtype>> = vec![None; names.len()];
+        for (row, fields) in self.data_records().enumerate() {
+            if fields.len() != names.len() {
+                return Err(Error::FieldCount {
+                    row,
+                    expected: names.len(),
+                    found: fields.len(),
+                });
+            }
+            for &c in &columns {
+                if fields[c].is_empty() || dtypes[c] == Some(Dtype::String) {
+                    continue;
+                }
+                let dtype = field_dtype(&fields[c]);
+                dtypes[c] = Some(match dtypes[c] {
+                    Some(before) => join_dtypes(before, dtype),
+                    None => dtype,
+                });
+            }
+        }
+        // all-null columns are strings
+        Ok(dtypes.into_iter().map(|dtype| dtype.unwrap_or(Dtype::String)).collect())
+    }
+
+    fn records(&self) -> Records<'_> {
+        Records {
+            text: &self.text,
+            pos: 0,
+            delimiter: self.delimiter,
+        }
+    }
+
+    /// Records after the header, if there is one.
+    fn data_records(&self) -> Records<'_> {
+        let mut records = self.records();
+        if self.has_header {
+            records.next();
+        }
+        records
+    }
+}
+
+fn new_column(dtype: Dtype) -> Box<dyn Column> {
+    // only called with the dtypes `field_dtype` gives
+    empty_column(dtype).unwrap()
+}
+
+/// Narrowest dtype which can hold a non-empty field.
+fn field_dtype(field: &str) -> Dtype {
+    if field.parse::<i64>().is_ok() {
+        Dtype::Int64
+    } else if field.parse::<f64>().is_ok() {
+        Dtype::Float64
+    } else if field == "true" || field == "false" {
+        Dtype::Boolean
+    } else {
+        Dtype::String
+    }
+}
+
+/// Dtype which can hold fields of both `a` and `b`.
+fn join_dtypes(a: Dtype, b: Dtype) -> Dtype {
+    match (a, b) {
+        _ if a == b => a,
+        (Dtype::Int64, Dtype::Float64) | (Dtype::Float64, Dtype::Int64) => Dtype::Float64,
+        _ => Dtype::String,
+    }
+}
+
+/// The value of a field at `row`, in a column of `dtype`.
+fn parse(field: &str, dtype: Dtype, row: usize) -> Result<Value> {
+    if field.is_empty() {
+        return Ok(Value::Null);
+    }
+    let (value, target) = match dtype {
+        Dtype::Int64 => (field.parse().ok().map(Value::Int), "int64"),
+        Dtype::Float64 => (field.parse().ok().map(Value::Float), "float64"),
+        Dtype::Boolean => (field.parse().ok().map(Value::Bool), "boolean"),
+        _ => (Some(Value::Str(field.to_owned())), "string"),
+    };
+    value.ok_or_else(|| Error::Parse {
+        row,
+        value: field.to_owned(),
+        target,
+    })
+}
+
+/// Splits CSV text into records of unquoted fields.
+struct Records<'a> {
+    text: &'a str,
+    pos: usize,
+    delimiter: u8,
+}
+
+impl<'a> Records<'a> {
+    fn at_field_end(&self) -> bool {
+        match self.text.as_bytes().get(self.pos) {
+            Some(&b) => b == self.delimiter || b == b'\n' || b == b'\r',
+            None => true,
+        }
+    }
+
+    /// The field starting at `pos`, leaving `pos` just after it.
+    fn field(&mut self) -> Cow<'a, str> {
+        let text = self.text;
+        let start = self.pos;
+        if text.as_bytes().get(start) != Some(&b'"') {
+            while !self.at_field_end() {
+                self.pos += 1;
+            }
+            return Cow::Borrowed(&text[start..self.pos]);
+        }
+
+        // runs to a quote which isn't doubled, or the end of the text
+        let mut field = Cow::Borrowed("");
+        self.pos += 1;
+        loop {
+            match text[self.pos..].find('"') {
+                Some(offset) => {
+                    let quote = self.pos + offset;
+                    append(&mut field, &text[self.pos..quote]);
+                    self.pos = quote + 1;
+                    if text.as_bytes().get(self.pos) == Some(&b'"') {
+                        append(&mut field, "\"");
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                },
+                None => {
+                    append(&mut field, &text[self.pos..]);
+                    self.pos = text.len();
+                    break;
+                },
+            }
+        }
+        // anything between the closing quote and the delimiter is kept
+        let rest = self.pos;
+        while !self.at_field_end() {
+            self.pos += 1;
+        }
+        append(&mut field, &text[rest..self.pos]);
+        field
+    }
+}
+
+fn append<'a>(field: &mut Cow<'a, str>, s: &'a str) {
+    if field.is_empty() {
+        *field = Cow::Borrowed(s);
+    } else if !s.is_empty() {
+        field.to_mut().push_str(s);
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Vec<Cow<'a, str>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.text.as_bytes();
+        while self.pos < bytes.len() {
+            let mut fields = vec![self.field()];
+            while bytes.get(self.pos) == Some(&self.delimiter) {
+                self.pos += 1;
+                fields.push(self.field());
+            }
+            if bytes.get(self.pos) == Some(&b'\r') {
+                self.pos += 1;
+            }
+            if bytes.get(self.pos) == Some(&b'\n') {
+                self.pos += 1;
+            }
+            // blank lines aren't records
+            if fields.len() > 1 || !fields[0].is_empty() {
+                return Some(fields);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{BooleanColumn, Float64Column, Int64Column, StringColumn};
+    use lazy::{col, lit};
+
+    const TEXT: &str = "name,x,y,ok\r\na,1,0.5,true\n\"b, \"\"quoted\"\"\",,2,false\n\nc,3,,\n";
+
+    fn reader() -> CsvReader {
+        CsvReader::from_reader(TEXT.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn records() {
+        let reader = reader();
+        let records: Vec<_> = reader.records().collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[2], vec!["b, \"quoted\"", "", "2", "false"]);
+        assert_eq!(records[3], vec!["c", "3", "", ""]);
+
+        let multiline = CsvReader::from_reader("a;b\n\"x\ny\";1".as_bytes()).unwrap().with_delimiter(';').unwrap();
+        assert_eq!(multiline.records().nth(1).unwrap(), vec!["x\ny", "1"]);
+        assert!(reader.with_delimiter('"').is_err());
+    }
+
+    #[test]
+    fn finish() {
+        let df = reader().finish().unwrap();
+        assert_eq!(df.column_names(), &["name", "x", "y", "ok"]);
+        assert_eq!(
+            df.column_as::<StringColumn>("name").unwrap(),
+            &StringColumn::from(vec!["a", "b, \"quoted\"", "c"])
+        );
+        assert_eq!(df.column_as::<Int64Column>("x").unwrap(), &Int64Column::from(vec![Some(1), None, Some(3)]));
+        assert_eq!(
+            df.column_as::<Float64Column>("y").unwrap(),
+            &Float64Column::from(vec![Some(0.5), Some(2.0), None])
+        );
+        assert_eq!(
+            df.column_as::<BooleanColumn>("ok").unwrap(),
+            &BooleanColumn::from(vec![Some(true), Some(false), None])
+        );
+
+        let headless = CsvReader::from_reader("1,x\n2,y".as_bytes()).unwrap().with_header(false);
+        assert_eq!(headless.finish().unwrap().column_names(), &["column_0", "column_1"]);
+        match CsvReader::from_reader("a,b\n1,2\n3".as_bytes()).unwrap().finish() {
+            Err(Error::FieldCount { row: 1, expected: 2, found: 1 }) => (),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    #[test]
+    fn projection_and_predicate() {
+        let projection = ["y".to_owned(), "x".to_owned()];
+        let predicate = col("name").not_equal(lit("a"));
+        let df = reader().read(Some(&projection), Some(&predicate)).unwrap();
+        assert_eq!(df.column_names(), &["y", "x"]);
+        assert_eq!(df.column_as::<Int64Column>("x").unwrap(), &Int64Column::from(vec![None, Some(3)]));
+
+        let df = reader().read(Some(&["x".to_owned()]), Some(&col("x").equal(lit(3)))).unwrap();
+        assert_eq!(df.column_as::<Int64Column>("x").unwrap(), &Int64Column::from(vec![3]));
+        let df = reader().read(Some(&["ok".to_owned(), "x".to_owned()]), Some(&lit(false))).unwrap();
+        assert_eq!(df.column_names(), &["ok", "x"]);
+        assert_eq!(df.len(), 0);
+        match reader().read(Some(&["z".to_owned()]), None) {
+            Err(Error::ColumnNotFound(ref name)) if name == "z" => (),
+            res => panic!("{:?}", res),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Summary statistics, like pandas' `describe()`.
+//!
+//! The result has a `statistic` column naming each row, then one
+//! column per described column, in the original order. Numeric
+//! columns (ints, floats and decimals, chunked or not) get float
+//! columns of count, null count, mean, std, min, quartiles and max.
+//! String columns get string columns of count, unique, top (most
+//! common value) and freq (its count), found from the dictionary codes
+//! rather than by comparing strings; plain Utf8 columns are converted
+//! to a dictionary first.
+//!
+//! Each kind only gets its own statistics; the other kind's rows are
+//! null. Columns of other dtypes are left out.
+
+use std::borrow::Cow;
+
+use column::{
+    visit_numeric, Column, Cumulative, Dtype, Float64Column, Interpolation, Numeric,
+    NumericVisitor, Primitive, StringColumn, Utf8Column,
+};
+use error::Result;
+use super::DataFrame;
+
+const NUMERIC_STATS: [&str; 9] = ["count", "null_count", "mean", "std", "min", "25%", "50%", "75%", "max"];
+
+const STRING_STATS: [&str; 4] = ["count", "unique", "top", "freq"];
+
+pub(crate) fn describe(df: &DataFrame) -> Result<DataFrame> {
+    let numeric: Vec<_> = df.columns()
+        .map(|(_, column)| visit_numeric(column, Stats))
+        .collect();
+    let has_numeric = numeric.iter().any(Option::is_some);
+    let has_strings = df.columns().any(|(_, column)| column.dtype() == Dtype::String);
+
+    // numeric rows come first, but only if there's something to
+    // describe with them (or nothing at all)
+    let numeric_rows = if has_numeric || !has_strings { NUMERIC_STATS.len() } else { 0 };
+    let mut statistics: Vec<&str> = NUMERIC_STATS[..numeric_rows].to_vec();
+    if has_strings {
+        statistics.extend(STRING_STATS.iter().skip(if numeric_rows > 0 { 1 } else { 0 }));
+    }
+
+    let mut res = DataFrame::new();
+    res.add_named_column("statistic", Box::new(StringColumn::from(statistics.clone())))?;
+    for ((name, column), stats) in df.columns().zip(numeric) {
+        let described: Box<dyn Column> = if let Some(stats) = stats {
+            let mut values = stats[..numeric_rows].to_vec();
+            values.resize(statistics.len(), None);
+            Box::new(Float64Column::from(values))
+        } else if let Some(strings) = string_column(column) {
+            let stats = string_stats(&strings);
+            // count is shared with the numeric rows
+            let mut values = vec![None; numeric_rows];
+            if numeric_rows > 0 {
+                values[0] = stats[0].clone();
+            }
+            values.extend(stats.into_iter().skip(if numeric_rows > 0 { 1 } else { 0 }));
+            let values: Vec<Option<&str>> = values.iter().map(|s| s.as_ref().map(|s| s.as_str())).collect();
+            Box::new(StringColumn::from(values))
+        } else {
+            continue;
+        };
+        res.add_named_column(name, described)?;
+    }
+    Ok(res)
+}
+
+/// Statistics in `NUMERIC_STATS` order.
+struct Stats;
+
+impl NumericVisitor for Stats {
+    type Output = Vec<Option<f64>>;
+
+    fn visit<C>(self, column: &C) -> Vec<Option<f64>>
+        where C: Numeric + Cumulative,
+              C::Item: Primitive
+    {
+        vec![
+            Some(column.count() as f64),
+            Some(column.null_count() as f64),
+            column.mean(),
+            column.std(1),
+            column.min().map(Primitive::to_f64),
+            column.quantile(0.25, Interpolation::Linear),
+            column.median(),
+            column.quantile(0.75, Interpolation::Linear),
+            column.max().map(Primitive::to_f64),
+        ]
+    }
+}
+
+/// `column` as a dictionary string column, if it's a string column.
+fn string_column(column: &dyn Column) -> Option<Cow<'_, StringColumn>> {
+    if let Some(flat) = column.flatten() {
+        return string_column(&*flat).map(|strings| Cow::Owned(strings.into_owned()));
+    }
+    let any = column.as_any();
+    if let Some(strings) = any.downcast_ref::<StringColumn>() {
+        Some(Cow::Borrowed(strings))
+    } else {
+        any.downcast_ref::<Utf8Column>().map(|strings| Cow::Owned(strings.clone().into()))
+    }
+}
+
+/// Statistics in `STRING_STATS` order.
+fn string_stats(column: &StringColumn) -> Vec<Option<String>> {
+    let count = (0..column.len()).filter(|&i| !column.is_null(i)).count();
+    let top = column.top();
+    vec![
+        Some(count.to_string()),
+        Some(column.n_unique().to_string()),
+        top.map(|(value, _)| value.to_owned()),
+        top.map(|(_, freq)| freq.to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use column::{
+        ChunkedColumn, Column, DecimalColumn, Float64Column, Int8Column, StringColumn, Utf8Column, Value,
+    };
+    use dataframe::DataFrame;
+
+    #[test]
+    fn numeric_and_strings() {
+        let df = DataFrame::from_columns(vec![
+            ("name", Box::new(StringColumn::from(vec![Some("b"), Some("a"), None, Some("a")])) as Box<dyn Column>),
+            ("flag", Box::new(::column::BooleanColumn::from(vec![true, false, true, true]))),
+            ("x", Box::new(Int8Column::from(vec![Some(100), Some(100), None, Some(-128)]))),
+        ]).unwrap();
+        let res = df.describe().unwrap();
+        assert_eq!(res.column_names(), &["statistic", "name", "x"]);
+        assert_eq!(
+            res.column_as::<StringColumn>("statistic").unwrap(),
+            &StringColumn::from(vec![
+                "count", "null_count", "mean", "std", "min", "25%", "50%", "75%", "max",
+                "unique", "top", "freq",
+            ])
+        );
+
+        let x = res.column_as::<Float64Column>("x").unwrap();
+        assert_eq!(x.get_value(0), Value::Float(3.0));
+        assert_eq!(x.get_value(1), Value::Float(1.0));
+        assert_eq!(x.get_value(2), Value::Float(24.0));
+        assert_eq!(x.get_value(4), Value::Float(-128.0));
+        assert_eq!(x.get_value(5), Value::Float(-14.0));
+        assert_eq!(x.get_value(8), Value::Float(100.0));
+        assert_eq!(x.get_value(10), Value::Null);
+
+        assert_eq!(
+            res.column_as::<StringColumn>("name").unwrap(),
+            &StringColumn::from(vec![
+                Some("3"), None, None, None, None, None, None, None, None,
+                Some("2"), Some("a"), Some("2"),
+            ])
+        );
+    }
+
+    #[test]
+    fn strings_only() {
+        let df = DataFrame::from_columns(vec![
+            ("s", Box::new(StringColumn::from(vec![Some("x"), Some("y")])) as Box<dyn Column>),
+            ("empty", Box::new(StringColumn::from(vec![None, None]))),
+        ]).unwrap();
+        let res = df.describe().unwrap();
+        assert_eq!(res.len(), 4);
+        assert_eq!(
+            res.column_as::<StringColumn>("s").unwrap(),
+            &StringColumn::from(vec!["2", "2", "x", "1"])
+        );
+        assert_eq!(
+            res.column_as::<StringColumn>("empty").unwrap(),
+            &StringColumn::from(vec![Some("0"), Some("0"), None, None])
+        );
+    }
+
+    #[test]
+    fn other_column_types() {
+        let df = DataFrame::from_columns(vec![
+            ("chunked", Box::new(ChunkedColumn::from_chunks(vec![
+                Int8Column::from(vec![1]),
+                Int8Column::from(vec![Some(3), None]),
+            ])) as Box<dyn Column>),
+            ("decimal", Box::new(DecimalColumn::from_values(vec![Some(150), Some(250), None], 5, 2).unwrap())),
+            ("utf8", Box::new(Utf8Column::from(vec![Some("x"), Some("x"), None]))),
+        ]).unwrap();
+        let res = df.describe().unwrap();
+        assert_eq!(res.column_names(), &["statistic", "chunked", "decimal", "utf8"]);
+        let chunked = res.column_as::<Float64Column>("chunked").unwrap();
+        assert_eq!(chunked.get_value(0), Value::Float(2.0));
+        assert_eq!(chunked.get_value(2), Value::Float(2.0));
+        let decimal = res.column_as::<Float64Column>("decimal").unwrap();
+        assert_eq!(decimal.get_value(8), Value::Float(2.5));
+        let utf8 = res.column_as::<StringColumn>("utf8").unwrap();
+        assert_eq!(utf8.get(0), Some("2"));
+        assert_eq!(utf8.get(10), Some("x"));
+    }
+}
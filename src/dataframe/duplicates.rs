@@ -0,0 +1,116 @@
+//! Duplicate rows
+//!
+//! Rows are duplicates if they have equal values in every column of a
+//! subset. Each row's values are hashed into one u64, so rows are only
+//! compared value by value when their hashes collide, and no key is
+//! built per row (unlike group-by, which keeps its keys).
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use column::{BooleanColumn, Column};
+use error::Result;
+use super::DataFrame;
+
+/// Which of a set of duplicate rows isn't marked as a duplicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keep {
+    First,
+    Last,
+    /// Every row of the set is a duplicate.
+    None,
+}
+
+/// Whether each row repeats another in the `subset` columns (all of
+/// them, if empty), apart from the one kept.
+pub(crate) fn duplicated(df: &DataFrame, subset: &[&str], keep: Keep) -> Result<BooleanColumn> {
+    let columns: Vec<&dyn Column> = if subset.is_empty() {
+        df.columns().map(|(_, column)| column).collect()
+    } else {
+        subset.iter().map(|&name| df.column(name)).collect::<Result<_>>()?
+    };
+    let same = |a: usize, b: usize| columns.iter().all(|column| column.get_value(a) == column.get_value(b));
+
+    // first row of each set of duplicates seen, by hash
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    // for each row, the first row with its values
+    let mut firsts = Vec::with_capacity(df.len());
+    // for each first row, the last row with its values and how many
+    let mut sets: HashMap<usize, (usize, usize)> = HashMap::new();
+    for i in 0..df.len() {
+        let mut hasher = DefaultHasher::new();
+        for column in &columns {
+            column.get_value(i).hash(&mut hasher);
+        }
+        let candidates = seen.entry(hasher.finish()).or_default();
+        let first = match candidates.iter().find(|&&first| same(first, i)) {
+            Some(&first) => first,
+            None => {
+                candidates.push(i);
+                i
+            },
+        };
+        firsts.push(first);
+        let set = sets.entry(first).or_insert((i, 0));
+        *set = (i, set.1 + 1);
+    }
+
+    let res: Vec<bool> = firsts.iter()
+        .enumerate()
+        .map(|(i, &first)| match keep {
+            Keep::First => i != first,
+            Keep::Last => i != sets[&first].0,
+            Keep::None => sets[&first].1 > 1,
+        })
+        .collect();
+    Ok(BooleanColumn::from(res))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Float64Column, Int8Column, StringColumn, Value};
+
+    fn frame() -> DataFrame {
+        DataFrame::from_columns(vec![
+            ("a", Box::new(StringColumn::from(vec![Some("x"), Some("y"), Some("x"), None, Some("x"), None])) as Box<dyn Column>),
+            ("b", Box::new(Int8Column::from(vec![1, 2, 1, 3, 2, 3]))),
+            ("c", Box::new(Float64Column::from(vec![f64::NAN, 0.0, f64::NAN, 1.0, 2.0, 3.0]))),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn marks() {
+        let df = frame();
+        assert_eq!(
+            duplicated(&df, &[], Keep::First).unwrap(),
+            BooleanColumn::from(vec![false, false, true, false, false, false])
+        );
+        assert_eq!(
+            duplicated(&df, &["a", "b"], Keep::First).unwrap(),
+            BooleanColumn::from(vec![false, false, true, false, false, true])
+        );
+        assert_eq!(
+            duplicated(&df, &["a", "b"], Keep::Last).unwrap(),
+            BooleanColumn::from(vec![true, false, false, true, false, false])
+        );
+        assert_eq!(
+            duplicated(&df, &["a"], Keep::None).unwrap(),
+            BooleanColumn::from(vec![true, false, true, true, true, true])
+        );
+        assert!(duplicated(&df, &["d"], Keep::First).is_err());
+    }
+
+    #[test]
+    fn drop_duplicates() {
+        let df = frame().set_index("b").unwrap();
+        let res = df.drop_duplicates(&["a"], Keep::Last).unwrap();
+        let a = res.column("a").unwrap();
+        assert_eq!((0..res.len()).map(|i| a.get_value(i)).collect::<Vec<_>>(), vec![
+            Value::Str("y".to_owned()), Value::Str("x".to_owned()), Value::Null,
+        ]);
+        assert_eq!(res.index().unwrap().get(1), Value::Int(2));
+        assert_eq!(df.drop_duplicates(&[], Keep::First).unwrap().len(), 5);
+    }
+}
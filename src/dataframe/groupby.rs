@@ -0,0 +1,391 @@
+//! Group-by machinery.
+//!
+//! Rows are split into groups by hashing their key values, then each
+//! aggregation reduces the rows of every group to one value. Groups
+//! are kept in order of first appearance, and null keys form their
+//! own group.
+//!
+//! Aggregation goes through the dynamic Column interface (`Value`s),
+//! so it works for every dtype that makes sense for the aggregation.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use column::{transform, Column, Dtype, Float64Column, Int64Column, Transform, Value};
+use error::{Error, Result};
+use super::DataFrame;
+
+/// How to reduce the rows of a group. Nulls are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Agg {
+    /// Number of non-null values.
+    Count,
+    /// Numeric columns only, skipping NaN too; 0 for a group of nulls.
+    /// Int sums which don't fit in an Int64 are an error.
+    Sum,
+    /// Numeric columns only, skipping NaN too.
+    Mean,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+impl Agg {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Agg::Count => "count",
+            Agg::Sum => "sum",
+            Agg::Mean => "mean",
+            Agg::Min => "min",
+            Agg::Max => "max",
+            Agg::First => "first",
+            Agg::Last => "last",
+        }
+    }
+}
+
+pub struct GroupBy<'a> {
+    df: &'a DataFrame,
+    keys: Vec<String>,
+    // row indices of each group
+    groups: Vec<Vec<usize>>,
+}
+
+impl<'a> GroupBy<'a> {
+    pub(crate) fn new(df: &'a DataFrame, keys: &[&str]) -> Result<Self> {
+        let key_columns = keys.iter()
+            .map(|&key| df.column(key))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut lookup: HashMap<Vec<Value>, usize> = HashMap::new();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for row in 0..df.len() {
+            let key: Vec<Value> = key_columns.iter().map(|column| column.get_value(row)).collect();
+            let next = groups.len();
+            let group = *lookup.entry(key).or_insert(next);
+            if group == next {
+                groups.push(Vec::new());
+            }
+            groups[group].push(row);
+        }
+
+        Ok(GroupBy {
+            df,
+            keys: keys.iter().map(|&key| key.to_owned()).collect(),
+            groups,
+        })
+    }
+
+    /// Row indices of each group, in order of first appearance.
+    pub fn groups(&self) -> &[Vec<usize>] {
+        &self.groups
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// One row per group: the key columns, then a column per
+    /// (column, aggregation) pair, named after the input column, or
+    /// like `v_count` if that name is taken.
+    pub fn agg(&self, aggs: &[(&str, Agg)]) -> Result<DataFrame> {
+        let mut res = self.key_frame()?;
+        for &(name, agg) in aggs {
+            let column = aggregate(self.df.column(name)?, &self.groups, agg)?;
+            res.add_named_column(&agg_name(&res, name, agg), column)?;
+        }
+        Ok(res)
+    }
+
+    /// Applies each transform to the rows of every group on their own,
+    /// like `shift` within each key. Unlike `agg`, the result has a row
+    /// per row of the frame, in the same order, with a column per
+    /// (column, transform) pair named after the input column.
+    pub fn transform(&self, transforms: &[(&str, Transform)]) -> Result<DataFrame> {
+        // group of each row, and its position in the group
+        let mut positions = vec![(0, 0); self.df.len()];
+        for (g, group) in self.groups.iter().enumerate() {
+            for (k, &row) in group.iter().enumerate() {
+                positions[row] = (g, k);
+            }
+        }
+
+        let mut res = DataFrame::new();
+        for &(name, t) in transforms {
+            let column = self.df.column(name)?;
+            let parts = self.groups.iter()
+                .map(|group| {
+                    let rows: Vec<_> = group.iter().cloned().map(Some).collect();
+                    transform(&*column.take(&rows), t)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // empty, but with the dtype of the results
+            let mut transformed = transform(&*column.take(&[]), t)?;
+            for &(g, k) in &positions {
+                transformed.push_value(parts[g].get_value(k))?;
+            }
+            res.add_named_column(name, transformed)?;
+        }
+        res.rows = self.df.rows.clone();
+        Ok(res)
+    }
+
+    /// The key columns, one row per group.
+    pub(crate) fn key_frame(&self) -> Result<DataFrame> {
+        let first_rows: Vec<Option<usize>> = self.groups.iter()
+            .map(|group| group.first().cloned())
+            .collect();
+
+        let mut res = DataFrame::new();
+        for key in &self.keys {
+            res.add_named_column(key, self.df.column(key)?.take(&first_rows))?;
+        }
+        Ok(res)
+    }
+}
+
+/// `name`, or `name_agg` if `res` already has a column called `name`.
+pub(crate) fn agg_name(res: &DataFrame, name: &str, agg: Agg) -> String {
+    if res.column_names().iter().any(|existing| existing == name) {
+        format!("{}_{}", name, agg.name())
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Reduces each group of rows of `column` to one value.
+pub(crate) fn aggregate(
+    column: &dyn Column,
+    groups: &[Vec<usize>],
+    agg: Agg
+) -> Result<Box<dyn Column>> {
+    let values = |group: &[usize]| -> Vec<Value> {
+        group.iter()
+            .map(|&row| column.get_value(row))
+            .filter(|value| !value.is_null())
+            .collect()
+    };
+
+    // NaN is skipped by the numeric reductions, like nulls
+    let numbers = |group: &[usize]| -> Vec<Value> {
+        values(group).into_iter().filter(|value| !value.is_missing()).collect()
+    };
+
+    let numeric = || -> Result<()> {
+        if column.dtype().is_numeric() {
+            Ok(())
+        } else {
+            Err(Error::TypeMismatch {
+                expected: format!("numeric column for {}", agg.name()),
+                found: column.dtype().to_string(),
+            })
+        }
+    };
+
+    let res: Box<dyn Column> = match agg {
+        Agg::Count => {
+            let counts: Vec<i64> = groups.iter().map(|group| values(group).len() as i64).collect();
+            Box::new(Int64Column::from(counts))
+        },
+        Agg::Sum => {
+            numeric()?;
+            if is_float(column.dtype()) {
+                let sums: Vec<f64> = groups.iter()
+                    .map(|group| numbers(group).iter().filter_map(|x| x.as_f64()).sum())
+                    .collect();
+                Box::new(Float64Column::from(sums))
+            } else {
+                // exact in i128, and an error if the total doesn't fit
+                let sums = groups.iter()
+                    .map(|group| {
+                        let sum: i128 = numbers(group).iter().filter_map(|x| x.as_i64()).map(i128::from).sum();
+                        i64::try_from(sum).map_err(|_| Error::AggregateOverflow {
+                            agg: "sum",
+                            target: Dtype::Int64.to_string(),
+                        })
+                    })
+                    .collect::<Result<Vec<i64>>>()?;
+                Box::new(Int64Column::from(sums))
+            }
+        },
+        Agg::Mean => {
+            numeric()?;
+            let means: Vec<Option<f64>> = groups.iter()
+                .map(|group| {
+                    let values = numbers(group);
+                    if values.is_empty() {
+                        None
+                    } else {
+                        let sum: f64 = values.iter().filter_map(|x| x.as_f64()).sum();
+                        Some(sum / values.len() as f64)
+                    }
+                })
+                .collect();
+            Box::new(Float64Column::from(means))
+        },
+        Agg::Min | Agg::Max | Agg::First | Agg::Last => {
+            let mut res = column.new_empty();
+            for group in groups {
+                let values = values(group).into_iter();
+                let value = match agg {
+                    Agg::Min => values.fold(None, |acc: Option<Value>, x| match acc {
+                        Some(acc) => Some(if x < acc { x } else { acc }),
+                        None => Some(x),
+                    }),
+                    Agg::Max => values.fold(None, |acc: Option<Value>, x| match acc {
+                        Some(acc) => Some(if x > acc { x } else { acc }),
+                        None => Some(x),
+                    }),
+                    Agg::First => values.take(1).next(),
+                    _ => values.last(),
+                };
+                res.push_value(value.unwrap_or(Value::Null))?;
+            }
+            res
+        },
+    };
+    Ok(res)
+}
+
+fn is_float(dtype: Dtype) -> bool {
+    dtype == Dtype::Float32 || dtype == Dtype::Float64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Closed, Int8Column, Interval, IntervalColumn, StringColumn};
+
+    fn frame() -> DataFrame {
+        DataFrame::from_columns(vec![
+            ("k", Box::new(StringColumn::from(vec![Some("a"), Some("b"), Some("a"), None, Some("b")])) as Box<dyn Column>),
+            ("v", Box::new(Int8Column::from(vec![Some(1), Some(2), None, Some(4), Some(100)]))),
+            ("f", Box::new(Float64Column::from(vec![0.5, 1.0, 1.5, 2.0, 2.5]))),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn groups_in_order() {
+        let df = frame();
+        let groups = df.group_by(&["k"]).unwrap();
+        assert_eq!(groups.groups(), &[vec![0, 2], vec![1, 4], vec![3]]);
+        assert!(df.group_by(&["nope"]).is_err());
+    }
+
+    #[test]
+    fn agg() {
+        let df = frame();
+        let res = df.group_by(&["k"]).unwrap().agg(&[
+            ("v", Agg::Sum),
+            ("v", Agg::Count),
+            ("f", Agg::Mean),
+            ("v", Agg::Max),
+            ("k", Agg::First),
+        ]).unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res.column_names(), &["k", "v", "v_count", "f", "v_max", "k_first"]);
+
+        let column = |i: usize| &res.columns[i];
+        assert_eq!(
+            column(0).as_any().downcast_ref::<StringColumn>().unwrap(),
+            &StringColumn::from(vec![Some("a"), Some("b"), None])
+        );
+        // summed as i64, so no i8 overflow
+        assert_eq!(
+            column(1).as_any().downcast_ref::<Int64Column>().unwrap(),
+            &Int64Column::from(vec![1, 102, 4])
+        );
+        assert_eq!(
+            column(2).as_any().downcast_ref::<Int64Column>().unwrap(),
+            &Int64Column::from(vec![1, 2, 1])
+        );
+        assert_eq!(
+            column(3).as_any().downcast_ref::<Float64Column>().unwrap(),
+            &Float64Column::from(vec![1.0, 1.75, 2.0])
+        );
+        assert_eq!(
+            column(4).as_any().downcast_ref::<Int8Column>().unwrap(),
+            &Int8Column::from(vec![1, 100, 4])
+        );
+        assert_eq!(column(5).get_value(2), Value::Null);
+
+        assert!(df.group_by(&["k"]).unwrap().agg(&[("k", Agg::Sum)]).is_err());
+    }
+
+    #[test]
+    fn intervals() {
+        let df = DataFrame::from_columns(vec![
+            ("k", Box::new(Int8Column::from(vec![1, 2, 1])) as Box<dyn Column>),
+            ("iv", Box::new(IntervalColumn::from_values(
+                vec![Some((0, 1)), Some((1, 2)), None],
+                Closed::Right,
+            ).unwrap())),
+        ]).unwrap();
+        let groups = df.group_by(&["k"]).unwrap();
+        let res = groups.agg(&[("iv", Agg::First), ("iv", Agg::Last), ("iv", Agg::Max)]).unwrap();
+        let first = res.column_as::<IntervalColumn<i32>>("iv").unwrap();
+        assert_eq!(first.get(0), Some(Some(Interval::new(0, 1, Closed::Right))));
+        assert_eq!(first.get(1), Some(Some(Interval::new(1, 2, Closed::Right))));
+        assert_eq!(res.column("iv_max").unwrap().get_value(0), first.get_value(0));
+
+        let shifted = groups.transform(&[("iv", Transform::Shift(1))]).unwrap();
+        let shifted = shifted.column_as::<IntervalColumn<i32>>("iv").unwrap();
+        assert!(shifted.is_null(0) && shifted.is_null(1));
+        assert_eq!(shifted.get(2), Some(Some(Interval::new(0, 1, Closed::Right))));
+    }
+
+    #[test]
+    fn sums_skip_nan_and_overflow() {
+        let df = DataFrame::from_columns(vec![
+            ("k", Box::new(Int8Column::from(vec![1, 1, 2])) as Box<dyn Column>),
+            ("f", Box::new(Float64Column::from(vec![1.0, f64::NAN, 3.0]))),
+            ("big", Box::new(Int64Column::from(vec![i64::MAX, 1, 0]))),
+        ]).unwrap();
+        let groups = df.group_by(&["k"]).unwrap();
+        let res = groups.agg(&[("f", Agg::Sum), ("f", Agg::Mean)]).unwrap();
+        assert_eq!(res.column_as::<Float64Column>("f").unwrap(), &Float64Column::from(vec![1.0, 3.0]));
+        assert_eq!(res.column_as::<Float64Column>("f_mean").unwrap(), &Float64Column::from(vec![1.0, 3.0]));
+
+        match groups.agg(&[("big", Agg::Sum)]) {
+            Err(Error::AggregateOverflow { agg: "sum", .. }) => (),
+            res => panic!("{:?}", res),
+        }
+        // only the total has to fit
+        let df = DataFrame::from_columns(vec![
+            ("k", Box::new(Int8Column::from(vec![1, 1, 1])) as Box<dyn Column>),
+            ("big", Box::new(Int64Column::from(vec![i64::MAX, 1, -1]))),
+        ]).unwrap();
+        let res = df.group_by(&["k"]).unwrap().agg(&[("big", Agg::Sum)]).unwrap();
+        assert_eq!(res.column_as::<Int64Column>("big").unwrap(), &Int64Column::from(vec![i64::MAX]));
+    }
+
+    #[test]
+    fn transform() {
+        let df = frame();
+        let res = df.group_by(&["k"]).unwrap().transform(&[
+            ("v", Transform::CumSum),
+            ("f", Transform::Diff(1)),
+            ("k", Transform::Shift(1)),
+        ]).unwrap();
+        assert_eq!(res.len(), 5);
+        assert_eq!(res.column_names(), &["v", "f", "k"]);
+        assert_eq!(
+            res.column_as::<Int64Column>("v").unwrap(),
+            &Int64Column::from(vec![Some(1), Some(2), None, Some(4), Some(102)])
+        );
+        assert_eq!(
+            res.column_as::<Float64Column>("f").unwrap(),
+            &Float64Column::from(vec![None, None, Some(1.0), None, Some(1.5)])
+        );
+        assert_eq!(
+            res.column_as::<StringColumn>("k").unwrap(),
+            &StringColumn::from(vec![None, None, Some("a"), None, Some("b")])
+        );
+        assert!(df.group_by(&["k"]).unwrap().transform(&[("k", Transform::CumMax)]).is_err());
+    }
+}
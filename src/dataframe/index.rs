@@ -0,0 +1,293 @@
+//! Row labels
+//!
+//! A frame can carry an Index: one label per row, which `loc` looks
+//! rows up by and arithmetic between frames lines rows up by. Labels are
+//! ints, strings or timestamps, or just the row numbers (a range, which
+//! stores nothing). A frame without an index acts as if it had a range.
+//!
+//! Labels don't have to be unique for lookups, but aligning two frames
+//! with different labels needs each label on one row per side.
+
+use std::collections::HashMap;
+
+use column::{Column, Dtype, Int64Column, StringColumn, TimestampColumn, Unit, Value};
+use error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Labels {
+    /// `0..n`, without storing them.
+    Range(usize),
+    Int(Vec<i64>),
+    Str(Vec<String>),
+    Timestamp(Vec<i64>, Unit),
+}
+
+/// For each aligned label, the row on one side which has it.
+type Rows = Vec<Option<usize>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    name: Option<String>,
+    labels: Labels,
+    // rows holding each label, for lookups; empty for ranges
+    rows: HashMap<Value, Vec<usize>>,
+}
+
+impl Index {
+    pub fn range(len: usize) -> Self {
+        Index {
+            name: None,
+            labels: Labels::Range(len),
+            rows: HashMap::new(),
+        }
+    }
+
+    /// Labels from the values of an int, string or timestamp column,
+    /// which can't have nulls.
+    pub fn from_column(name: &str, column: &dyn Column) -> Result<Self> {
+        match column.dtype() {
+            Dtype::Int8 | Dtype::Int16 | Dtype::Int32 | Dtype::Int64 |
+            Dtype::String | Dtype::Timestamp(_) => (),
+            dtype => return Err(Error::TypeMismatch {
+                expected: "int, string or timestamp labels".to_owned(),
+                found: dtype.to_string(),
+            }),
+        }
+        if let Some(row) = (0..column.len()).find(|&i| column.is_null(i)) {
+            return Err(Error::TypeMismatch {
+                expected: "a label for every row".to_owned(),
+                found: format!("null at row {}", row),
+            });
+        }
+        let values = (0..column.len()).map(|i| column.get_value(i)).collect();
+        Index::from_values(Some(name.to_owned()), values)
+    }
+
+    /// Labels all of one kind; timestamps take the unit of the first.
+    fn from_values(name: Option<String>, values: Vec<Value>) -> Result<Self> {
+        let first = match values.first() {
+            Some(first) => first.clone(),
+            None => return Ok(Index::with_labels(name, Labels::Int(Vec::new()))),
+        };
+        let mismatch = |found: &Value| Error::TypeMismatch {
+            expected: format!("{} labels", first.kind()),
+            found: found.kind().to_owned(),
+        };
+        let labels = match first {
+            Value::Int(_) => Labels::Int(values.iter()
+                .map(|value| match *value {
+                    Value::Int(x) => Ok(x),
+                    ref value => Err(mismatch(value)),
+                })
+                .collect::<Result<_>>()?),
+            Value::Str(_) => Labels::Str(values.iter()
+                .map(|value| match *value {
+                    Value::Str(ref x) => Ok(x.clone()),
+                    ref value => Err(mismatch(value)),
+                })
+                .collect::<Result<_>>()?),
+            Value::Timestamp(_, unit) => Labels::Timestamp(values.iter()
+                .map(|value| match *value {
+                    Value::Timestamp(x, from) => from.convert(x, unit).ok_or_else(|| mismatch(value)),
+                    ref value => Err(mismatch(value)),
+                })
+                .collect::<Result<_>>()?, unit),
+            ref value => return Err(Error::TypeMismatch {
+                expected: "int, string or timestamp labels".to_owned(),
+                found: value.kind().to_owned(),
+            }),
+        };
+        Ok(Index::with_labels(name, labels))
+    }
+
+    fn with_labels(name: Option<String>, labels: Labels) -> Self {
+        let mut index = Index {
+            name,
+            labels,
+            rows: HashMap::new(),
+        };
+        if let Labels::Range(_) = index.labels {
+            return index;
+        }
+        let mut rows: HashMap<Value, Vec<usize>> = HashMap::new();
+        for i in 0..index.len() {
+            rows.entry(index.get(i)).or_default().push(i);
+        }
+        index.rows = rows;
+        index
+    }
+
+    /// Name of the column the labels came from, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn labels(&self) -> &Labels {
+        &self.labels
+    }
+
+    pub fn len(&self) -> usize {
+        match self.labels {
+            Labels::Range(len) => len,
+            Labels::Int(ref x) => x.len(),
+            Labels::Str(ref x) => x.len(),
+            Labels::Timestamp(ref x, _) => x.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Label of row `i`.
+    pub fn get(&self, i: usize) -> Value {
+        match self.labels {
+            Labels::Range(len) => {
+                assert!(i < len, "index {} out of bounds for length {}", i, len);
+                Value::Int(i as i64)
+            },
+            Labels::Int(ref x) => Value::Int(x[i]),
+            Labels::Str(ref x) => Value::Str(x[i].clone()),
+            Labels::Timestamp(ref x, unit) => Value::Timestamp(x[i], unit),
+        }
+    }
+
+    /// Rows labelled `label`, in order. Timestamps in another unit
+    /// are converted first.
+    pub fn rows(&self, label: &Value) -> Vec<usize> {
+        match (&self.labels, label) {
+            (&Labels::Range(len), &Value::Int(x)) => {
+                if x >= 0 && (x as usize) < len { vec![x as usize] } else { Vec::new() }
+            },
+            (&Labels::Timestamp(_, unit), &Value::Timestamp(x, from)) => from.convert(x, unit)
+                .and_then(|x| self.rows.get(&Value::Timestamp(x, unit)))
+                .cloned()
+                .unwrap_or_default(),
+            _ => self.rows.get(label).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Whether no label is on more than one row.
+    pub fn is_unique(&self) -> bool {
+        match self.labels {
+            Labels::Range(_) => true,
+            _ => self.rows.len() == self.len(),
+        }
+    }
+
+    /// Labels of the given rows, in that order.
+    pub fn take(&self, rows: &[usize]) -> Index {
+        let labels = match self.labels {
+            Labels::Range(len) => Labels::Int(rows.iter()
+                .map(|&i| {
+                    assert!(i < len, "index {} out of bounds for length {}", i, len);
+                    i as i64
+                })
+                .collect()),
+            Labels::Int(ref x) => Labels::Int(rows.iter().map(|&i| x[i]).collect()),
+            Labels::Str(ref x) => Labels::Str(rows.iter().map(|&i| x[i].clone()).collect()),
+            Labels::Timestamp(ref x, unit) => Labels::Timestamp(rows.iter().map(|&i| x[i]).collect(), unit),
+        };
+        Index::with_labels(self.name.clone(), labels)
+    }
+
+    /// The labels as a column: Int64 for ranges and ints.
+    pub fn to_column(&self) -> Box<dyn Column> {
+        match self.labels {
+            Labels::Range(len) => Box::new(Int64Column::from((0..len as i64).collect::<Vec<_>>())),
+            Labels::Int(ref x) => Box::new(Int64Column::from(x.clone())),
+            Labels::Str(ref x) => {
+                Box::new(StringColumn::from(x.iter().map(|s| s.as_str()).collect::<Vec<_>>()))
+            },
+            Labels::Timestamp(ref x, unit) => {
+                Box::new(TimestampColumn::from_values(x.iter().cloned().map(Some).collect(), unit))
+            },
+        }
+    }
+
+    /// The labels of both indexes, `self`'s first, and for each of them
+    /// the row on either side which has it (if any). Indexes with the
+    /// same labels in the same order line up as they are; otherwise
+    /// both sides need unique labels.
+    pub(crate) fn align(&self, other: &Index) -> Result<(Index, Rows, Rows)> {
+        let name = if self.name == other.name { self.name.clone() } else { None };
+        let same = match (&self.labels, &other.labels) {
+            (&Labels::Range(a), &Labels::Int(ref b)) | (&Labels::Int(ref b), &Labels::Range(a)) => {
+                a == b.len() && b.iter().enumerate().all(|(i, &x)| x == i as i64)
+            },
+            (a, b) => a == b,
+        };
+        if same {
+            let rows: Vec<_> = (0..self.len()).map(Some).collect();
+            let mut res = self.clone();
+            res.name = name;
+            return Ok((res, rows.clone(), rows));
+        }
+
+        for index in &[self, other] {
+            if let Some((label, _)) = index.rows.iter().find(|&(_, rows)| rows.len() > 1) {
+                return Err(Error::DuplicateLabel(label.to_string()));
+            }
+        }
+        let mut labels: Vec<Value> = (0..self.len()).map(|i| self.get(i)).collect();
+        labels.extend((0..other.len()).map(|i| other.get(i)).filter(|label| self.rows(label).is_empty()));
+        let find = |index: &Index, label: &Value| index.rows(label).first().cloned();
+        let left = labels.iter().map(|label| find(self, label)).collect();
+        let right = labels.iter().map(|label| find(other, label)).collect();
+        Ok((Index::from_values(name, labels)?, left, right))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_and_lookups() {
+        let column = StringColumn::from(vec!["a", "b", "a"]);
+        let index = Index::from_column("key", &column).unwrap();
+        assert_eq!(index.name(), Some("key"));
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.get(1), Value::Str("b".to_owned()));
+        assert_eq!(index.rows(&Value::Str("a".to_owned())), vec![0, 2]);
+        assert!(index.rows(&Value::Int(0)).is_empty());
+        assert!(!index.is_unique());
+        assert_eq!(index.take(&[2, 1]).labels(), &Labels::Str(vec!["a".to_owned(), "b".to_owned()]));
+        assert_eq!(index.to_column().as_any().downcast_ref::<StringColumn>().unwrap(), &column);
+
+        let range = Index::range(3);
+        assert_eq!(range.rows(&Value::Int(2)), vec![2]);
+        assert!(range.rows(&Value::Int(3)).is_empty());
+        assert!(range.is_unique());
+
+        let times = TimestampColumn::from_values(vec![Some(1), Some(2)], Unit::Seconds);
+        let index = Index::from_column("t", &times).unwrap();
+        assert_eq!(index.rows(&Value::Timestamp(2000, Unit::Milliseconds)), vec![1]);
+
+        assert!(Index::from_column("x", &Int64Column::from(vec![Some(1), None])).is_err());
+        assert!(Index::from_column("x", &::column::Float64Column::from(vec![1.0])).is_err());
+    }
+
+    #[test]
+    fn align() {
+        let a = Index::from_column("k", &Int64Column::from(vec![3, 1])).unwrap();
+        let b = Index::from_column("k", &Int64Column::from(vec![1, 2])).unwrap();
+        let (index, left, right) = a.align(&b).unwrap();
+        assert_eq!(index.labels(), &Labels::Int(vec![3, 1, 2]));
+        assert_eq!(index.name(), Some("k"));
+        assert_eq!(left, vec![Some(0), Some(1), None]);
+        assert_eq!(right, vec![None, Some(0), Some(1)]);
+
+        let (index, left, _) = Index::range(2).align(&Index::range(2)).unwrap();
+        assert_eq!(index.labels(), &Labels::Range(2));
+        assert_eq!(left, vec![Some(0), Some(1)]);
+
+        let dup = Index::from_column("k", &Int64Column::from(vec![1, 1])).unwrap();
+        match a.align(&dup) {
+            Err(Error::DuplicateLabel(ref label)) if label == "1" => (),
+            res => panic!("{:?}", res),
+        }
+        let strs = Index::from_column("k", &StringColumn::from(vec!["x"])).unwrap();
+        assert!(a.align(&strs).is_err());
+    }
+}
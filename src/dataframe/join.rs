@@ -0,0 +1,101 @@
+//! Hash joins between frames.
+//!
+//! The right frame's rows are hashed by their key values, then each
+//! left row looks up its matches, so rows come out in left order (and
+//! right order within a left row). Like SQL, null keys never match.
+//!
+//! The result has the left columns, then the right columns other than
+//! the keys. Right columns whose names clash with a left column get a
+//! `_right` suffix.
+
+use std::collections::HashMap;
+
+use column::Value;
+use error::Result;
+use super::DataFrame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JoinType {
+    /// Only rows with a match on both sides.
+    Inner,
+    /// Every left row; the right columns are null where there's no match.
+    Left,
+}
+
+pub(crate) fn join(left: &DataFrame, right: &DataFrame, on: &[&str], how: JoinType) -> Result<DataFrame> {
+    let left_keys = on.iter().map(|&key| left.column(key)).collect::<Result<Vec<_>>>()?;
+    let right_keys = on.iter().map(|&key| right.column(key)).collect::<Result<Vec<_>>>()?;
+
+    let mut lookup: HashMap<Vec<Value>, Vec<usize>> = HashMap::new();
+    for row in 0..right.len() {
+        let key: Vec<Value> = right_keys.iter().map(|column| column.get_value(row)).collect();
+        if !key.iter().any(Value::is_null) {
+            lookup.entry(key).or_default().push(row);
+        }
+    }
+
+    let mut left_rows = Vec::new();
+    let mut right_rows = Vec::new();
+    for row in 0..left.len() {
+        let key: Vec<Value> = left_keys.iter().map(|column| column.get_value(row)).collect();
+        match lookup.get(&key) {
+            Some(matches) => {
+                for &other in matches {
+                    left_rows.push(Some(row));
+                    right_rows.push(Some(other));
+                }
+            },
+            None => if how == JoinType::Left {
+                left_rows.push(Some(row));
+                right_rows.push(None);
+            },
+        }
+    }
+
+    let mut res = left.take(&left_rows);
+    for (name, column) in right.columns() {
+        if on.contains(&name) {
+            continue;
+        }
+        let name = if left.column(name).is_ok() { format!("{}_right", name) } else { name.to_owned() };
+        res.add_named_column(&name, column.take(&right_rows))?;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Column, Int8Column, StringColumn};
+
+    #[test]
+    fn inner_and_left() {
+        let left = DataFrame::from_columns(vec![
+            ("id", Box::new(Int8Column::from(vec![Some(1), Some(2), None, Some(3)])) as Box<dyn Column>),
+            ("x", Box::new(StringColumn::from(vec!["a", "b", "c", "d"]))),
+        ]).unwrap();
+        let right = DataFrame::from_columns(vec![
+            ("id", Box::new(Int8Column::from(vec![Some(3), Some(1), Some(3), None])) as Box<dyn Column>),
+            ("x", Box::new(StringColumn::from(vec!["p", "q", "r", "s"]))),
+        ]).unwrap();
+
+        let inner = left.join(&right, &["id"], JoinType::Inner).unwrap();
+        assert_eq!(inner.column_names(), &["id", "x", "x_right"]);
+        assert_eq!(
+            inner.column_as::<Int8Column>("id").unwrap(),
+            &Int8Column::from(vec![1, 3, 3])
+        );
+        assert_eq!(
+            inner.column_as::<StringColumn>("x_right").unwrap(),
+            &StringColumn::from(vec!["q", "p", "r"])
+        );
+
+        let outer = left.join(&right, &["id"], JoinType::Left).unwrap();
+        assert_eq!(outer.len(), 5);
+        assert_eq!(
+            outer.column_as::<StringColumn>("x_right").unwrap(),
+            &StringColumn::from(vec![Some("q"), None, None, Some("p"), Some("r")])
+        );
+        assert!(left.join(&right, &["y"], JoinType::Inner).is_err());
+    }
+}
@@ -1,10 +1,57 @@
-use bit_vec::BitVec;
 use std::any::Any;
-use super::column::{Column, DataType};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 
+use error::{Error, Result};
+use lazy::LazyFrame;
+use super::column::{combine, BooleanColumn, Column, Dtype, ListColumn, Operator, StructColumn, Value};
+
+mod describe;
+mod duplicates;
+mod groupby;
+mod index;
+mod join;
+mod multi_index;
+mod resample;
+mod window;
+
+pub use self::duplicates::Keep;
+pub use self::groupby::{Agg, GroupBy};
+pub(crate) use self::groupby::aggregate;
+pub use self::index::{Index, Labels};
+pub use self::join::JoinType;
+pub use self::multi_index::{MultiIndex, NAME_SEPARATOR};
+pub use self::resample::Resample;
+pub use self::window::TimeRolling;
+
+/// Row labels of a frame.
+#[derive(Debug, Clone)]
+enum RowIndex {
+    /// Just the row numbers, without storing them.
+    Range,
+    Flat(Index),
+    Multi(MultiIndex),
+}
+
+impl RowIndex {
+    /// Labels of the given rows, in that order.
+    fn take(&self, rows: &[usize]) -> RowIndex {
+        match *self {
+            RowIndex::Range => RowIndex::Range,
+            RowIndex::Flat(ref index) => RowIndex::Flat(index.take(rows)),
+            RowIndex::Multi(ref index) => RowIndex::Multi(index.take(rows)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct DataFrame {
     column_names: Vec<String>, //keep name and index synced?
-    columns: Vec<Box<Column>>,
+    columns: Vec<Box<dyn Column>>,
+    rows: RowIndex,
+    // hierarchical column labels, which column_names are flattened
+    // from; dropped when columns are added or removed
+    column_index: Option<MultiIndex>,
 }
 
 impl DataFrame {
@@ -12,23 +59,746 @@ impl DataFrame {
         DataFrame {
             column_names: Vec::new(),
             columns: Vec::new(),
+            rows: RowIndex::Range,
+            column_index: None,
         }
     }
 
-    pub fn add_column(&mut self, column: Box<Column>) {
+    /// From (name, column) pairs, which must all be the same length.
+    pub fn from_columns(columns: Vec<(&str, Box<dyn Column>)>) -> Result<Self> {
+        let mut df = DataFrame::new();
+        for (name, column) in columns {
+            df.add_named_column(name, column)?;
+        }
+        Ok(df)
+    }
+
+    pub fn column_names(&self) -> &[String] {
+        &self.column_names
+    }
+
+    /// Adds a column named by its position. Errors like
+    /// `add_named_column`, including if a column already has that name.
+    pub fn add_column(&mut self, column: Box<dyn Column>) -> Result<()> {
+        let name = self.columns.len().to_string();
+        self.add_named_column(&name, column)
+    }
+
+    /// Errors if the column's length doesn't match the other columns,
+    /// or if a column is already called `name`.
+    pub fn add_named_column(&mut self, name: &str, column: Box<dyn Column>) -> Result<()> {
+        if !self.columns.is_empty() && column.len() != self.len() {
+            return Err(Error::LengthMismatch {
+                expected: self.len(),
+                found: column.len(),
+            });
+        }
+        if self.column_names.iter().any(|existing| existing == name) {
+            return Err(Error::DuplicateColumn(name.to_owned()));
+        }
+        self.column_index = None;
+        self.column_names.push(name.to_owned());
         self.columns.push(column);
+        Ok(())
+    }
+
+    /// Number of rows.
+    pub fn len(&self) -> usize {
+        self.columns.first().map_or(0, |column| column.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn column(&self, name: &str) -> Result<&dyn Column> {
+        self.position(name).map(|i| &*self.columns[i])
+    }
+
+    /// Column downcast to its concrete type.
+    pub fn column_as<T: Any>(&self, name: &str) -> Result<&T> {
+        let column = self.column(name)?;
+        column.as_any()
+            .downcast_ref::<T>()
+            .ok_or_else(|| Error::TypeMismatch {
+                expected: format!("{} to be a different column type", name),
+                found: column.dtype().to_string(),
+            })
+    }
+
+    /// Iterates over (name, column) pairs, in order.
+    pub fn columns(&self) -> impl Iterator<Item=(&str, &dyn Column)> {
+        self.column_names.iter()
+            .map(|name| name.as_str())
+            .zip(self.columns.iter().map(|column| &**column))
+    }
+
+    /// Replaces the column named `name`, or adds it at the end if
+    /// there isn't one.
+    pub fn set_column(&mut self, name: &str, column: Box<dyn Column>) -> Result<()> {
+        match self.position(name) {
+            Ok(i) => {
+                if column.len() != self.len() {
+                    return Err(Error::LengthMismatch {
+                        expected: self.len(),
+                        found: column.len(),
+                    });
+                }
+                self.columns[i] = column;
+                Ok(())
+            },
+            Err(_) => self.add_named_column(name, column),
+        }
+    }
+
+    /// New frame with just the named columns, in the given order. Row
+    /// labels come along, and so do the column labels of those columns.
+    pub fn select(&self, names: &[&str]) -> Result<DataFrame> {
+        let positions = names.iter().map(|name| self.position(name)).collect::<Result<Vec<_>>>()?;
+        let mut res = DataFrame::new();
+        for (&name, &position) in names.iter().zip(&positions) {
+            res.add_named_column(name, self.columns[position].clone_box())?;
+        }
+        res.rows = self.rows.clone();
+        res.column_index = self.column_index.as_ref().map(|index| index.take(&positions));
+        Ok(res)
+    }
+
+    /// New frame with rows gathered by index; `None` gives a null row.
+    /// The row labels come along, unless there are null rows.
+    pub fn take(&self, indices: &[Option<usize>]) -> DataFrame {
+        let rows = indices.iter().cloned().collect::<Option<Vec<_>>>();
+        DataFrame {
+            column_names: self.column_names.clone(),
+            columns: self.columns.iter().map(|column| column.take(indices)).collect(),
+            rows: rows.map_or(RowIndex::Range, |rows| self.rows.take(&rows)),
+            column_index: self.column_index.clone(),
+        }
+    }
+
+    /// Row labels, if the frame has any.
+    pub fn index(&self) -> Option<&Index> {
+        match self.rows {
+            RowIndex::Flat(ref index) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Hierarchical row labels, if the frame has them.
+    pub fn multi_index(&self) -> Option<&MultiIndex> {
+        match self.rows {
+            RowIndex::Multi(ref index) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Hierarchical column labels, if the frame has them.
+    pub fn column_index(&self) -> Option<&MultiIndex> {
+        self.column_index.as_ref()
+    }
+
+    /// The labels, or a range for a frame without any.
+    fn row_index(&self) -> Cow<'_, Index> {
+        match self.index() {
+            Some(index) => Cow::Borrowed(index),
+            None => Cow::Owned(Index::range(self.len())),
+        }
+    }
+
+    /// Moves the column `name` into the index, replacing any index
+    /// there was. It must be an int, string or timestamp column without
+    /// nulls.
+    pub fn set_index(&self, name: &str) -> Result<DataFrame> {
+        let position = self.position(name)?;
+        let index = Index::from_column(name, &*self.columns[position])?;
+        let mut res = self.clone();
+        res.column_names.remove(position);
+        res.columns.remove(position);
+        res.rows = RowIndex::Flat(index);
+        res.column_index = None;
+        Ok(res)
+    }
+
+    /// Moves the named columns into hierarchical row labels, one level
+    /// each, replacing any index there was.
+    pub fn set_multi_index(&self, names: &[&str]) -> Result<DataFrame> {
+        let levels = names.iter()
+            .map(|&name| Ok((name, self.column(name)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let index = MultiIndex::from_columns(levels)?;
+        let mut res = DataFrame::new();
+        for (name, column) in self.columns() {
+            if !names.contains(&name) {
+                res.add_named_column(name, column.clone_box())?;
+            }
+        }
+        res.rows = RowIndex::Multi(index);
+        Ok(res)
+    }
+
+    /// Moves the index back to a column at the front, named after the
+    /// column it came from (or `index`); a MultiIndex becomes a column
+    /// per level (`level_0` and so on, if unnamed). Frames without an
+    /// index are returned as they are. Errors if a label column's name
+    /// is already taken.
+    pub fn reset_index(&self) -> Result<DataFrame> {
+        let mut res = DataFrame::new();
+        match self.rows {
+            RowIndex::Range => return Ok(self.clone()),
+            RowIndex::Flat(ref index) => {
+                res.add_named_column(index.name().unwrap_or("index"), index.to_column())?;
+            },
+            RowIndex::Multi(ref index) => {
+                for (level, name) in index.names().into_iter().enumerate() {
+                    let name = name.map_or_else(|| format!("level_{}", level), |name| name.to_owned());
+                    res.add_named_column(&name, index.level_values(level))?;
+                }
+            },
+        }
+        for (name, column) in self.columns() {
+            res.add_named_column(name, column.clone_box())?;
+        }
+        Ok(res)
+    }
+
+    fn require_multi_index(&self) -> Result<&MultiIndex> {
+        self.multi_index().ok_or_else(|| Error::TypeMismatch {
+            expected: "hierarchical row labels".to_owned(),
+            found: "none".to_owned(),
+        })
+    }
+
+    /// Rows whose hierarchical labels start with `key`, which can
+    /// leave out inner levels. Errors if there aren't any.
+    pub fn xs(&self, key: &[Value]) -> Result<DataFrame> {
+        let rows = self.require_multi_index()?.rows(key)?;
+        if rows.is_empty() {
+            return Err(Error::LabelNotFound(Value::List(key.to_vec()).to_string()));
+        }
+        Ok(self.take(&rows.into_iter().map(Some).collect::<Vec<_>>()))
+    }
+
+    /// Swaps two levels of the hierarchical row labels.
+    pub fn swap_levels(&self, i: usize, j: usize) -> Result<DataFrame> {
+        let mut res = self.clone();
+        res.rows = RowIndex::Multi(self.require_multi_index()?.swap_levels(i, j)?);
+        Ok(res)
+    }
+
+    /// Moves row label level `level` into the columns; see the
+    /// multi_index module.
+    pub fn unstack(&self, level: usize) -> Result<DataFrame> {
+        multi_index::unstack(self, level)
+    }
+
+    /// Moves the innermost column label level into the rows; see the
+    /// multi_index module.
+    pub fn stack(&self) -> Result<DataFrame> {
+        multi_index::stack(self)
+    }
+
+    /// Rows labelled `label`, copied into a new frame (like `take`).
+    /// Errors if there aren't any.
+    pub fn loc(&self, label: &Value) -> Result<DataFrame> {
+        let rows = self.row_index().rows(label);
+        if rows.is_empty() {
+            return Err(Error::LabelNotFound(label.to_string()));
+        }
+        Ok(self.take(&rows.into_iter().map(Some).collect::<Vec<_>>()))
+    }
+
+    /// Rows with labels from `start` to `end`, both included, in their
+    /// current order. Like `loc`, the rows are copied.
+    pub fn loc_range(&self, start: &Value, end: &Value) -> DataFrame {
+        let index = self.row_index();
+        let rows: Vec<_> = (0..index.len())
+            .filter(|&i| {
+                let label = index.get(i);
+                label.partial_cmp(start).is_some_and(|ord| ord != Ordering::Less) &&
+                    label.partial_cmp(end).is_some_and(|ord| ord != Ordering::Greater)
+            })
+            .map(Some)
+            .collect();
+        self.take(&rows)
+    }
+
+    /// `self + other`, lining rows up by label; see `combine`.
+    pub fn add(&self, other: &DataFrame) -> Result<DataFrame> {
+        self.combine(other, Operator::Add)
+    }
+
+    pub fn sub(&self, other: &DataFrame) -> Result<DataFrame> {
+        self.combine(other, Operator::Sub)
+    }
+
+    pub fn mul(&self, other: &DataFrame) -> Result<DataFrame> {
+        self.combine(other, Operator::Mul)
+    }
+
+    pub fn div(&self, other: &DataFrame) -> Result<DataFrame> {
+        self.combine(other, Operator::Div)
+    }
+
+    /// Applies `op` to the columns with the same name in both frames,
+    /// after lining their rows up by label (see the index module). The
+    /// result has every label and column of either frame; rows or
+    /// columns missing from one side come out null. Columns must be
+    /// numeric, with the dtype rules of `column::combine`.
+    pub fn combine(&self, other: &DataFrame, op: Operator) -> Result<DataFrame> {
+        let (index, left, right) = self.row_index().align(&other.row_index())?;
+        let nulls = vec![None; index.len()];
+
+        let mut res = DataFrame::new();
+        let mut names: Vec<&str> = self.column_names.iter().map(|name| name.as_str()).collect();
+        names.extend(other.column_names.iter().map(|name| name.as_str()).filter(|name| self.column(name).is_err()));
+        for name in names {
+            let column = match (self.column(name), other.column(name)) {
+                (Ok(a), Ok(b)) => combine(&*a.take(&left), op, &*b.take(&right))?,
+                // all null, but with the dtype it would have had
+                (Ok(a), Err(_)) | (Err(_), Ok(a)) => {
+                    let missing = a.take(&nulls);
+                    combine(&*missing, op, &*missing)?
+                },
+                (Err(_), Err(_)) => unreachable!(),
+            };
+            res.add_named_column(name, column)?;
+        }
+        if self.index().is_some() || other.index().is_some() {
+            res.rows = RowIndex::Flat(index);
+        }
+        Ok(res)
+    }
+
+    /// Summary statistics of the numeric and string columns; see the
+    /// describe module for what's included.
+    pub fn describe(&self) -> Result<DataFrame> {
+        describe::describe(self)
+    }
+
+    /// Rows ordered by the values of `by`. The sort is stable, and
+    /// nulls (and NaN) go last either way.
+    pub fn sort(&self, by: &str, descending: bool) -> Result<DataFrame> {
+        let indices: Vec<Option<usize>> = self.sort_order(by, descending)?.into_iter().map(Some).collect();
+        Ok(self.take(&indices))
+    }
+
+    /// Row positions in `sort` order, without gathering the rows.
+    pub(crate) fn sort_order(&self, by: &str, descending: bool) -> Result<Vec<usize>> {
+        let column = self.column(by)?;
+        let values: Vec<Value> = (0..self.len()).map(|i| column.get_value(i)).collect();
+
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&values[a], &values[b]);
+            match (a.is_missing(), b.is_missing()) {
+                (false, false) => {
+                    let ord = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+                    if descending { ord.reverse() } else { ord }
+                },
+                (a, b) => a.cmp(&b),
+            }
+        });
+        Ok(order)
+    }
+
+    /// The `n` rows with the largest values of `by`, largest first.
+    /// Nulls and NaN are left out, and ties keep row order.
+    pub fn nlargest(&self, n: usize, by: &str) -> Result<DataFrame> {
+        self.select_extremes(n, by, true)
+    }
+
+    /// The `n` rows with the smallest values of `by`, smallest first.
+    pub fn nsmallest(&self, n: usize, by: &str) -> Result<DataFrame> {
+        self.select_extremes(n, by, false)
+    }
+
+    /// Picks out the first `n` rows in order with a partial selection,
+    /// then sorts just those, rather than sorting every row.
+    fn select_extremes(&self, n: usize, by: &str, largest: bool) -> Result<DataFrame> {
+        let column = self.column(by)?;
+        let values: Vec<Value> = (0..self.len()).map(|i| column.get_value(i)).collect();
+        let compare = |&a: &usize, &b: &usize| {
+            let ord = values[a].partial_cmp(&values[b]).unwrap_or(Ordering::Equal);
+            let ord = if largest { ord.reverse() } else { ord };
+            ord.then(a.cmp(&b))
+        };
+
+        let mut order: Vec<usize> = (0..self.len()).filter(|&i| !values[i].is_missing()).collect();
+        if n == 0 {
+            order.clear();
+        } else if n < order.len() {
+            order.select_nth_unstable_by(n - 1, compare);
+            order.truncate(n);
+        }
+        order.sort_unstable_by(compare);
+        let indices: Vec<Option<usize>> = order.into_iter().map(Some).collect();
+        Ok(self.take(&indices))
+    }
+
+    /// Whether each row has the same values as another in the `subset`
+    /// columns (every column, if empty), leaving out the one `keep` says.
+    pub fn duplicated(&self, subset: &[&str], keep: Keep) -> Result<BooleanColumn> {
+        duplicates::duplicated(self, subset, keep)
+    }
+
+    /// Rows which aren't `duplicated`, in order, with their labels.
+    pub fn drop_duplicates(&self, subset: &[&str], keep: Keep) -> Result<DataFrame> {
+        let duplicated = self.duplicated(subset, keep)?;
+        let rows: Vec<Option<usize>> = (0..self.len())
+            .filter(|&i| duplicated.get_value(i) == Value::Bool(false))
+            .map(Some)
+            .collect();
+        Ok(self.take(&rows))
+    }
+
+    /// Joins with `other` on the key columns `on`, which both frames
+    /// must have; see the join module.
+    pub fn join(&self, other: &DataFrame, on: &[&str], how: JoinType) -> Result<DataFrame> {
+        join::join(self, other, on, how)
+    }
+
+    /// Defers the operations on this frame until `collect`.
+    pub fn lazy(self) -> LazyFrame {
+        LazyFrame::from(self)
+    }
+
+    /// Groups rows by the values in the `keys` columns.
+    pub fn group_by(&self, keys: &[&str]) -> Result<GroupBy<'_>> {
+        GroupBy::new(self, keys)
+    }
+
+    /// Groups rows into fixed-size time bins like `1h`, by the
+    /// timestamp column `on`.
+    pub fn resample(&self, on: &str, freq: &str) -> Result<Resample<'_>> {
+        Resample::new(self, on, freq)
+    }
+
+    /// Windows over the last `window` of time (like `5min`) at each
+    /// row, on a frame indexed by increasing timestamps; rows with fewer
+    /// than `min_periods` valid values in a window come out null.
+    pub fn rolling_time(&self, window: &str, min_periods: usize) -> Result<TimeRolling<'_>> {
+        TimeRolling::new(self, window, min_periods)
+    }
+
+    /// Casts the named columns, erroring on the first row which can't
+    /// be converted.
+    pub fn cast(&self, casts: &[(&str, Dtype)]) -> Result<DataFrame> {
+        self.cast_columns(casts, true)
+    }
+
+    /// Casts the named columns, with nulls for the rows which can't
+    /// be converted.
+    pub fn cast_lenient(&self, casts: &[(&str, Dtype)]) -> Result<DataFrame> {
+        self.cast_columns(casts, false)
+    }
+
+    fn cast_columns(&self, casts: &[(&str, Dtype)], strict: bool) -> Result<DataFrame> {
+        let mut res = self.clone();
+        for &(name, dtype) in casts {
+            let position = self.position(name)?;
+            let column = &*res.columns[position];
+            res.columns[position] = if strict { column.cast(dtype)? } else { column.cast_lenient(dtype)? };
+        }
+        Ok(res)
+    }
+
+    /// One row per element of the list column `column`, repeating the
+    /// other columns. Null and empty lists give one row with a null
+    /// element, so no rows are dropped.
+    pub fn explode(&self, column: &str) -> Result<DataFrame> {
+        let position = self.position(column)?;
+        let lists = self.column_as::<ListColumn>(column)?;
+
+        // source row of each output row, and its element (if any)
+        let mut rows = Vec::new();
+        let mut elements = Vec::new();
+        for (row, w) in lists.offsets().windows(2).enumerate() {
+            if w[0] == w[1] {
+                rows.push(Some(row));
+                elements.push(None);
+            }
+            for element in w[0]..w[1] {
+                rows.push(Some(row));
+                elements.push(Some(element));
+            }
+        }
+
+        let mut res = self.take(&rows);
+        res.columns[position] = lists.values().take(&elements);
+        Ok(res)
+    }
+
+    /// Replaces the struct column `column` with one column per field,
+    /// named after the field. Fields are null where the struct is.
+    pub fn unnest(&self, column: &str) -> Result<DataFrame> {
+        let position = self.position(column)?;
+        let fields = self.column_as::<StructColumn>(column)?.unnest();
+
+        let mut res = DataFrame::new();
+        res.rows = self.rows.clone();
+        for (i, (name, column)) in self.columns().enumerate() {
+            if i == position {
+                for (name, field) in &fields {
+                    res.add_named_column(name, field.clone())?;
+                }
+            } else {
+                res.add_named_column(name, column.clone_box())?;
+            }
+        }
+        Ok(res)
+    }
+
+    fn position(&self, name: &str) -> Result<usize> {
+        self.column_names
+            .iter()
+            .position(|n| n == name)
+            .ok_or_else(|| Error::ColumnNotFound(name.to_owned()))
     }
 
     // TODO add melt
 }
 
+impl Default for DataFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use column::{Int8Column, StringColumn};
 
     #[test]
     fn dataframe_init() {
         let mut df = DataFrame::new();
-        df.add_column(Box::new(::column::Int8Column::new()));
+        df.add_column(Box::new(::column::Int8Column::new())).unwrap();
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let mut df = DataFrame::from_columns(vec![("1", Box::new(Int8Column::from(vec![1, 2])))]).unwrap();
+        match df.add_named_column("1", Box::new(Int8Column::from(vec![3, 4]))) {
+            Err(Error::DuplicateColumn(ref name)) if name == "1" => (),
+            res => panic!("{:?}", res),
+        }
+        // named "1" by position
+        assert!(df.add_column(Box::new(Int8Column::from(vec![3, 4]))).is_err());
+        assert!(df.add_column(Box::new(Int8Column::from(vec![3]))).is_err());
+        assert_eq!(df.width(), 1);
+    }
+
+    #[test]
+    fn named_columns() {
+        let mut df = DataFrame::from_columns(vec![
+            ("a", Box::new(Int8Column::from(vec![1, 2, 3]))),
+            ("b", Box::new(StringColumn::from(vec!["x", "y", "z"]))),
+        ]).unwrap();
+        assert_eq!(df.len(), 3);
+        assert_eq!(df.width(), 2);
+        assert_eq!(df.column_names(), &["a".to_owned(), "b".to_owned()]);
+        assert_eq!(df.column_as::<StringColumn>("b").unwrap().get(1), Some("y"));
+        assert!(df.column_as::<Int8Column>("b").is_err());
+        assert!(df.column("c").is_err());
+        assert!(df.add_named_column("c", Box::new(Int8Column::from(vec![1]))).is_err());
+
+        let taken = df.take(&[Some(2), None, Some(0)]);
+        assert_eq!(
+            taken.column_as::<Int8Column>("a").unwrap(),
+            &Int8Column::from(vec![Some(3), None, Some(1)])
+        );
+        assert_eq!(
+            taken.column_as::<StringColumn>("b").unwrap(),
+            &StringColumn::from(vec![Some("z"), None, Some("x")])
+        );
+    }
+
+    #[test]
+    fn cast() {
+        let df = DataFrame::from_columns(vec![
+            ("a", Box::new(StringColumn::from(vec!["1", "2", "x"])) as Box<dyn Column>),
+            ("b", Box::new(Int8Column::from(vec![1, 2, 3]))),
+        ]).unwrap();
+        match df.cast(&[("b", Dtype::Float64), ("a", Dtype::Int16)]) {
+            Err(Error::Cast { row: 2, .. }) => (),
+            res => panic!("{:?}", res),
+        }
+        let res = df.cast_lenient(&[("b", Dtype::Float64), ("a", Dtype::Int16)]).unwrap();
+        assert_eq!(res.column("a").unwrap().dtype(), Dtype::Int16);
+        assert_eq!(res.column("a").unwrap().get_value(2), ::column::Value::Null);
+        assert_eq!(res.column("b").unwrap().dtype(), Dtype::Float64);
+        assert!(df.cast(&[("c", Dtype::Int8)]).is_err());
+    }
+
+    #[test]
+    fn explode_and_unnest() {
+        let lists = ListColumn::from_parts(
+            vec![0, 2, 2, 2, 3],
+            Box::new(Int8Column::from(vec![1, 2, 3])),
+            Some(vec![true, true, false, true].into_iter().collect()),
+        ).unwrap();
+        let df = DataFrame::from_columns(vec![
+            ("id", Box::new(StringColumn::from(vec!["a", "b", "c", "d"])) as Box<dyn Column>),
+            ("xs", Box::new(lists)),
+        ]).unwrap();
+
+        let exploded = df.explode("xs").unwrap();
+        assert_eq!(
+            exploded.column_as::<StringColumn>("id").unwrap(),
+            &StringColumn::from(vec!["a", "a", "b", "c", "d"])
+        );
+        assert_eq!(
+            exploded.column_as::<Int8Column>("xs").unwrap(),
+            &Int8Column::from(vec![Some(1), Some(2), None, None, Some(3)])
+        );
+        assert!(df.explode("id").is_err());
+
+        let point = StructColumn::new(vec![
+            ("x", Box::new(Int8Column::from(vec![1, 2])) as Box<dyn Column>),
+            ("y", Box::new(Int8Column::from(vec![3, 4]))),
+        ], Some(vec![true, false].into_iter().collect())).unwrap();
+        let df = DataFrame::from_columns(vec![
+            ("point", Box::new(point) as Box<dyn Column>),
+            ("id", Box::new(StringColumn::from(vec!["a", "b"]))),
+        ]).unwrap();
+        let unnested = df.unnest("point").unwrap();
+        assert_eq!(unnested.column_names(), &["x", "y", "id"]);
+        assert_eq!(
+            unnested.column_as::<Int8Column>("y").unwrap(),
+            &Int8Column::from(vec![Some(3), None])
+        );
+        assert!(df.unnest("id").is_err());
+    }
+
+    #[test]
+    fn nlargest_and_nsmallest() {
+        let df = DataFrame::from_columns(vec![
+            ("id", Box::new(StringColumn::from(vec!["a", "b", "c", "d", "e"])) as Box<dyn Column>),
+            ("x", Box::new(::column::Float64Column::from(vec![
+                Some(2.0), None, Some(5.0), Some(f64::NAN), Some(2.0),
+            ]))),
+        ]).unwrap();
+        let ids = |df: &DataFrame| -> Vec<Value> {
+            let id = df.column("id").unwrap();
+            (0..df.len()).map(|i| id.get_value(i)).collect()
+        };
+        let strs = |xs: &[&str]| -> Vec<Value> { xs.iter().map(|x| Value::Str((*x).to_owned())).collect() };
+
+        assert_eq!(ids(&df.nlargest(2, "x").unwrap()), strs(&["c", "a"]));
+        assert_eq!(ids(&df.nsmallest(2, "x").unwrap()), strs(&["a", "e"]));
+        assert_eq!(ids(&df.nlargest(9, "x").unwrap()), strs(&["c", "a", "e"]));
+        assert_eq!(df.nsmallest(0, "x").unwrap().len(), 0);
+        assert!(df.nlargest(1, "y").is_err());
+    }
+
+    #[test]
+    fn index_and_loc() {
+        let df = DataFrame::from_columns(vec![
+            ("key", Box::new(StringColumn::from(vec!["a", "b", "a"])) as Box<dyn Column>),
+            ("x", Box::new(Int8Column::from(vec![1, 2, 3]))),
+        ]).unwrap();
+        assert!(df.index().is_none());
+        assert_eq!(df.loc(&Value::Int(1)).unwrap().column("x").unwrap().get_value(0), Value::Int(2));
+
+        let indexed = df.set_index("key").unwrap();
+        assert_eq!(indexed.column_names(), &["x"]);
+        assert_eq!(indexed.index().unwrap().name(), Some("key"));
+        let rows = indexed.loc(&Value::Str("a".to_owned())).unwrap();
+        assert_eq!(rows.column_as::<Int8Column>("x").unwrap(), &Int8Column::from(vec![1, 3]));
+        assert_eq!(rows.index().unwrap().len(), 2);
+        match indexed.loc(&Value::Str("c".to_owned())) {
+            Err(Error::LabelNotFound(_)) => (),
+            res => panic!("{:?}", res),
+        }
+        let range = indexed.loc_range(&Value::Str("a".to_owned()), &Value::Str("az".to_owned()));
+        assert_eq!(range.column_as::<Int8Column>("x").unwrap(), &Int8Column::from(vec![1, 3]));
+        assert_eq!(indexed.select(&["x"]).unwrap().index(), indexed.index());
+
+        let sorted = indexed.sort("x", true).unwrap();
+        assert_eq!(sorted.index().unwrap().get(0), Value::Str("a".to_owned()));
+        let reset = sorted.reset_index().unwrap();
+        assert!(reset.index().is_none());
+        assert_eq!(reset.column_names(), &["key", "x"]);
+        assert_eq!(
+            reset.column_as::<StringColumn>("key").unwrap(),
+            &StringColumn::from(vec!["a", "b", "a"])
+        );
+        assert!(df.set_index("x").unwrap().index().is_some());
+
+        let mut taken = indexed.clone();
+        taken.add_named_column("key", Box::new(Int8Column::from(vec![1, 2, 3]))).unwrap();
+        match taken.reset_index() {
+            Err(Error::DuplicateColumn(ref name)) if name == "key" => (),
+            res => panic!("{:?}", res),
+        }
+        assert!(df.set_index("y").is_err());
+    }
+
+    #[test]
+    fn aligned_arithmetic() {
+        let a = DataFrame::from_columns(vec![
+            ("k", Box::new(StringColumn::from(vec!["x", "y"])) as Box<dyn Column>),
+            ("v", Box::new(Int8Column::from(vec![1, 2]))),
+            ("only_a", Box::new(Int8Column::from(vec![1, 1]))),
+        ]).unwrap().set_index("k").unwrap();
+        let b = DataFrame::from_columns(vec![
+            ("k", Box::new(StringColumn::from(vec!["z", "y"])) as Box<dyn Column>),
+            ("v", Box::new(::column::Float64Column::from(vec![10.0, 20.0]))),
+        ]).unwrap().set_index("k").unwrap();
+
+        let sum = a.add(&b).unwrap();
+        assert_eq!(sum.column_names(), &["v", "only_a"]);
+        assert_eq!(
+            sum.index().unwrap().labels(),
+            &Labels::Str(vec!["x".to_owned(), "y".to_owned(), "z".to_owned()])
+        );
+        assert_eq!(
+            sum.column_as::<::column::Float64Column>("v").unwrap(),
+            &::column::Float64Column::from(vec![None, Some(22.0), None])
+        );
+        assert_eq!(sum.column("only_a").unwrap().dtype(), Dtype::Int64);
+        let only_a = sum.column("only_a").unwrap();
+        assert!((0..3).all(|i| only_a.is_null(i)));
+
+        // same labels line up by position
+        let diff = a.sub(&a).unwrap();
+        assert_eq!(diff.column("v").unwrap().get_value(1), Value::Int(0));
+        assert!(a.add(&a.reset_index().unwrap()).is_err());
+    }
+
+    #[test]
+    fn multi_index_from_group_by() {
+        let df = DataFrame::from_columns(vec![
+            ("a", Box::new(StringColumn::from(vec!["x", "y", "x", "x"])) as Box<dyn Column>),
+            ("b", Box::new(Int8Column::from(vec![1, 1, 2, 1]))),
+            ("v", Box::new(Int8Column::from(vec![1, 2, 3, 4]))),
+        ]).unwrap();
+        let sums = df.group_by(&["a", "b"]).unwrap()
+            .agg(&[("v", Agg::Sum)]).unwrap()
+            .set_multi_index(&["a", "b"]).unwrap();
+        assert_eq!(sums.column_names(), &["v"]);
+
+        let x = sums.xs(&[Value::Str("x".to_owned())]).unwrap();
+        assert_eq!(x.column("v").unwrap().get_value(0), Value::Int(5));
+        assert_eq!(x.len(), 2);
+        assert!(sums.xs(&[Value::Str("z".to_owned())]).is_err());
+        assert!(df.xs(&[]).is_err());
+        assert!(sums.xs(&[Value::Str("x".to_owned()), Value::Int(1), Value::Int(1)]).is_err());
+        assert!(sums.swap_levels(0, 5).is_err());
+        assert!(df.set_multi_index(&[]).is_err());
+
+        let swapped = sums.swap_levels(0, 1).unwrap();
+        let y = swapped.xs(&[Value::Int(1), Value::Str("y".to_owned())]).unwrap();
+        assert_eq!(y.column("v").unwrap().get_value(0), Value::Int(2));
+
+        let reset = swapped.reset_index().unwrap();
+        assert!(reset.multi_index().is_none());
+        assert_eq!(reset.column_names(), &["b", "a", "v"]);
+        assert_eq!(reset.column("a").unwrap().get_value(1), Value::Str("y".to_owned()));
     }
 }
@@ -0,0 +1,554 @@
+//! Hierarchical labels
+//!
+//! A MultiIndex labels each row (or column) with a tuple, one value
+//! per level, like the keys of a group-by on several columns. Each level
+//! is stored the way CategoricalVec stores strings: a dictionary of its
+//! distinct labels, in order of first appearance, and a packed code per
+//! row pointing into it. Lookups and reshaping work on the codes.
+//!
+//! `unstack` moves a level of the row labels into the columns, and
+//! `stack` moves the innermost level of the column labels back into the
+//! rows. Frames only have column names, so hierarchical column labels
+//! are also flattened into names, joined with `_`.
+
+use std::collections::HashMap;
+
+use llamas_categorical::Codes;
+
+use column::{Column, Dtype, Int64Column, StringColumn, Value};
+use error::{Error, Result};
+use super::{DataFrame, Index, RowIndex};
+
+/// Separator between the labels of a flattened name.
+pub const NAME_SEPARATOR: &str = "_";
+
+#[derive(Debug, Clone)]
+struct Level {
+    name: Option<String>,
+    // distinct labels, in order of first appearance
+    categories: Box<dyn Column>,
+    lookup: HashMap<Value, usize>,
+    codes: Codes,
+}
+
+impl Level {
+    fn new(name: Option<String>, column: &dyn Column) -> Self {
+        let mut lookup = HashMap::new();
+        let mut first_rows = Vec::new();
+        let mut codes = Codes::new();
+        for i in 0..column.len() {
+            let next = first_rows.len();
+            let code = *lookup.entry(column.get_value(i)).or_insert(next);
+            if code == next {
+                first_rows.push(Some(i));
+            }
+            codes.push(code);
+        }
+        Level {
+            name,
+            categories: column.take(&first_rows),
+            lookup,
+            codes,
+        }
+    }
+
+    fn code(&self, i: usize) -> usize {
+        self.codes.get(i).expect("row out of bounds")
+    }
+
+    /// The label of every row, as a column.
+    fn to_column(&self) -> Box<dyn Column> {
+        let codes: Vec<Option<usize>> = self.codes.iter().map(Some).collect();
+        self.categories.take(&codes)
+    }
+
+    /// Same dictionary, codes of the given rows.
+    fn take(&self, rows: &[usize]) -> Level {
+        let mut codes = Codes::new();
+        codes.extend(rows.iter().map(|&i| self.code(i)));
+        Level {
+            codes,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MultiIndex {
+    levels: Vec<Level>,
+}
+
+impl MultiIndex {
+    /// One level per (name, column); there must be at least one, and
+    /// the columns must be the same length. Nulls are labels like any
+    /// other.
+    pub fn from_columns(levels: Vec<(&str, &dyn Column)>) -> Result<Self> {
+        let levels = levels.into_iter()
+            .map(|(name, column)| (Some(name.to_owned()), column))
+            .collect();
+        MultiIndex::from_levels(levels)
+    }
+
+    fn from_levels(levels: Vec<(Option<String>, &dyn Column)>) -> Result<Self> {
+        let len = match levels.first() {
+            Some(&(_, column)) => column.len(),
+            None => return Err(Error::TypeMismatch {
+                expected: "at least one level".to_owned(),
+                found: "0 levels".to_owned(),
+            }),
+        };
+        if let Some(&(_, column)) = levels.iter().find(|&&(_, column)| column.len() != len) {
+            return Err(Error::LengthMismatch {
+                expected: len,
+                found: column.len(),
+            });
+        }
+        Ok(MultiIndex {
+            levels: levels.into_iter().map(|(name, column)| Level::new(name, column)).collect(),
+        })
+    }
+
+    fn from_boxed(levels: &[(Option<String>, Box<dyn Column>)]) -> Result<Self> {
+        MultiIndex::from_levels(levels.iter().map(|(name, column)| (name.clone(), &**column)).collect())
+    }
+
+    pub fn n_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Number of labelled rows (or columns).
+    pub fn len(&self) -> usize {
+        self.levels[0].codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn names(&self) -> Vec<Option<&str>> {
+        self.levels.iter().map(|level| level.name.as_deref()).collect()
+    }
+
+    /// Label tuple of row `i`.
+    pub fn get(&self, i: usize) -> Vec<Value> {
+        self.levels.iter()
+            .map(|level| level.categories.get_value(level.code(i)))
+            .collect()
+    }
+
+    /// Distinct labels of `level`, in order of first appearance.
+    pub fn categories(&self, level: usize) -> &dyn Column {
+        &*self.levels[level].categories
+    }
+
+    /// Per-row codes of `level`, indexing into its categories.
+    pub fn codes(&self, level: usize) -> &Codes {
+        &self.levels[level].codes
+    }
+
+    /// The label of `level` for every row, as a column.
+    pub fn level_values(&self, level: usize) -> Box<dyn Column> {
+        self.levels[level].to_column()
+    }
+
+    /// Rows whose labels start with `key`, which can be shorter than
+    /// the number of levels but not longer.
+    pub fn rows(&self, key: &[Value]) -> Result<Vec<usize>> {
+        if key.len() > self.levels.len() {
+            return Err(Error::LengthMismatch {
+                expected: self.levels.len(),
+                found: key.len(),
+            });
+        }
+        let codes: Option<Vec<usize>> = self.levels.iter()
+            .zip(key)
+            .map(|(level, label)| level.lookup.get(label).cloned())
+            .collect();
+        let codes = match codes {
+            Some(codes) => codes,
+            None => return Ok(Vec::new()),
+        };
+        Ok((0..self.len())
+            .filter(|&i| self.levels.iter().zip(&codes).all(|(level, &code)| level.code(i) == code))
+            .collect())
+    }
+
+    /// Levels `i` and `j` trade places.
+    pub fn swap_levels(&self, i: usize, j: usize) -> Result<MultiIndex> {
+        self.check_level(i)?;
+        self.check_level(j)?;
+        let mut res = self.clone();
+        res.levels.swap(i, j);
+        Ok(res)
+    }
+
+    /// Errors unless there's a level `level`.
+    fn check_level(&self, level: usize) -> Result<()> {
+        if level < self.levels.len() {
+            Ok(())
+        } else {
+            Err(Error::TypeMismatch {
+                expected: format!("a level below {}", self.levels.len()),
+                found: format!("level {}", level),
+            })
+        }
+    }
+
+    /// Labels of the given rows, in that order. Every level keeps its
+    /// categories, even ones no longer used.
+    pub fn take(&self, rows: &[usize]) -> MultiIndex {
+        MultiIndex {
+            levels: self.levels.iter().map(|level| level.take(rows)).collect(),
+        }
+    }
+
+    /// Each label tuple as one string, joined with `sep`.
+    pub fn flatten(&self, sep: &str) -> Vec<String> {
+        (0..self.len())
+            .map(|i| {
+                self.get(i).iter().map(|label| label.to_string()).collect::<Vec<_>>().join(sep)
+            })
+            .collect()
+    }
+}
+
+fn two_levels<'a>(index: Option<&'a MultiIndex>, which: &str) -> Result<&'a MultiIndex> {
+    index.filter(|index| index.n_levels() >= 2).ok_or_else(|| Error::TypeMismatch {
+        expected: format!("{} labels with at least two levels", which),
+        found: index.map_or(0, |index| index.n_levels()).to_string() + " levels",
+    })
+}
+
+/// Dtype which all of `columns` can be cast to: their own if they
+/// share one, Int64 for mixed ints and Float64 for ints and floats.
+fn common_dtype(columns: &[&dyn Column]) -> Result<Dtype> {
+    let first = columns[0].dtype();
+    if columns.iter().all(|column| column.dtype() == first) {
+        return Ok(first);
+    }
+    if !columns.iter().all(|column| column.dtype().is_numeric()) {
+        let dtypes: Vec<String> = columns.iter().map(|column| column.dtype().to_string()).collect();
+        return Err(Error::TypeMismatch {
+            expected: "columns of one dtype, or numeric columns".to_owned(),
+            found: dtypes.join(", "),
+        });
+    }
+    let is_int = |dtype| matches!(dtype, Dtype::Int8 | Dtype::Int16 | Dtype::Int32 | Dtype::Int64);
+    if columns.iter().all(|column| is_int(column.dtype())) {
+        Ok(Dtype::Int64)
+    } else {
+        Ok(Dtype::Float64)
+    }
+}
+
+/// Groups rows by their codes in `levels`, in order of first
+/// appearance: the group of each row, and the first row of each group.
+fn group_codes(index: &MultiIndex, levels: &[usize]) -> (Vec<usize>, Vec<usize>) {
+    let mut lookup: HashMap<Vec<usize>, usize> = HashMap::new();
+    let mut groups = Vec::with_capacity(index.len());
+    let mut first_rows = Vec::new();
+    for i in 0..index.len() {
+        let key: Vec<usize> = levels.iter().map(|&level| index.levels[level].code(i)).collect();
+        let next = first_rows.len();
+        let group = *lookup.entry(key).or_insert(next);
+        if group == next {
+            first_rows.push(i);
+        }
+        groups.push(group);
+    }
+    (groups, first_rows)
+}
+
+/// Names, and column labels if more than one level is left.
+fn column_labels(levels: Vec<(Option<String>, Box<dyn Column>)>) -> Result<(Vec<String>, Option<MultiIndex>)> {
+    if levels.len() == 1 {
+        let column = &levels[0].1;
+        let names = (0..column.len()).map(|i| column.get_value(i).to_string()).collect();
+        return Ok((names, None));
+    }
+    let index = MultiIndex::from_boxed(&levels)?;
+    Ok((index.flatten(NAME_SEPARATOR), Some(index)))
+}
+
+/// Moves row level `level` into the columns: one row per distinct
+/// label of the other levels, and a column per (column, label of
+/// `level`), null where that combination had no row.
+pub(crate) fn unstack(df: &DataFrame, level: usize) -> Result<DataFrame> {
+    let index = two_levels(df.multi_index(), "row")?;
+    index.check_level(level)?;
+    let keep: Vec<usize> = (0..index.n_levels()).filter(|&l| l != level).collect();
+    let (groups, first_rows) = group_codes(index, &keep);
+    let unstacked = &index.levels[level];
+
+    // source row of each (label of `level`, output row)
+    let n_categories = unstacked.categories.len();
+    let mut sources = vec![vec![None; first_rows.len()]; n_categories];
+    for (row, &group) in groups.iter().enumerate() {
+        let source = &mut sources[unstacked.code(row)][group];
+        if source.is_some() {
+            let label = Value::List(index.get(row));
+            return Err(Error::DuplicateLabel(label.to_string()));
+        }
+        *source = Some(row);
+    }
+
+    // labels of the input columns, then the unstacked level's
+    let mut column_levels: Vec<(Option<String>, Box<dyn Column>)> = match df.column_index {
+        Some(ref columns) => columns.levels.iter()
+            .map(|level| (level.name.clone(), level.to_column()))
+            .collect(),
+        None => {
+            let names: Vec<&str> = df.column_names.iter().map(|name| name.as_str()).collect();
+            vec![(None, Box::new(StringColumn::from(names)))]
+        },
+    };
+    let pairs: Vec<(usize, usize)> = (0..df.width())
+        .flat_map(|column| (0..n_categories).map(move |code| (column, code)))
+        .collect();
+    for level in &mut column_levels {
+        let rows: Vec<_> = pairs.iter().map(|&(column, _)| Some(column)).collect();
+        level.1 = level.1.take(&rows);
+    }
+    let codes: Vec<_> = pairs.iter().map(|&(_, code)| Some(code)).collect();
+    column_levels.push((unstacked.name.clone(), unstacked.categories.take(&codes)));
+    let (names, column_index) = column_labels(column_levels)?;
+
+    let mut res = DataFrame::new();
+    for (&(column, code), name) in pairs.iter().zip(&names) {
+        res.add_named_column(name, df.columns[column].take(&sources[code]))?;
+    }
+    res.column_index = column_index;
+    let rows = index.take(&first_rows);
+    if keep.len() == 1 {
+        let level = &rows.levels[keep[0]];
+        let name = level.name.clone().unwrap_or_else(|| "index".to_owned());
+        res.rows = RowIndex::Flat(Index::from_column(&name, &*level.to_column())?);
+    } else {
+        res.rows = RowIndex::Multi(MultiIndex {
+            levels: keep.iter().map(|&l| rows.levels[l].clone()).collect(),
+        });
+    }
+    Ok(res)
+}
+
+/// Moves the innermost column level into the rows: each row becomes
+/// one row per label of that level, and the columns are the distinct
+/// labels of the other levels. Combinations without a column are null.
+/// The columns stacked together are cast to a common dtype (see
+/// `common_dtype`), and two columns with the same labels are an error.
+pub(crate) fn stack(df: &DataFrame) -> Result<DataFrame> {
+    let columns = two_levels(df.column_index.as_ref(), "column")?;
+    let last = columns.n_levels() - 1;
+    let keep: Vec<usize> = (0..last).collect();
+    let (groups, first_columns) = group_codes(columns, &keep);
+    let stacked = &columns.levels[last];
+    let n_categories = stacked.categories.len();
+
+    // input column of each (output column, label of the stacked level)
+    let mut sources = vec![vec![None; n_categories]; first_columns.len()];
+    for (column, &group) in groups.iter().enumerate() {
+        let source = &mut sources[group][stacked.code(column)];
+        if source.is_some() {
+            let label = Value::List(columns.get(column));
+            return Err(Error::DuplicateLabel(label.to_string()));
+        }
+        *source = Some(column);
+    }
+
+    let column_levels = keep.iter()
+        .map(|&l| {
+            let level = &columns.levels[l];
+            let rows: Vec<_> = first_columns.iter().map(|&i| Some(level.code(i))).collect();
+            (level.name.clone(), level.categories.take(&rows))
+        })
+        .collect();
+    let (names, column_index) = column_labels(column_levels)?;
+
+    let mut res = DataFrame::new();
+    for (sources, name) in sources.iter().zip(&names) {
+        // the grouped columns cast to one dtype, so their values can
+        // go in one column
+        let parts: Vec<&dyn Column> = sources.iter().flatten().map(|&source| &*df.columns[source]).collect();
+        let dtype = common_dtype(&parts)?;
+        let cast = sources.iter()
+            .map(|source| match *source {
+                Some(source) if df.columns[source].dtype() != dtype => df.columns[source].cast(dtype).map(Some),
+                Some(source) => Ok(Some(df.columns[source].clone_box())),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut column = cast.iter().flatten().next().expect("every group has a column").new_empty();
+        for row in 0..df.len() {
+            for source in &cast {
+                let value = source.as_ref().map_or(Value::Null, |source| source.get_value(row));
+                column.push_value(value)?;
+            }
+        }
+        res.add_named_column(name, column)?;
+    }
+    res.column_index = column_index;
+
+    // the old row labels repeated, then the stacked labels cycling
+    let rows: Vec<_> = (0..df.len()).flat_map(|row| (0..n_categories).map(move |_| Some(row))).collect();
+    let mut row_levels: Vec<(Option<String>, Box<dyn Column>)> = match df.rows {
+        RowIndex::Multi(ref index) => index.levels.iter()
+            .map(|level| (level.name.clone(), level.to_column().take(&rows)))
+            .collect(),
+        RowIndex::Flat(ref index) => vec![(index.name().map(|name| name.to_owned()), index.to_column().take(&rows))],
+        RowIndex::Range => {
+            let positions = Int64Column::from((0..df.len() as i64).collect::<Vec<_>>());
+            vec![(None, positions.take(&rows))]
+        },
+    };
+    let codes: Vec<_> = (0..rows.len()).map(|i| Some(i % n_categories)).collect();
+    row_levels.push((stacked.name.clone(), stacked.categories.take(&codes)));
+    res.rows = RowIndex::Multi(MultiIndex::from_boxed(&row_levels)?);
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Float64Column, Int8Column};
+
+    fn index() -> MultiIndex {
+        let a = StringColumn::from(vec!["x", "x", "y", "y"]);
+        let b = Int8Column::from(vec![1, 2, 1, 2]);
+        MultiIndex::from_columns(vec![("a", &a as &dyn Column), ("b", &b)]).unwrap()
+    }
+
+    #[test]
+    fn levels_and_lookups() {
+        let index = index();
+        assert_eq!(index.n_levels(), 2);
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.names(), vec![Some("a"), Some("b")]);
+        assert_eq!(index.get(2), vec![Value::Str("y".to_owned()), Value::Int(1)]);
+        assert_eq!(index.categories(1).len(), 2);
+        assert_eq!(index.codes(0).iter().collect::<Vec<_>>(), vec![0, 0, 1, 1]);
+
+        assert_eq!(index.rows(&[Value::Str("y".to_owned())]).unwrap(), vec![2, 3]);
+        assert_eq!(index.rows(&[Value::Str("x".to_owned()), Value::Int(2)]).unwrap(), vec![1]);
+        assert!(index.rows(&[Value::Str("z".to_owned())]).unwrap().is_empty());
+        assert_eq!(index.rows(&[]).unwrap(), vec![0, 1, 2, 3]);
+        match index.rows(&[Value::Str("x".to_owned()), Value::Int(1), Value::Int(1)]) {
+            Err(Error::LengthMismatch { expected: 2, found: 3 }) => (),
+            res => panic!("{:?}", res),
+        }
+
+        let swapped = index.swap_levels(0, 1).unwrap();
+        assert_eq!(swapped.names(), vec![Some("b"), Some("a")]);
+        assert_eq!(swapped.flatten("_"), vec!["1_x", "2_x", "1_y", "2_y"]);
+        assert_eq!(index.take(&[3]).get(0), vec![Value::Str("y".to_owned()), Value::Int(2)]);
+
+        let short = Int8Column::from(vec![1]);
+        assert!(MultiIndex::from_columns(vec![("a", &short as &dyn Column), ("b", &Int8Column::new())]).is_err());
+        assert!(MultiIndex::from_columns(vec![]).is_err());
+        assert!(index.swap_levels(0, 5).is_err());
+    }
+
+    #[test]
+    fn unstack_and_stack() {
+        let df = DataFrame::from_columns(vec![
+            ("a", Box::new(StringColumn::from(vec!["x", "x", "y"])) as Box<dyn Column>),
+            ("b", Box::new(Int8Column::from(vec![1, 2, 2]))),
+            ("v", Box::new(Float64Column::from(vec![0.5, 1.0, 2.0]))),
+        ]).unwrap().set_multi_index(&["a", "b"]).unwrap();
+
+        let wide = df.unstack(1).unwrap();
+        assert_eq!(wide.column_names(), &["v_1", "v_2"]);
+        assert_eq!(wide.index().unwrap().name(), Some("a"));
+        assert_eq!(
+            wide.column_as::<Float64Column>("v_1").unwrap(),
+            &Float64Column::from(vec![Some(0.5), None])
+        );
+        assert_eq!(wide.column_index().unwrap().names(), vec![None, Some("b")]);
+        let v_2 = wide.select(&["v_2"]).unwrap();
+        assert_eq!(v_2.column_index().unwrap().get(0), vec![Value::Str("v".to_owned()), Value::Int(2)]);
+        assert_eq!(v_2.index(), wide.index());
+
+        let long = wide.stack().unwrap();
+        assert_eq!(long.column_names(), &["v"]);
+        let index = long.multi_index().unwrap();
+        assert_eq!(index.names(), vec![Some("a"), Some("b")]);
+        assert_eq!(index.get(1), vec![Value::Str("x".to_owned()), Value::Int(2)]);
+        assert_eq!(
+            long.column_as::<Float64Column>("v").unwrap(),
+            &Float64Column::from(vec![Some(0.5), Some(1.0), None, Some(2.0)])
+        );
+
+        assert!(wide.unstack(0).is_err());
+        match df.unstack(5) {
+            Err(Error::TypeMismatch { .. }) => (),
+            res => panic!("{:?}", res),
+        }
+        assert!(df.stack().is_err());
+        let dup = DataFrame::from_columns(vec![
+            ("a", Box::new(Int8Column::from(vec![1, 1])) as Box<dyn Column>),
+            ("b", Box::new(Int8Column::from(vec![2, 2]))),
+            ("v", Box::new(Int8Column::from(vec![3, 4]))),
+        ]).unwrap().set_multi_index(&["a", "b"]).unwrap();
+        match dup.unstack(1) {
+            Err(Error::DuplicateLabel(ref label)) if label == "[1, 2]" => (),
+            res => panic!("{:?}", res),
+        }
+    }
+
+    /// A frame with the given columns, labelled by `outer` and `inner`.
+    fn labelled(columns: Vec<Box<dyn Column>>, outer: Vec<&str>, inner: Vec<&str>) -> DataFrame {
+        let names: Vec<String> = (0..columns.len()).map(|i| format!("c{}", i)).collect();
+        let mut df = DataFrame::from_columns(names.iter().map(|name| name.as_str()).zip(columns).collect()).unwrap();
+        let (outer, inner) = (StringColumn::from(outer), StringColumn::from(inner));
+        df.column_index = Some(MultiIndex::from_columns(vec![("o", &outer as &dyn Column), ("i", &inner)]).unwrap());
+        df
+    }
+
+    #[test]
+    fn stack_mixed_columns() {
+        let df = labelled(
+            vec![
+                Box::new(Int8Column::from(vec![1, 2])),
+                Box::new(Float64Column::from(vec![0.5, 1.5])),
+                Box::new(Int8Column::from(vec![3, 4])),
+                Box::new(Int64Column::from(vec![i64::MAX, 5])),
+            ],
+            vec!["a", "a", "b", "b"],
+            vec!["x", "y", "x", "y"],
+        );
+        let long = df.stack().unwrap();
+        assert_eq!(long.column_names(), &["a", "b"]);
+        assert_eq!(
+            long.column_as::<Float64Column>("a").unwrap(),
+            &Float64Column::from(vec![1.0, 0.5, 2.0, 1.5])
+        );
+        assert_eq!(
+            long.column_as::<Int64Column>("b").unwrap(),
+            &Int64Column::from(vec![3, i64::MAX, 4, 5])
+        );
+
+        let intervals = || -> Box<dyn Column> {
+            Box::new(::column::IntervalColumn::from_values(vec![Some((0, 1)), None], ::column::Closed::Right).unwrap())
+        };
+        let df = labelled(vec![intervals(), intervals()], vec!["a", "a"], vec!["x", "y"]);
+        let long = df.stack().unwrap();
+        assert_eq!(long.column("a").unwrap().get_value(1), long.column("a").unwrap().get_value(0));
+        assert!(long.column("a").unwrap().is_null(3));
+
+        let strings = labelled(
+            vec![Box::new(Int8Column::from(vec![1])), Box::new(StringColumn::from(vec!["s"]))],
+            vec!["a", "a"],
+            vec!["x", "y"],
+        );
+        assert!(strings.stack().is_err());
+
+        let dup = labelled(
+            vec![Box::new(Int8Column::from(vec![1])), Box::new(Int8Column::from(vec![2]))],
+            vec!["a", "a"],
+            vec!["x", "x"],
+        );
+        match dup.stack() {
+            Err(Error::DuplicateLabel(ref label)) if label == "[a, x]" => (),
+            res => panic!("{:?}", res),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Time-based resampling.
+//!
+//! Rows are put into fixed-size bins by a timestamp column, where
+//! bins start at multiples of the freq counted from the epoch (same
+//! as `TimestampColumn::floor`). Every bin between the first and last
+//! timestamp is kept, even if no rows fall in it, so the output is
+//! evenly spaced.
+//!
+//! Downsampling aggregates the rows of each bin with the group-by
+//! machinery. Upsampling (`asfreq`, `ffill`) picks one row per bin,
+//! masking the bins with no row to pick.
+
+use column::{parse_freq, DataType, DataTypeMut, TimestampColumn};
+use error::Result;
+use super::groupby::{agg_name, aggregate, Agg};
+use super::DataFrame;
+
+pub struct Resample<'a> {
+    df: &'a DataFrame,
+    on: String,
+    // start of each bin
+    bins: TimestampColumn,
+    // row indices of each bin, in row order
+    groups: Vec<Vec<usize>>,
+}
+
+impl<'a> Resample<'a> {
+    pub(crate) fn new(df: &'a DataFrame, on: &str, freq: &str) -> Result<Self> {
+        let ts = df.column_as::<TimestampColumn>(on)?;
+        let step = parse_freq(freq, ts.unit())?;
+        let floored = ts.floor(freq)?;
+
+        let valid: Vec<i64> = floored.values().flatten().cloned().collect();
+        let first = valid.iter().min().cloned();
+        let last = valid.iter().max().cloned();
+
+        let mut bins = TimestampColumn::new(ts.unit());
+        if let Some(tz) = ts.tz() {
+            bins = bins.with_tz(tz);
+        }
+        let mut groups = Vec::new();
+        if let (Some(first), Some(last)) = (first, last) {
+            let n = ((last - first) / step) as usize + 1;
+            for i in 0..n {
+                bins.push(Some(first + i as i64 * step));
+                groups.push(Vec::new());
+            }
+            for (row, bin) in floored.values().enumerate() {
+                if let Some(&bin) = bin {
+                    groups[((bin - first) / step) as usize].push(row);
+                }
+            }
+        }
+
+        Ok(Resample {
+            df,
+            on: on.to_owned(),
+            bins,
+            groups,
+        })
+    }
+
+    /// Start of each bin.
+    pub fn bins(&self) -> &TimestampColumn {
+        &self.bins
+    }
+
+    /// Row indices of each bin.
+    pub fn groups(&self) -> &[Vec<usize>] {
+        &self.groups
+    }
+
+    /// One row per bin: the bin starts under the `on` column's name,
+    /// then a column per (column, aggregation) pair, named as in
+    /// `GroupBy::agg`. Empty bins give
+    /// a count or sum of 0, and null for other aggregations.
+    pub fn agg(&self, aggs: &[(&str, Agg)]) -> Result<DataFrame> {
+        let mut res = DataFrame::new();
+        res.add_named_column(&self.on, Box::new(self.bins.clone()))?;
+        for &(name, agg) in aggs {
+            let column = aggregate(self.df.column(name)?, &self.groups, agg)?;
+            res.add_named_column(&agg_name(&res, name, agg), column)?;
+        }
+        Ok(res)
+    }
+
+    /// Upsamples, taking the row whose timestamp is exactly at each
+    /// bin start. Bins without one are null in every column.
+    pub fn asfreq(&self) -> DataFrame {
+        let ts = self.timestamps();
+        let rows = self.bins.values()
+            .zip(&self.groups)
+            .map(|(bin, group)| {
+                let bin = bin.cloned();
+                group.iter().cloned().find(|&row| ts.get(row).and_then(|x| x).cloned() == bin)
+            })
+            .collect::<Vec<_>>();
+        self.with_bins(&rows)
+    }
+
+    /// Upsamples, taking the last row at or before each bin start, so
+    /// values carry forward into bins without rows of their own.
+    pub fn ffill(&self) -> DataFrame {
+        let ts = self.timestamps();
+        let mut order: Vec<(i64, usize)> = ts.values()
+            .enumerate()
+            .filter_map(|(row, x)| x.map(|&x| (x, row)))
+            .collect();
+        // stable, so ties keep the later row last
+        order.sort_by_key(|&(x, _)| x);
+
+        let mut next = 0;
+        let mut current = None;
+        let rows = self.bins.values()
+            .map(|bin| {
+                let bin = *bin.expect("bins are never null");
+                while next < order.len() && order[next].0 <= bin {
+                    current = Some(order[next].1);
+                    next += 1;
+                }
+                current
+            })
+            .collect::<Vec<_>>();
+        self.with_bins(&rows)
+    }
+
+    fn timestamps(&self) -> &TimestampColumn {
+        self.df.column_as::<TimestampColumn>(&self.on)
+            .expect("checked when resampling")
+    }
+
+    /// Rows taken from the frame, with the `on` column replaced by bins.
+    fn with_bins(&self, rows: &[Option<usize>]) -> DataFrame {
+        let mut res = DataFrame::new();
+        for (name, column) in self.df.columns() {
+            let column = if name == self.on {
+                Box::new(self.bins.clone())
+            } else {
+                column.take(rows)
+            };
+            // all columns are as long as the bins
+            res.add_named_column(name, column).unwrap();
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Column, Float64Column, Int64Column, StringColumn, Unit, Value};
+
+    fn frame() -> DataFrame {
+        let ts = TimestampColumn::parse(&StringColumn::from(vec![
+            Some("2017-06-01T00:10:00"),
+            Some("2017-06-01T00:50:00"),
+            None,
+            Some("2017-06-01T03:00:00"),
+            Some("2017-06-01T01:00:00"),
+        ]), Unit::Seconds).unwrap().with_tz("UTC");
+        DataFrame::from_columns(vec![
+            ("ts", Box::new(ts) as Box<dyn Column>),
+            ("v", Box::new(Float64Column::from(vec![1.0, 2.0, 3.0, 4.0, 5.0]))),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn downsample() {
+        let df = frame();
+        let resampled = df.resample("ts", "1h").unwrap();
+        assert_eq!(resampled.groups(), &[vec![0, 1], vec![4], vec![], vec![3]]);
+
+        let res = resampled.agg(&[("v", Agg::Sum), ("v", Agg::Mean), ("v", Agg::Count)]).unwrap();
+        let bins = res.column_as::<TimestampColumn>("ts").unwrap();
+        assert_eq!(bins, &TimestampColumn::date_range(
+            "2017-06-01", "2017-06-01T03:00:00", "1h", Unit::Seconds
+        ).unwrap().with_tz("UTC"));
+
+        let columns: Vec<_> = res.columns().map(|(_, column)| column).collect();
+        assert_eq!(
+            columns[1].as_any().downcast_ref::<Float64Column>().unwrap(),
+            &Float64Column::from(vec![3.0, 5.0, 0.0, 4.0])
+        );
+        assert_eq!(
+            columns[2].as_any().downcast_ref::<Float64Column>().unwrap(),
+            &Float64Column::from(vec![Some(1.5), Some(5.0), None, Some(4.0)])
+        );
+        assert_eq!(
+            columns[3].as_any().downcast_ref::<Int64Column>().unwrap(),
+            &Int64Column::from(vec![2, 1, 0, 1])
+        );
+
+        assert!(df.resample("v", "1h").is_err());
+        assert!(df.resample("ts", "1 fortnight").is_err());
+    }
+
+    #[test]
+    fn upsample() {
+        let df = frame();
+        let resampled = df.resample("ts", "30min").unwrap();
+        assert_eq!(resampled.bins().len(), 7);
+
+        let exact = resampled.asfreq();
+        assert_eq!(exact.len(), 7);
+        assert_eq!(
+            exact.column_as::<Float64Column>("v").unwrap(),
+            &Float64Column::from(vec![None, None, Some(5.0), None, None, None, Some(4.0)])
+        );
+
+        let filled = resampled.ffill();
+        assert_eq!(
+            filled.column_as::<Float64Column>("v").unwrap(),
+            &Float64Column::from(vec![None, Some(1.0), Some(5.0), Some(5.0), Some(5.0), Some(5.0), Some(4.0)])
+        );
+        assert_eq!(filled.column("ts").unwrap().get_value(0), Value::Timestamp(1496275200, Unit::Seconds));
+    }
+
+    #[test]
+    fn empty() {
+        let df = DataFrame::from_columns(vec![
+            ("ts", Box::new(TimestampColumn::from_values(vec![None], Unit::Seconds)) as Box<dyn Column>),
+        ]).unwrap();
+        let resampled = df.resample("ts", "1D").unwrap();
+        assert!(resampled.bins().is_empty());
+        assert!(resampled.agg(&[]).unwrap().is_empty());
+        assert!(resampled.ffill().is_empty());
+    }
+}
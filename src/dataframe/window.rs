@@ -0,0 +1,127 @@
+//! Time-based windows
+//!
+//! On a frame indexed by increasing timestamps, `rolling_time("5min")`
+//! gives each row the window of rows from the last five minutes: those
+//! with labels in `(t - 5min, t]`. The window slides like a rolling
+//! window over a column (see the column window module), just with a
+//! varying number of rows.
+
+use bit_vec::BitVec;
+
+use column::{parse_freq, Float64Column, Rolling};
+use error::{Error, Result};
+use super::{DataFrame, Labels};
+
+pub struct TimeRolling<'a> {
+    df: &'a DataFrame,
+    // first row of the window ending at each row
+    starts: Vec<usize>,
+    min_periods: usize,
+}
+
+impl<'a> TimeRolling<'a> {
+    pub(crate) fn new(df: &'a DataFrame, window: &str, min_periods: usize) -> Result<Self> {
+        let (times, unit) = match df.index().map(|index| index.labels()) {
+            Some(&Labels::Timestamp(ref times, unit)) => (times, unit),
+            _ => return Err(Error::TypeMismatch {
+                expected: "timestamp row labels".to_owned(),
+                found: df.index().map_or("none", |_| "other labels").to_owned(),
+            }),
+        };
+        if let Some(i) = (1..times.len()).find(|&i| times[i] < times[i - 1]) {
+            return Err(Error::TypeMismatch {
+                expected: "timestamp labels in increasing order".to_owned(),
+                found: format!("a decrease at row {}", i),
+            });
+        }
+        let width = parse_freq(window, unit)?;
+        if width <= 0 {
+            return Err(Error::InvalidFreq(window.to_owned()));
+        }
+
+        let mut starts = Vec::with_capacity(times.len());
+        let mut start = 0;
+        for &t in times {
+            while times[start] <= t - width {
+                start += 1;
+            }
+            starts.push(start);
+        }
+        Ok(TimeRolling {
+            df,
+            starts,
+            min_periods,
+        })
+    }
+
+    pub fn sum(&self) -> Result<DataFrame> {
+        self.apply(|rolling| rolling.sum())
+    }
+
+    pub fn mean(&self) -> Result<DataFrame> {
+        self.apply(|rolling| rolling.mean())
+    }
+
+    pub fn std(&self) -> Result<DataFrame> {
+        self.apply(|rolling| rolling.std())
+    }
+
+    pub fn min(&self) -> Result<DataFrame> {
+        self.apply(|rolling| rolling.min())
+    }
+
+    pub fn max(&self) -> Result<DataFrame> {
+        self.apply(|rolling| rolling.max())
+    }
+
+    /// Reduces the windows of every numeric column with `f`; other
+    /// columns are left out. Keeps the row labels.
+    fn apply<F>(&self, f: F) -> Result<DataFrame>
+        where F: Fn(&Rolling<'_, f64>) -> Float64Column
+    {
+        let mut res = DataFrame::new();
+        for (name, column) in self.df.columns().filter(|&(_, column)| column.dtype().is_numeric()) {
+            let values: Vec<f64> = (0..column.len())
+                .map(|i| column.get_value(i).as_f64().unwrap_or(0.0))
+                .collect();
+            let mask = BitVec::from_fn(column.len(), |i| !column.is_null(i));
+            let rolling = Rolling::with_starts(&values, &mask, self.starts.clone(), self.min_periods);
+            res.add_named_column(name, Box::new(f(&rolling)))?;
+        }
+        res.rows = self.df.rows.clone();
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use column::{Column, Int8Column, StringColumn, TimestampColumn, Unit, Value};
+    use dataframe::DataFrame;
+
+    #[test]
+    fn time_windows() {
+        let times = TimestampColumn::from_values(vec![Some(0), Some(60), Some(90), Some(300)], Unit::Seconds);
+        let df = DataFrame::from_columns(vec![
+            ("t", Box::new(times) as Box<dyn Column>),
+            ("x", Box::new(Int8Column::from(vec![Some(1), Some(2), None, Some(4)]))),
+            ("s", Box::new(StringColumn::from(vec!["a", "b", "c", "d"]))),
+        ]).unwrap().set_index("t").unwrap();
+
+        let rolling = df.rolling_time("2min", 1).unwrap();
+        let sums = rolling.sum().unwrap();
+        assert_eq!(sums.column_names(), &["x"]);
+        let x = sums.column("x").unwrap();
+        let values: Vec<Value> = (0..4).map(|i| x.get_value(i)).collect();
+        assert_eq!(values, vec![Value::Float(1.0), Value::Float(3.0), Value::Float(3.0), Value::Float(4.0)]);
+        assert_eq!(sums.index(), df.index());
+
+        let counted = df.rolling_time("1min", 2).unwrap().max().unwrap();
+        assert!((0..4).all(|i| counted.column("x").unwrap().is_null(i)));
+
+        assert!(df.reset_index().unwrap().rolling_time("1min", 1).is_err());
+        assert!(df.rolling_time("1 minute", 1).is_err());
+        assert!(df.rolling_time("0s", 1).is_err());
+        let unsorted = df.sort("x", true).unwrap();
+        assert!(unsorted.rolling_time("1min", 1).is_err());
+    }
+}
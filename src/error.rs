@@ -0,0 +1,113 @@
+//! Errors for fallible operations in llamas.
+
+use regex;
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A regex pattern which failed to compile.
+    Regex(regex::Error),
+    /// Reading a file or other source failed.
+    Io(io::Error),
+    /// Bytes which aren't valid utf8, at the listed rows.
+    InvalidUtf8 { rows: Vec<usize> },
+    /// A value at `row` which couldn't be parsed as `target`.
+    Parse { row: usize, value: String, target: &'static str },
+    /// A CSV record at `row` with a different number of fields than
+    /// the first.
+    FieldCount { row: usize, expected: usize, found: usize },
+    /// A value or column of the wrong dtype.
+    TypeMismatch { expected: String, found: String },
+    /// No column with this name in the DataFrame.
+    ColumnNotFound(String),
+    /// A column name already in the DataFrame.
+    DuplicateColumn(String),
+    /// A column with a different number of rows than the DataFrame.
+    LengthMismatch { expected: usize, found: usize },
+    /// A frequency (like `1h`, or quarters starting in some month)
+    /// which couldn't be understood or isn't valid.
+    InvalidFreq(String),
+    /// An interval at `row` whose left bound isn't at most its right
+    /// (including NaN bounds).
+    InvalidInterval { row: usize },
+    /// List offsets which go backwards at `row`, or a null row at
+    /// `row` which isn't empty.
+    InvalidOffsets { row: usize },
+    /// A value at `row` too large (or not finite) for `target`.
+    Overflow { row: usize, value: String, target: String },
+    /// An aggregation (like `sum`) whose result doesn't fit in `target`.
+    AggregateOverflow { agg: &'static str, target: String },
+    /// A value at `row` which couldn't be cast to `target`.
+    Cast { row: usize, value: String, target: String },
+    /// A row label which isn't in the index.
+    LabelNotFound(String),
+    /// A label on more than one row, where labels must be unique.
+    DuplicateLabel(String),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Regex(ref err) => write!(f, "invalid regex: {}", err),
+            Error::Io(ref err) => write!(f, "couldn't read: {}", err),
+            Error::InvalidUtf8 { ref rows } => write!(f, "invalid utf8 at rows {:?}", rows),
+            Error::Parse { row, ref value, target } => {
+                write!(f, "couldn't parse {:?} at row {} as {}", value, row, target)
+            },
+            Error::FieldCount { row, expected, found } => {
+                write!(f, "record at row {} has {} fields, expected {}", row, found, expected)
+            },
+            Error::TypeMismatch { ref expected, ref found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            },
+            Error::ColumnNotFound(ref name) => write!(f, "no column named {:?}", name),
+            Error::DuplicateColumn(ref name) => write!(f, "already a column named {:?}", name),
+            Error::LengthMismatch { expected, found } => {
+                write!(f, "expected {} rows, found {}", expected, found)
+            },
+            Error::InvalidFreq(ref freq) => write!(f, "invalid frequency {:?}", freq),
+            Error::InvalidInterval { row } => {
+                write!(f, "interval at row {} has its left bound above its right", row)
+            },
+            Error::InvalidOffsets { row } => write!(f, "invalid list offsets at row {}", row),
+            Error::Overflow { row, ref value, ref target } => {
+                write!(f, "{} at row {} doesn't fit in {}", value, row, target)
+            },
+            Error::AggregateOverflow { agg, ref target } => {
+                write!(f, "{} doesn't fit in {}", agg, target)
+            },
+            Error::Cast { row, ref value, ref target } => {
+                write!(f, "couldn't cast {} at row {} to {}", value, row, target)
+            },
+            Error::LabelNotFound(ref label) => write!(f, "no row labelled {}", label),
+            Error::DuplicateLabel(ref label) => write!(f, "more than one row labelled {}", label),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Regex(ref err) => Some(err),
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(err: regex::Error) -> Self {
+        Error::Regex(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,524 @@
+//! Expressions over the columns of a frame.
+//!
+//! Built with `col` and `lit` and combined with the usual operators:
+//! `(col("a") + lit(1)).gt(col("b"))`. Nothing is evaluated until the
+//! plan they're part of is collected.
+//!
+//! Evaluation goes through the dynamic Column interface (`Value`s), like
+//! group-by aggregation, so it works across dtypes. Operators follow
+//! the dtype and null rules of `column::combine`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops;
+
+use column::{empty_column, BooleanColumn, Column, Dtype, Operator, Value};
+use dataframe::{aggregate, Agg, DataFrame};
+use error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Column(String),
+    Literal(Value),
+    Binary {
+        left: Box<Expr>,
+        op: Operator,
+        right: Box<Expr>,
+    },
+    Not(Box<Expr>),
+    IsNull(Box<Expr>),
+    IsNotNull(Box<Expr>),
+    Cast(Box<Expr>, Dtype),
+    Alias(Box<Expr>, String),
+    /// Reduces the whole column to one row, or each group to one row
+    /// in a group-by.
+    Agg(Box<Expr>, Agg),
+}
+
+/// The column named `name`.
+pub fn col(name: &str) -> Expr {
+    Expr::Column(name.to_owned())
+}
+
+/// A constant, repeated to the length of the columns around it.
+pub fn lit<T: Into<Value>>(x: T) -> Expr {
+    Expr::Literal(x.into())
+}
+
+impl Expr {
+    fn binary(self, op: Operator, other: Expr) -> Expr {
+        Expr::Binary {
+            left: Box::new(self),
+            op,
+            right: Box::new(other),
+        }
+    }
+
+    pub fn equal(self, other: Expr) -> Expr {
+        self.binary(Operator::Eq, other)
+    }
+
+    pub fn not_equal(self, other: Expr) -> Expr {
+        self.binary(Operator::NotEq, other)
+    }
+
+    pub fn lt(self, other: Expr) -> Expr {
+        self.binary(Operator::Lt, other)
+    }
+
+    pub fn lt_eq(self, other: Expr) -> Expr {
+        self.binary(Operator::LtEq, other)
+    }
+
+    pub fn gt(self, other: Expr) -> Expr {
+        self.binary(Operator::Gt, other)
+    }
+
+    pub fn gt_eq(self, other: Expr) -> Expr {
+        self.binary(Operator::GtEq, other)
+    }
+
+    pub fn is_null(self) -> Expr {
+        Expr::IsNull(Box::new(self))
+    }
+
+    pub fn is_not_null(self) -> Expr {
+        Expr::IsNotNull(Box::new(self))
+    }
+
+    /// Strict cast; see `Column::cast`.
+    pub fn cast(self, dtype: Dtype) -> Expr {
+        Expr::Cast(Box::new(self), dtype)
+    }
+
+    /// Names the resulting column.
+    pub fn alias(self, name: &str) -> Expr {
+        Expr::Alias(Box::new(self), name.to_owned())
+    }
+
+    pub fn agg(self, agg: Agg) -> Expr {
+        Expr::Agg(Box::new(self), agg)
+    }
+
+    pub fn count(self) -> Expr {
+        self.agg(Agg::Count)
+    }
+
+    pub fn sum(self) -> Expr {
+        self.agg(Agg::Sum)
+    }
+
+    pub fn mean(self) -> Expr {
+        self.agg(Agg::Mean)
+    }
+
+    pub fn min(self) -> Expr {
+        self.agg(Agg::Min)
+    }
+
+    pub fn max(self) -> Expr {
+        self.agg(Agg::Max)
+    }
+
+    pub fn first(self) -> Expr {
+        self.agg(Agg::First)
+    }
+
+    pub fn last(self) -> Expr {
+        self.agg(Agg::Last)
+    }
+
+    /// Name of the resulting column: the alias if there is one,
+    /// otherwise the leftmost column it's computed from.
+    pub fn output_name(&self) -> String {
+        match *self {
+            Expr::Column(ref name) | Expr::Alias(_, ref name) => name.clone(),
+            Expr::Literal(_) => "literal".to_owned(),
+            Expr::Binary { ref left, .. } => left.output_name(),
+            Expr::Not(ref e) | Expr::IsNull(ref e) | Expr::IsNotNull(ref e) |
+            Expr::Cast(ref e, _) | Expr::Agg(ref e, _) => e.output_name(),
+        }
+    }
+
+    /// Names of the columns it reads, with repeats, left to right.
+    pub(crate) fn column_refs(&self, refs: &mut Vec<String>) {
+        match *self {
+            Expr::Column(ref name) => refs.push(name.clone()),
+            Expr::Literal(_) => (),
+            Expr::Binary { ref left, ref right, .. } => {
+                left.column_refs(refs);
+                right.column_refs(refs);
+            },
+            Expr::Not(ref e) | Expr::IsNull(ref e) | Expr::IsNotNull(ref e) |
+            Expr::Cast(ref e, _) | Expr::Alias(ref e, _) | Expr::Agg(ref e, _) => e.column_refs(refs),
+        }
+    }
+
+    /// Names of the columns it reads.
+    pub(crate) fn columns(&self) -> HashSet<String> {
+        let mut refs = Vec::new();
+        self.column_refs(&mut refs);
+        refs.into_iter().collect()
+    }
+
+    pub(crate) fn has_agg(&self) -> bool {
+        match *self {
+            Expr::Column(_) | Expr::Literal(_) => false,
+            Expr::Agg(..) => true,
+            Expr::Binary { ref left, ref right, .. } => left.has_agg() || right.has_agg(),
+            Expr::Not(ref e) | Expr::IsNull(ref e) | Expr::IsNotNull(ref e) |
+            Expr::Cast(ref e, _) | Expr::Alias(ref e, _) => e.has_agg(),
+        }
+    }
+
+    /// Replaces the columns named in `exprs` with their expression.
+    /// Each expression's output name is the name it replaces, so the
+    /// output name doesn't change.
+    pub(crate) fn substitute(self, exprs: &HashMap<String, Expr>) -> Expr {
+        match self {
+            Expr::Column(name) => exprs.get(&name).cloned().unwrap_or(Expr::Column(name)),
+            e => e.map_children(&|child| child.substitute(exprs)),
+        }
+    }
+
+    /// Evaluates the parts which don't read any columns, so that
+    /// `col("a") + (lit(1) + lit(2))` becomes `col("a") + lit(3)`.
+    /// Parts which fail to evaluate are left, to fail when collected.
+    pub(crate) fn fold_constants(self) -> Expr {
+        let e = self.map_children(&Expr::fold_constants);
+        match e {
+            // a folded cast would lose its dtype, since literals
+            // repeat into the widest column of their kind
+            Expr::Literal(_) | Expr::Alias(..) | Expr::Cast(..) => e,
+            _ if !e.has_agg() && e.columns().is_empty() => match e.eval(&DataFrame::new()) {
+                Ok(Evaluated::Scalar(value)) => Expr::Literal(value),
+                _ => e,
+            },
+            e => e,
+        }
+    }
+
+    /// Applies `f` to each direct child.
+    fn map_children<F>(self, f: &F) -> Expr
+        where F: Fn(Expr) -> Expr
+    {
+        let boxed = |e: Box<Expr>| Box::new(f(*e));
+        match self {
+            Expr::Column(_) | Expr::Literal(_) => self,
+            Expr::Binary { left, op, right } => Expr::Binary {
+                left: boxed(left),
+                op,
+                right: boxed(right),
+            },
+            Expr::Not(e) => Expr::Not(boxed(e)),
+            Expr::IsNull(e) => Expr::IsNull(boxed(e)),
+            Expr::IsNotNull(e) => Expr::IsNotNull(boxed(e)),
+            Expr::Cast(e, dtype) => Expr::Cast(boxed(e), dtype),
+            Expr::Alias(e, name) => Expr::Alias(boxed(e), name),
+            Expr::Agg(e, agg) => Expr::Agg(boxed(e), agg),
+        }
+    }
+
+    /// Evaluates against `df`, giving a column of `df.len()` rows, or
+    /// one row for an aggregation. Literals are repeated to `df.len()`.
+    pub(crate) fn evaluate(&self, df: &DataFrame) -> Result<Box<dyn Column>> {
+        self.eval(df)?.into_column(df.len())
+    }
+
+    fn eval(&self, df: &DataFrame) -> Result<Evaluated> {
+        let res = match *self {
+            Expr::Column(ref name) => Evaluated::Column(df.column(name)?.clone_box()),
+            Expr::Literal(ref value) => Evaluated::Scalar(value.clone()),
+            Expr::Binary { ref left, op, ref right } => binary(left.eval(df)?, op, right.eval(df)?)?,
+            Expr::Not(ref e) => {
+                let e = e.eval(df)?;
+                check_dtype(&e, Dtype::Boolean)?;
+                e.map(Dtype::Boolean, |value| match value {
+                    Value::Bool(x) => Value::Bool(!x),
+                    value => value,
+                })?
+            },
+            Expr::IsNull(ref e) => e.eval(df)?.map(Dtype::Boolean, |value| Value::Bool(value.is_null()))?,
+            Expr::IsNotNull(ref e) => e.eval(df)?.map(Dtype::Boolean, |value| Value::Bool(!value.is_null()))?,
+            Expr::Cast(ref e, dtype) => match e.eval(df)? {
+                Evaluated::Column(column) => Evaluated::Column(column.cast(dtype)?),
+                scalar => Evaluated::Scalar(scalar.into_column(1)?.cast(dtype)?.get_value(0)),
+            },
+            Expr::Alias(ref e, _) => e.eval(df)?,
+            Expr::Agg(ref e, agg) => {
+                let column = e.evaluate(df)?;
+                let rows: Vec<usize> = (0..column.len()).collect();
+                Evaluated::Column(aggregate(&*column, &[rows], agg)?)
+            },
+        };
+        Ok(res)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expr::Column(ref name) => write!(f, "col({:?})", name),
+            Expr::Literal(Value::Str(ref s)) => write!(f, "lit({:?})", s),
+            Expr::Literal(ref value) => write!(f, "lit({})", value),
+            Expr::Binary { ref left, op, ref right } => write!(f, "({} {} {})", left, op.symbol(), right),
+            Expr::Not(ref e) => write!(f, "!{}", e),
+            Expr::IsNull(ref e) => write!(f, "{}.is_null()", e),
+            Expr::IsNotNull(ref e) => write!(f, "{}.is_not_null()", e),
+            Expr::Cast(ref e, dtype) => write!(f, "{}.cast({})", e, dtype),
+            Expr::Alias(ref e, ref name) => write!(f, "{}.alias({:?})", e, name),
+            Expr::Agg(ref e, agg) => write!(f, "{}.{}()", e, agg.name()),
+        }
+    }
+}
+
+macro_rules! expr_op {
+    ($trait:ident, $method:ident, $op:expr) => {
+        impl ops::$trait for Expr {
+            type Output = Expr;
+
+            fn $method(self, other: Expr) -> Expr {
+                self.binary($op, other)
+            }
+        }
+    };
+}
+
+expr_op!(Add, add, Operator::Add);
+expr_op!(Sub, sub, Operator::Sub);
+expr_op!(Mul, mul, Operator::Mul);
+expr_op!(Div, div, Operator::Div);
+expr_op!(Rem, rem, Operator::Rem);
+expr_op!(BitAnd, bitand, Operator::And);
+expr_op!(BitOr, bitor, Operator::Or);
+
+impl ops::Not for Expr {
+    type Output = Expr;
+
+    fn not(self) -> Expr {
+        Expr::Not(Box::new(self))
+    }
+}
+
+/// A column, or a literal not yet repeated to any length.
+enum Evaluated {
+    Column(Box<dyn Column>),
+    Scalar(Value),
+}
+
+impl Evaluated {
+    fn dtype(&self) -> Result<Dtype> {
+        match *self {
+            Evaluated::Column(ref column) => Ok(column.dtype()),
+            Evaluated::Scalar(ref value) => literal_dtype(value),
+        }
+    }
+
+    fn len(&self) -> Option<usize> {
+        match *self {
+            Evaluated::Column(ref column) => Some(column.len()),
+            Evaluated::Scalar(_) => None,
+        }
+    }
+
+    fn get(&self, i: usize) -> Value {
+        match *self {
+            Evaluated::Column(ref column) => column.get_value(i),
+            Evaluated::Scalar(ref value) => value.clone(),
+        }
+    }
+
+    /// The column, or the literal repeated `len` times.
+    fn into_column(self, len: usize) -> Result<Box<dyn Column>> {
+        match self {
+            Evaluated::Column(column) => Ok(column),
+            Evaluated::Scalar(value) => {
+                let mut res = new_column(literal_dtype(&value)?);
+                for _ in 0..len {
+                    res.push_value(value.clone())?;
+                }
+                Ok(res)
+            },
+        }
+    }
+
+    /// Applies `f` to each value, giving a `dtype` column.
+    fn map<F>(self, dtype: Dtype, f: F) -> Result<Evaluated>
+        where F: Fn(Value) -> Value
+    {
+        match self {
+            Evaluated::Scalar(value) => Ok(Evaluated::Scalar(f(value))),
+            Evaluated::Column(column) => {
+                let mut res = new_column(dtype);
+                for i in 0..column.len() {
+                    res.push_value(f(column.get_value(i)))?;
+                }
+                Ok(Evaluated::Column(res))
+            },
+        }
+    }
+}
+
+/// Dtype of the column a literal repeats into. Null literals are
+/// float columns, which mix with anything numeric.
+fn literal_dtype(value: &Value) -> Result<Dtype> {
+    match *value {
+        Value::Null | Value::Float(_) => Ok(Dtype::Float64),
+        Value::Int(_) => Ok(Dtype::Int64),
+        Value::Bool(_) => Ok(Dtype::Boolean),
+        Value::Str(_) => Ok(Dtype::String),
+        Value::Bytes(_) => Ok(Dtype::Binary),
+        Value::Timestamp(_, unit) => Ok(Dtype::Timestamp(unit)),
+        Value::Date(_) => Ok(Dtype::Date),
+        Value::Timedelta(_, unit) => Ok(Dtype::Timedelta(unit)),
+        Value::Period(_, freq) => Ok(Dtype::Period(freq.check()?)),
+        ref value => Err(Error::TypeMismatch {
+            expected: "a literal which can be repeated into a column".to_owned(),
+            found: value.kind().to_owned(),
+        }),
+    }
+}
+
+fn new_column(dtype: Dtype) -> Box<dyn Column> {
+    // only called with dtypes that can be built from scratch
+    empty_column(dtype).unwrap()
+}
+
+fn check_dtype(e: &Evaluated, expected: Dtype) -> Result<()> {
+    let found = e.dtype()?;
+    if found == expected {
+        Ok(())
+    } else {
+        Err(Error::TypeMismatch {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        })
+    }
+}
+
+fn binary(left: Evaluated, op: Operator, right: Evaluated) -> Result<Evaluated> {
+    let dtype = op.result_dtype(left.dtype()?, right.dtype()?)?;
+    let len = match (left.len(), right.len()) {
+        (Some(a), Some(b)) if a != b => return Err(Error::LengthMismatch { expected: a, found: b }),
+        (Some(len), _) | (_, Some(len)) => len,
+        (None, None) => return op.apply(left.get(0), right.get(0), dtype).map(Evaluated::Scalar),
+    };
+    let mut res = new_column(dtype);
+    for i in 0..len {
+        res.push_value(op.apply(left.get(i), right.get(i), dtype)?)?;
+    }
+    Ok(Evaluated::Column(res))
+}
+
+/// Evaluates `exprs` against `df` as (name, column) pairs. Literals
+/// and one-row results (aggregations) are repeated to the length of
+/// the longest result, so `[col("a"), col("a").sum()]` works.
+pub(crate) fn evaluate_all(exprs: &[Expr], df: &DataFrame) -> Result<Vec<(String, Box<dyn Column>)>> {
+    let evaluated = exprs.iter().map(|e| e.eval(df)).collect::<Result<Vec<_>>>()?;
+    let len = evaluated.iter().filter_map(Evaluated::len).max().unwrap_or(df.len());
+    exprs.iter()
+        .zip(evaluated)
+        .map(|(e, evaluated)| Ok((e.output_name(), broadcast(evaluated.into_column(len)?, len)?)))
+        .collect()
+}
+
+/// `column` repeated to `len` rows if it has one row.
+pub(crate) fn broadcast(column: Box<dyn Column>, len: usize) -> Result<Box<dyn Column>> {
+    if column.len() == len {
+        Ok(column)
+    } else if column.len() == 1 {
+        Ok(column.take(&vec![Some(0); len]))
+    } else {
+        Err(Error::LengthMismatch {
+            expected: len,
+            found: column.len(),
+        })
+    }
+}
+
+/// Rows where `predicate` is true; null counts as false.
+pub(crate) fn selected_rows(predicate: &dyn Column) -> Result<Vec<Option<usize>>> {
+    let predicate = predicate.as_any()
+        .downcast_ref::<BooleanColumn>()
+        .ok_or_else(|| Error::TypeMismatch {
+            expected: "boolean predicate".to_owned(),
+            found: predicate.dtype().to_string(),
+        })?;
+    Ok((0..predicate.len())
+        .filter(|&i| predicate.get_value(i) == Value::Bool(true))
+        .map(Some)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Float64Column, Int8Column, Int64Column, StringColumn};
+
+    fn frame() -> DataFrame {
+        DataFrame::from_columns(vec![
+            ("a", Box::new(Int8Column::from(vec![Some(1), Some(2), None, Some(100)])) as Box<dyn Column>),
+            ("b", Box::new(Float64Column::from(vec![0.5, 1.0, 2.0, f64::NAN]))),
+            ("s", Box::new(StringColumn::from(vec!["x", "y", "x", "z"]))),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        let df = frame();
+        let res = (col("a") + lit(100)).evaluate(&df).unwrap();
+        assert_eq!(
+            res.as_any().downcast_ref::<Int64Column>().unwrap(),
+            &Int64Column::from(vec![Some(101), Some(102), None, Some(200)])
+        );
+        let res = (col("a") * col("b") / lit(0)).evaluate(&df).unwrap();
+        assert_eq!(res.dtype(), Dtype::Float64);
+        assert_eq!(res.get_value(0), Value::Float(f64::INFINITY));
+        assert_eq!((col("a") % lit(0)).evaluate(&df).unwrap().get_value(0), Value::Null);
+        assert!((col("a") + col("s")).evaluate(&df).is_err());
+        assert!(col("c").evaluate(&df).is_err());
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        let df = frame();
+        let res = (col("b").gt(lit(0.75)) & !col("s").equal(lit("z"))).evaluate(&df).unwrap();
+        assert_eq!(
+            res.as_any().downcast_ref::<BooleanColumn>().unwrap(),
+            &BooleanColumn::from(vec![false, true, true, false])
+        );
+        let res = col("a").lt_eq(lit(2)).evaluate(&df).unwrap();
+        assert_eq!(
+            res.as_any().downcast_ref::<BooleanColumn>().unwrap(),
+            &BooleanColumn::from(vec![Some(true), Some(true), None, Some(false)])
+        );
+        assert_eq!(selected_rows(&*res).unwrap(), vec![Some(0), Some(1)]);
+        assert!((col("a") & col("b")).evaluate(&df).is_err());
+        assert!(col("s").lt(lit(1)).evaluate(&df).is_err());
+
+        let nulls = col("a").is_null().evaluate(&df).unwrap();
+        assert_eq!(nulls.get_value(2), Value::Bool(true));
+    }
+
+    #[test]
+    fn names_and_aggs() {
+        let e = (col("a") + lit(1)).alias("c");
+        assert_eq!(e.output_name(), "c");
+        assert_eq!(e.to_string(), r#"(col("a") + lit(1)).alias("c")"#);
+        assert_eq!((lit(1) + col("a")).output_name(), "literal");
+        assert_eq!(col("a").sum().output_name(), "a");
+
+        let df = frame();
+        let sum = col("a").sum().evaluate(&df).unwrap();
+        assert_eq!(sum.len(), 1);
+        assert_eq!(sum.get_value(0), Value::Int(103));
+        assert_eq!(lit("k").evaluate(&df).unwrap().len(), 4);
+        let cast = col("a").cast(Dtype::Float32).evaluate(&df).unwrap();
+        assert_eq!(cast.dtype(), Dtype::Float32);
+
+        let folded = (col("a") + (lit(1) + lit(2)) * lit(3)).fold_constants();
+        assert_eq!(folded, col("a") + lit(9));
+        assert_eq!(lit(1).cast(Dtype::Int8).fold_constants(), lit(1).cast(Dtype::Int8));
+        assert_eq!((lit(1) / lit(0)).is_null().fold_constants(), lit(true));
+    }
+}
@@ -0,0 +1,245 @@
+//! Lazy evaluation.
+//!
+//! A LazyFrame records operations (`filter`, `select`, `with_column`,
+//! `group_by().agg()`, `sort`, `join`) as a logical plan instead of
+//! running them, with the columns they use described by `Expr`s. Nothing
+//! runs until `collect`, which executes the whole plan at once; errors
+//! like a missing column show up then too.
+//!
+//! A frame becomes lazy with `df.lazy()`, and a CSV reader with
+//! `reader.lazy()`; a reader isn't read until `collect`.
+//!
+//! Having the whole pipeline up front lets `collect` optimize the plan
+//! first (see the optimize module; `explain` shows the result). Filters
+//! and projections are pushed into the scan, so it copies (or, for a
+//! reader, parses) only the rows and columns later steps use. Past the
+//! scan, filters and sorts pass a selection vector on instead of
+//! gathering their rows, so the next step copies only the columns it
+//! reads (see the plan module); other steps run as the eager DataFrame
+//! operation.
+
+mod expr;
+mod optimize;
+mod plan;
+
+pub use column::Operator;
+pub use self::expr::{col, lit, Expr};
+pub(crate) use self::expr::selected_rows;
+
+use std::rc::Rc;
+
+use csv::CsvReader;
+use dataframe::{DataFrame, JoinType};
+use error::Result;
+use self::optimize::optimize;
+use self::plan::{LogicalPlan, ScanSource};
+
+#[derive(Debug, Clone)]
+pub struct LazyFrame {
+    plan: LogicalPlan,
+}
+
+impl LazyFrame {
+    fn with_plan(plan: LogicalPlan) -> Self {
+        LazyFrame {
+            plan,
+        }
+    }
+
+    /// Rows where `predicate` is true (not false or null).
+    pub fn filter(self, predicate: Expr) -> LazyFrame {
+        LazyFrame::with_plan(LogicalPlan::Filter {
+            input: Box::new(self.plan),
+            predicate,
+        })
+    }
+
+    /// A column per expression, named by `Expr::output_name`.
+    pub fn select(self, exprs: Vec<Expr>) -> LazyFrame {
+        LazyFrame::with_plan(LogicalPlan::Select {
+            input: Box::new(self.plan),
+            exprs,
+        })
+    }
+
+    /// Adds the column computed by `expr`, or replaces the column
+    /// with its name.
+    pub fn with_column(self, expr: Expr) -> LazyFrame {
+        self.with_columns(vec![expr])
+    }
+
+    /// Like `with_column`, for several columns computed from the
+    /// same input.
+    pub fn with_columns(self, exprs: Vec<Expr>) -> LazyFrame {
+        LazyFrame::with_plan(LogicalPlan::WithColumns {
+            input: Box::new(self.plan),
+            exprs,
+        })
+    }
+
+    pub fn group_by(self, keys: &[&str]) -> LazyGroupBy {
+        LazyGroupBy {
+            input: self,
+            keys: keys.iter().map(|&key| key.to_owned()).collect(),
+        }
+    }
+
+    /// See `DataFrame::sort`.
+    pub fn sort(self, by: &str, descending: bool) -> LazyFrame {
+        LazyFrame::with_plan(LogicalPlan::Sort {
+            input: Box::new(self.plan),
+            by: by.to_owned(),
+            descending,
+        })
+    }
+
+    /// See `DataFrame::join`.
+    pub fn join(self, other: LazyFrame, on: &[&str], how: JoinType) -> LazyFrame {
+        LazyFrame::with_plan(LogicalPlan::Join {
+            left: Box::new(self.plan),
+            right: Box::new(other.plan),
+            on: on.iter().map(|&key| key.to_owned()).collect(),
+            how,
+        })
+    }
+
+    /// Optimizes the plan, then runs it.
+    pub fn collect(self) -> Result<DataFrame> {
+        optimize(self.plan).execute()
+    }
+
+    /// The optimized plan, one step per line with its inputs indented
+    /// under it.
+    pub fn explain(&self) -> String {
+        optimize(self.plan.clone()).to_string()
+    }
+}
+
+impl From<DataFrame> for LazyFrame {
+    fn from(df: DataFrame) -> Self {
+        LazyFrame::with_plan(LogicalPlan::Scan {
+            source: ScanSource::Frame(Rc::new(df)),
+            projection: None,
+            predicate: None,
+        })
+    }
+}
+
+impl From<CsvReader> for LazyFrame {
+    fn from(reader: CsvReader) -> Self {
+        LazyFrame::with_plan(LogicalPlan::Scan {
+            source: ScanSource::Csv(Rc::new(reader)),
+            projection: None,
+            predicate: None,
+        })
+    }
+}
+
+pub struct LazyGroupBy {
+    input: LazyFrame,
+    keys: Vec<String>,
+}
+
+impl LazyGroupBy {
+    /// One row per group: the keys, then a column per aggregation
+    /// (like `col("a").sum()`), named by `Expr::output_name`.
+    pub fn agg(self, aggs: Vec<Expr>) -> LazyFrame {
+        LazyFrame::with_plan(LogicalPlan::Aggregate {
+            input: Box::new(self.input.plan),
+            keys: self.keys,
+            aggs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Column, Float64Column, Int8Column, Int64Column, StringColumn};
+    use dataframe::JoinType;
+    use error::Error;
+
+    fn frame() -> DataFrame {
+        DataFrame::from_columns(vec![
+            ("key", Box::new(StringColumn::from(vec!["x", "y", "x", "y", "x"])) as Box<dyn Column>),
+            ("a", Box::new(Int8Column::from(vec![Some(1), Some(2), Some(3), None, Some(5)]))),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn pipeline() {
+        let res = frame().lazy()
+            .filter(col("a").gt(lit(1)))
+            .with_column((col("a") * lit(10)).alias("b"))
+            .sort("b", true)
+            .collect()
+            .unwrap();
+        assert_eq!(res.column_names(), &["key", "a", "b"]);
+        assert_eq!(
+            res.column_as::<Int64Column>("b").unwrap(),
+            &Int64Column::from(vec![50, 30, 20])
+        );
+
+        let res = frame().lazy()
+            .select(vec![col("a"), col("a").sum().alias("total"), lit(1.5)])
+            .collect()
+            .unwrap();
+        assert_eq!(res.column_names(), &["a", "total", "literal"]);
+        assert_eq!(res.column_as::<Int64Column>("total").unwrap().get_value(4), ::column::Value::Int(11));
+        assert_eq!(
+            res.column_as::<Float64Column>("literal").unwrap(),
+            &Float64Column::from(vec![1.5; 5])
+        );
+    }
+
+    #[test]
+    fn selections() {
+        // the filters stay above with_column, so they pass selections up
+        let lazy = frame().lazy()
+            .with_column((col("a") * lit(10)).alias("b"))
+            .filter(col("b").gt(lit(10)))
+            .sort("a", true)
+            .filter(col("key").equal(lit("x")));
+        let res = lazy.clone().select(vec![col("b"), lit(1)]).collect().unwrap();
+        assert_eq!(res.column_as::<Int64Column>("b").unwrap(), &Int64Column::from(vec![50, 30]));
+        assert_eq!(res.column_as::<Int64Column>("literal").unwrap(), &Int64Column::from(vec![1, 1]));
+
+        let res = lazy.clone().group_by(&["key"]).agg(vec![col("b").sum()]).collect().unwrap();
+        assert_eq!(res.column_as::<Int64Column>("b").unwrap(), &Int64Column::from(vec![80]));
+
+        let res = lazy.collect().unwrap();
+        assert_eq!(res.column_names(), &["key", "a", "b"]);
+        assert_eq!(res.column_as::<Int8Column>("a").unwrap(), &Int8Column::from(vec![5, 3]));
+    }
+
+    #[test]
+    fn group_by_and_join() {
+        let totals = frame().lazy()
+            .group_by(&["key"])
+            .agg(vec![col("a").sum(), (col("a") * lit(2)).max().alias("max2")]);
+        let res = frame().lazy()
+            .join(totals, &["key"], JoinType::Inner)
+            .collect()
+            .unwrap();
+        assert_eq!(res.column_names(), &["key", "a", "a_right", "max2"]);
+        assert_eq!(
+            res.column_as::<Int64Column>("a_right").unwrap(),
+            &Int64Column::from(vec![9, 2, 9, 2, 9])
+        );
+        assert_eq!(
+            res.column_as::<Int64Column>("max2").unwrap(),
+            &Int64Column::from(vec![10, 4, 10, 4, 10])
+        );
+    }
+
+    #[test]
+    fn errors_on_collect() {
+        let lazy = frame().lazy().filter(col("missing").gt(lit(1)));
+        match lazy.collect() {
+            Err(Error::ColumnNotFound(ref name)) if name == "missing" => (),
+            res => panic!("{:?}", res),
+        }
+        assert!(frame().lazy().group_by(&["key"]).agg(vec![col("a")]).collect().is_err());
+        assert!(frame().lazy().filter(col("a")).collect().is_err());
+    }
+}
@@ -0,0 +1,537 @@
+//! Rewrites of logical plans which give the same result, faster.
+//!
+//! The passes, in order:
+//!
+//! - constant folding: the parts of expressions which don't read any
+//!   columns are evaluated once, and filters which are always true go.
+//! - merging: a select or with_columns step over another one becomes a
+//!   single step, with the inner expressions substituted into the outer.
+//! - predicate pushdown: filters move below the steps which don't
+//!   affect them, down into the scan if they can, so every later step
+//!   sees fewer rows.
+//! - projection pushdown: scans only read the columns that something
+//!   uses, and columns nothing uses aren't computed.
+//!
+//! Filters which aggregate (`col("a").gt(col("a").mean())`) depend on
+//! which rows they see, so they stay where they are.
+
+use std::collections::{HashMap, HashSet};
+
+use column::{Operator, Value};
+use dataframe::JoinType;
+use super::expr::Expr;
+use super::plan::LogicalPlan;
+
+pub(crate) fn optimize(plan: LogicalPlan) -> LogicalPlan {
+    let plan = fold_constants(plan);
+    let plan = merge_projections(plan);
+    let plan = push_predicates(plan, Vec::new());
+    push_projections(plan, None)
+}
+
+/// Applies `f` to each input of `plan`.
+fn map_inputs<F>(plan: LogicalPlan, mut f: F) -> LogicalPlan
+    where F: FnMut(LogicalPlan) -> LogicalPlan
+{
+    let mut boxed = |plan: Box<LogicalPlan>| Box::new(f(*plan));
+    match plan {
+        LogicalPlan::Scan { .. } => plan,
+        LogicalPlan::Filter { input, predicate } => LogicalPlan::Filter {
+            input: boxed(input),
+            predicate,
+        },
+        LogicalPlan::Select { input, exprs } => LogicalPlan::Select {
+            input: boxed(input),
+            exprs,
+        },
+        LogicalPlan::WithColumns { input, exprs } => LogicalPlan::WithColumns {
+            input: boxed(input),
+            exprs,
+        },
+        LogicalPlan::Aggregate { input, keys, aggs } => LogicalPlan::Aggregate {
+            input: boxed(input),
+            keys,
+            aggs,
+        },
+        LogicalPlan::Sort { input, by, descending } => LogicalPlan::Sort {
+            input: boxed(input),
+            by,
+            descending,
+        },
+        LogicalPlan::Join { left, right, on, how } => LogicalPlan::Join {
+            left: boxed(left),
+            right: boxed(right),
+            on,
+            how,
+        },
+    }
+}
+
+fn fold_constants(plan: LogicalPlan) -> LogicalPlan {
+    let fold = |exprs: Vec<Expr>| exprs.into_iter().map(Expr::fold_constants).collect();
+    match map_inputs(plan, fold_constants) {
+        LogicalPlan::Scan { source, projection, predicate } => LogicalPlan::Scan {
+            source,
+            projection,
+            predicate: predicate.map(Expr::fold_constants),
+        },
+        LogicalPlan::Filter { input, predicate } => match predicate.fold_constants() {
+            Expr::Literal(Value::Bool(true)) => *input,
+            predicate => LogicalPlan::Filter {
+                input,
+                predicate,
+            },
+        },
+        LogicalPlan::Select { input, exprs } => LogicalPlan::Select {
+            input,
+            exprs: fold(exprs),
+        },
+        LogicalPlan::WithColumns { input, exprs } => LogicalPlan::WithColumns {
+            input,
+            exprs: fold(exprs),
+        },
+        LogicalPlan::Aggregate { input, keys, aggs } => LogicalPlan::Aggregate {
+            input,
+            keys,
+            aggs: fold(aggs),
+        },
+        plan => plan,
+    }
+}
+
+fn merge_projections(plan: LogicalPlan) -> LogicalPlan {
+    match map_inputs(plan, merge_projections) {
+        LogicalPlan::WithColumns { input, exprs: outer } => match *input {
+            // independent steps can be evaluated together
+            LogicalPlan::WithColumns { input, exprs: mut inner }
+                if outer.iter().all(|e| e.columns().iter().all(|c| !outputs(&inner).contains(c))) =>
+            {
+                inner.extend(outer);
+                LogicalPlan::WithColumns {
+                    input,
+                    exprs: inner,
+                }
+            },
+            input => LogicalPlan::WithColumns {
+                input: Box::new(input),
+                exprs: outer,
+            },
+        },
+        LogicalPlan::Select { input, exprs: outer } => match *input {
+            LogicalPlan::Select { input, exprs: inner } if can_inline(&outer, &inner, true) => LogicalPlan::Select {
+                input,
+                exprs: inline(outer, inner),
+            },
+            LogicalPlan::WithColumns { input, exprs: inner } if can_inline(&outer, &inner, false) => LogicalPlan::Select {
+                input,
+                exprs: inline(outer, inner),
+            },
+            input => LogicalPlan::Select {
+                input: Box::new(input),
+                exprs: outer,
+            },
+        },
+        plan => plan,
+    }
+}
+
+fn outputs(exprs: &[Expr]) -> HashSet<String> {
+    exprs.iter().map(Expr::output_name).collect()
+}
+
+/// Whether the `inner` expressions can be substituted into `outer`.
+/// If `exclusive`, `inner` gives the only columns `outer` can read.
+fn can_inline(outer: &[Expr], inner: &[Expr], exclusive: bool) -> bool {
+    let names = outputs(inner);
+    let mut refs = Vec::new();
+    for e in outer {
+        e.column_refs(&mut refs);
+    }
+    // an aggregation can't be evaluated inside another expression, and
+    // anything more than a column or literal shouldn't be computed twice
+    names.len() == inner.len() &&
+        inner.iter().all(|e| {
+            let uses = refs.iter().filter(|&name| *name == e.output_name()).count();
+            !e.has_agg() && (uses <= 1 || matches!(*e, Expr::Column(_) | Expr::Literal(_)))
+        }) &&
+        (!exclusive || refs.iter().all(|name| names.contains(name)))
+}
+
+fn inline(outer: Vec<Expr>, inner: Vec<Expr>) -> Vec<Expr> {
+    let inner: HashMap<String, Expr> = inner.into_iter().map(|e| (e.output_name(), e)).collect();
+    outer.into_iter().map(|e| e.substitute(&inner)).collect()
+}
+
+/// Moves `predicates` (filters from above `plan`) and the filters in
+/// `plan` as far down as they go.
+fn push_predicates(plan: LogicalPlan, mut predicates: Vec<Expr>) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Filter { input, predicate } => {
+            if predicate.has_agg() {
+                let input = push_predicates(*input, Vec::new());
+                return filtered(LogicalPlan::Filter { input: Box::new(input), predicate }, predicates);
+            }
+            let mut conjuncts = Vec::new();
+            split_conjuncts(predicate, &mut conjuncts);
+            // this filter's predicates come before those above
+            conjuncts.extend(predicates);
+            push_predicates(*input, conjuncts)
+        },
+        LogicalPlan::Scan { source, projection, predicate } => {
+            let mut conjuncts = Vec::new();
+            if let Some(predicate) = predicate {
+                split_conjuncts(predicate, &mut conjuncts);
+            }
+            conjuncts.extend(predicates);
+            LogicalPlan::Scan {
+                source,
+                projection,
+                predicate: conjunction(conjuncts),
+            }
+        },
+        LogicalPlan::Sort { input, by, descending } => LogicalPlan::Sort {
+            input: Box::new(push_predicates(*input, predicates)),
+            by,
+            descending,
+        },
+        LogicalPlan::Select { input, exprs } => {
+            // only through columns which are passed through as they are
+            let (below, above) = predicates.into_iter().partition(|p: &Expr| {
+                !exprs.iter().any(Expr::has_agg) &&
+                    p.columns().into_iter().all(|c| exprs.contains(&Expr::Column(c)))
+            });
+            let plan = LogicalPlan::Select {
+                input: Box::new(push_predicates(*input, below)),
+                exprs,
+            };
+            filtered(plan, above)
+        },
+        LogicalPlan::WithColumns { input, exprs } => {
+            let names = outputs(&exprs);
+            let (below, above) = predicates.into_iter().partition(|p: &Expr| {
+                !exprs.iter().any(Expr::has_agg) && p.columns().is_disjoint(&names)
+            });
+            let plan = LogicalPlan::WithColumns {
+                input: Box::new(push_predicates(*input, below)),
+                exprs,
+            };
+            filtered(plan, above)
+        },
+        LogicalPlan::Aggregate { input, keys, aggs } => {
+            // filtering on keys drops whole groups
+            let (below, above) = predicates.into_iter().partition(|p: &Expr| {
+                p.columns().iter().all(|c| keys.contains(c))
+            });
+            let plan = LogicalPlan::Aggregate {
+                input: Box::new(push_predicates(*input, below)),
+                keys,
+                aggs,
+            };
+            filtered(plan, above)
+        },
+        LogicalPlan::Join { left, right, on, how } => {
+            let left_schema = left.schema();
+            let right_only: Vec<String> = right.schema()
+                .into_iter()
+                .filter(|name| !on.contains(name) && !left_schema.contains(name))
+                .collect();
+            let mut to_left = Vec::new();
+            let mut to_right = Vec::new();
+            let mut above = Vec::new();
+            for p in predicates.drain(..) {
+                let columns = p.columns();
+                if columns.iter().all(|c| left_schema.contains(c)) {
+                    to_left.push(p);
+                } else if how == JoinType::Inner && columns.iter().all(|c| right_only.contains(c)) {
+                    // a left join keeps unmatched rows, with nulls on
+                    // the right, so the right side can't be filtered
+                    to_right.push(p);
+                } else {
+                    above.push(p);
+                }
+            }
+            let plan = LogicalPlan::Join {
+                left: Box::new(push_predicates(*left, to_left)),
+                right: Box::new(push_predicates(*right, to_right)),
+                on,
+                how,
+            };
+            filtered(plan, above)
+        },
+    }
+}
+
+fn split_conjuncts(predicate: Expr, conjuncts: &mut Vec<Expr>) {
+    match predicate {
+        Expr::Binary { left, op: Operator::And, right } => {
+            split_conjuncts(*left, conjuncts);
+            split_conjuncts(*right, conjuncts);
+        },
+        Expr::Literal(Value::Bool(true)) => (),
+        predicate => conjuncts.push(predicate),
+    }
+}
+
+fn conjunction(predicates: Vec<Expr>) -> Option<Expr> {
+    predicates.into_iter().fold(None, |acc, p| match acc {
+        Some(acc) => Some(acc & p),
+        None => Some(p),
+    })
+}
+
+fn filtered(plan: LogicalPlan, predicates: Vec<Expr>) -> LogicalPlan {
+    match conjunction(predicates) {
+        Some(predicate) => LogicalPlan::Filter {
+            input: Box::new(plan),
+            predicate,
+        },
+        None => plan,
+    }
+}
+
+/// Narrows scans to the columns in `required` (or that the steps
+/// between need), and drops computed columns which aren't required.
+/// `None` requires every column.
+fn push_projections(plan: LogicalPlan, required: Option<HashSet<String>>) -> LogicalPlan {
+    let with = |required: Option<HashSet<String>>, columns: HashSet<String>| {
+        required.map(|mut required| {
+            required.extend(columns);
+            required
+        })
+    };
+    match plan {
+        LogicalPlan::Scan { source, projection, predicate } => {
+            let projection = match required {
+                Some(required) => {
+                    let available = projection.unwrap_or_else(|| source.column_names());
+                    let mut names: Vec<String> = available.iter()
+                        .filter(|&name| required.contains(name))
+                        .cloned()
+                        .collect();
+                    // the frame's length comes from its columns
+                    if names.is_empty() {
+                        names.extend(available.first().cloned());
+                    }
+                    Some(names)
+                },
+                None => projection,
+            };
+            LogicalPlan::Scan {
+                source,
+                projection,
+                predicate,
+            }
+        },
+        LogicalPlan::Filter { input, predicate } => LogicalPlan::Filter {
+            input: Box::new(push_projections(*input, with(required, predicate.columns()))),
+            predicate,
+        },
+        LogicalPlan::Sort { input, by, descending } => LogicalPlan::Sort {
+            input: Box::new(push_projections(*input, with(required, Some(by.clone()).into_iter().collect()))),
+            by,
+            descending,
+        },
+        LogicalPlan::Select { input, exprs } => {
+            let columns = exprs.iter().flat_map(Expr::columns).collect();
+            LogicalPlan::Select {
+                input: Box::new(push_projections(*input, Some(columns))),
+                exprs,
+            }
+        },
+        LogicalPlan::WithColumns { input, exprs } => match required {
+            None => LogicalPlan::WithColumns {
+                input: Box::new(push_projections(*input, None)),
+                exprs,
+            },
+            Some(required) => {
+                let exprs: Vec<Expr> = exprs.into_iter()
+                    .filter(|e| required.contains(&e.output_name()))
+                    .collect();
+                let names = outputs(&exprs);
+                let columns = exprs.iter().flat_map(Expr::columns).collect();
+                let required = required.into_iter().filter(|name| !names.contains(name)).collect();
+                let input = push_projections(*input, with(Some(required), columns));
+                if exprs.is_empty() {
+                    input
+                } else {
+                    LogicalPlan::WithColumns {
+                        input: Box::new(input),
+                        exprs,
+                    }
+                }
+            },
+        },
+        LogicalPlan::Aggregate { input, keys, aggs } => {
+            let aggs: Vec<Expr> = match required {
+                Some(required) => aggs.into_iter().filter(|e| required.contains(&e.output_name())).collect(),
+                None => aggs,
+            };
+            let columns = keys.iter().cloned().chain(aggs.iter().flat_map(Expr::columns)).collect();
+            LogicalPlan::Aggregate {
+                input: Box::new(push_projections(*input, Some(columns))),
+                keys,
+                aggs,
+            }
+        },
+        LogicalPlan::Join { left, right, on, how } => {
+            let (left_required, right_required) = match required {
+                Some(required) => {
+                    let left_schema = left.schema();
+                    // right columns keep the names they'd have had
+                    let right_kept: HashSet<String> = right.schema()
+                        .into_iter()
+                        .filter(|name| !on.contains(name))
+                        .filter(|name| {
+                            let output = if left_schema.contains(name) { format!("{}_right", name) } else { name.clone() };
+                            required.contains(&output)
+                        })
+                        .collect();
+                    let left_required = left_schema.into_iter()
+                        .filter(|name| required.contains(name) || on.contains(name) || right_kept.contains(name))
+                        .collect();
+                    let right_required = right_kept.into_iter().chain(on.iter().cloned()).collect();
+                    (Some(left_required), Some(right_required))
+                },
+                None => (None, None),
+            };
+            LogicalPlan::Join {
+                left: Box::new(push_projections(*left, left_required)),
+                right: Box::new(push_projections(*right, right_required)),
+                on,
+                how,
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use column::{Column, Int8Column, StringColumn};
+    use csv::CsvReader;
+    use dataframe::DataFrame;
+    use lazy::{col, lit, LazyFrame};
+
+    fn frame() -> DataFrame {
+        DataFrame::from_columns(vec![
+            ("key", Box::new(StringColumn::from(vec!["x", "y", "x", "y", "x"])) as Box<dyn Column>),
+            ("a", Box::new(Int8Column::from(vec![Some(1), Some(2), Some(3), None, Some(5)]))),
+            ("b", Box::new(Int8Column::from(vec![10, 20, 30, 40, 50]))),
+            ("unused", Box::new(Int8Column::from(vec![0, 0, 0, 0, 0]))),
+        ]).unwrap()
+    }
+
+    /// Same result with and without optimizing.
+    fn check(lazy: LazyFrame) {
+        let expected = format!("{:?}", lazy.plan.clone().execute());
+        assert_eq!(format!("{:?}", lazy.collect()), expected);
+    }
+
+    #[test]
+    fn pushdown() {
+        let lazy = frame().lazy()
+            .with_column((col("a") * (lit(2) + lit(3))).alias("c"))
+            .filter(col("b").gt(lit(15)) & lit(true))
+            .filter(col("c").lt(lit(20)))
+            .select(vec![col("key"), col("c")]);
+        assert_eq!(
+            lazy.explain(),
+            concat!(
+                "SELECT [col(\"key\"), col(\"c\")]\n",
+                "  FILTER (col(\"c\") < lit(20))\n",
+                "    WITH COLUMNS [(col(\"a\") * lit(5)).alias(\"c\")]\n",
+                "      SCAN 5 rows [key, a] FILTER (col(\"b\") > lit(15))\n",
+            )
+        );
+        check(lazy);
+    }
+
+    #[test]
+    fn csv_scan() {
+        let reader = CsvReader::from_reader("key,a,b\nx,1,10\ny,2,20\nx,3,30\n".as_bytes()).unwrap();
+        let lazy = reader.lazy()
+            .filter(col("b").gt(lit(15)))
+            .select(vec![col("key")]);
+        assert_eq!(
+            lazy.explain(),
+            concat!(
+                "SELECT [col(\"key\")]\n",
+                "  SCAN CSV [key] FILTER (col(\"b\") > lit(15))\n",
+            )
+        );
+        check(lazy);
+    }
+
+    #[test]
+    fn joins_and_aggregates() {
+        let totals = frame().lazy()
+            .group_by(&["key"])
+            .agg(vec![col("b").sum().alias("total"), col("a").max()]);
+        let lazy = frame().lazy()
+            .join(totals, &["key"], JoinType::Inner)
+            .filter(col("key").equal(lit("x")) & col("total").gt(lit(0)) & col("a_right").gt(col("b")))
+            .select(vec![col("b"), col("total")]);
+        assert_eq!(
+            lazy.explain(),
+            concat!(
+                "SELECT [col(\"b\"), col(\"total\")]\n",
+                "  FILTER (col(\"a_right\") > col(\"b\"))\n",
+                "    Inner JOIN ON [key]\n",
+                "      SCAN 5 rows [key, a, b] FILTER (col(\"key\") == lit(\"x\"))\n",
+                "      FILTER (col(\"total\") > lit(0))\n",
+                "        AGGREGATE [col(\"b\").sum().alias(\"total\"), col(\"a\").max()] BY [key]\n",
+                "          SCAN 5 rows [key, a, b]\n",
+            )
+        );
+        check(lazy);
+
+        // a left join keeps right predicates above it
+        let left = frame().lazy()
+            .join(frame().lazy().filter(col("a").lt(lit(3))), &["a"], JoinType::Left)
+            .filter(col("key_right").is_null());
+        assert!(left.explain().starts_with("FILTER col(\"key_right\").is_null()\n  Left JOIN"));
+        check(left);
+    }
+
+    #[test]
+    fn aggregating_filters_stay() {
+        let lazy = frame().lazy()
+            .filter(col("a").gt(col("a").mean()))
+            .filter(col("b").lt(lit(50)));
+        assert!(lazy.explain().starts_with("FILTER (col(\"b\") < lit(50))\n  FILTER"));
+        check(lazy);
+
+        let lazy = frame().lazy()
+            .with_column(col("b").sum().alias("total"))
+            .filter(col("a").gt(lit(1)))
+            .select(vec![col("total")]);
+        check(lazy);
+    }
+
+    #[test]
+    fn merging() {
+        let lazy = frame().lazy()
+            .with_column((col("a") + lit(1)).alias("c"))
+            .with_column((col("b") * lit(2)).alias("d"))
+            .select(vec![col("c"), col("d").alias("e")])
+            .select(vec![col("e") - col("c")]);
+        assert_eq!(
+            lazy.explain(),
+            concat!(
+                "SELECT [((col(\"b\") * lit(2)).alias(\"d\").alias(\"e\") - (col(\"a\") + lit(1)).alias(\"c\"))]\n",
+                "  SCAN 5 rows [a, b]\n",
+            )
+        );
+        check(lazy);
+
+        // c is used twice, so it's computed once
+        let lazy = frame().lazy()
+            .with_column((col("a") + lit(1)).alias("c"))
+            .select(vec![col("c") * col("c")]);
+        assert!(lazy.explain().starts_with("SELECT [(col(\"c\") * col(\"c\"))]\n  WITH COLUMNS"));
+        check(lazy);
+
+        // reading a column the inner select dropped is still an error
+        assert!(frame().lazy().select(vec![col("a")]).select(vec![col("b")]).collect().is_err());
+    }
+}
@@ -0,0 +1,318 @@
+//! Logical plans: the tree of operations a LazyFrame builds up.
+//!
+//! Executing a plan runs its inputs first, then the operation on their
+//! result, using the eager DataFrame operations. A filter or sort
+//! doesn't gather its rows itself: it passes the frame up with a
+//! selection vector of the rows to keep, and the next step takes just
+//! the columns it reads. Filters and sorts in a row compose their
+//! selections, and steps that need every column (`with_column`, joins)
+//! gather them all first.
+//!
+//! A scan reads only its projected columns, and applies its predicate
+//! before copying them, so the optimizer can push work down into it.
+//! Scanning a CsvReader only parses those columns, and skips rows the
+//! predicate rejects before parsing the rest of their fields.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use csv::CsvReader;
+use dataframe::{aggregate, Agg, DataFrame, JoinType};
+use error::{Error, Result};
+use super::expr::{broadcast, evaluate_all, selected_rows, Expr};
+
+/// Where a scan reads from.
+#[derive(Debug, Clone)]
+pub(crate) enum ScanSource {
+    Frame(Rc<DataFrame>),
+    Csv(Rc<CsvReader>),
+}
+
+impl ScanSource {
+    pub(crate) fn column_names(&self) -> Vec<String> {
+        match *self {
+            ScanSource::Frame(ref df) => df.column_names().to_vec(),
+            ScanSource::Csv(ref reader) => reader.column_names(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum LogicalPlan {
+    Scan {
+        source: ScanSource,
+        /// Columns to read, in frame order; all if `None`.
+        projection: Option<Vec<String>>,
+        /// Rows to read; all if `None`.
+        predicate: Option<Expr>,
+    },
+    Filter {
+        input: Box<LogicalPlan>,
+        predicate: Expr,
+    },
+    Select {
+        input: Box<LogicalPlan>,
+        exprs: Vec<Expr>,
+    },
+    WithColumns {
+        input: Box<LogicalPlan>,
+        exprs: Vec<Expr>,
+    },
+    Aggregate {
+        input: Box<LogicalPlan>,
+        keys: Vec<String>,
+        aggs: Vec<Expr>,
+    },
+    Sort {
+        input: Box<LogicalPlan>,
+        by: String,
+        descending: bool,
+    },
+    Join {
+        left: Box<LogicalPlan>,
+        right: Box<LogicalPlan>,
+        on: Vec<String>,
+        how: JoinType,
+    },
+}
+
+/// A frame and the rows of it a step kept, in order; `None` is all of
+/// them.
+struct Selection {
+    df: DataFrame,
+    rows: Option<Vec<Option<usize>>>,
+}
+
+impl Selection {
+    fn all(df: DataFrame) -> Selection {
+        Selection { df, rows: None }
+    }
+
+    /// The selected rows of the columns in `names`, so a step reading
+    /// only a few columns doesn't gather the rest. Names not in the frame
+    /// are left for evaluation to report.
+    fn narrow<'a>(&'a self, names: &HashSet<String>) -> Result<Cow<'a, DataFrame>> {
+        let rows = match self.rows {
+            Some(ref rows) => rows,
+            None => return Ok(Cow::Borrowed(&self.df)),
+        };
+        let mut kept: Vec<&str> = self.df.column_names().iter()
+            .filter(|name| names.contains(*name))
+            .map(|name| name.as_str())
+            .collect();
+        // keep a column for the row count, e.g. for a literal
+        if kept.is_empty() {
+            kept.extend(self.df.column_names().first().map(|name| name.as_str()));
+        }
+        Ok(Cow::Owned(self.df.select(&kept)?.take(rows)))
+    }
+
+    /// Narrows the selection to `rows`, positions among the rows
+    /// already selected.
+    fn compose(self, rows: Vec<Option<usize>>) -> Selection {
+        let rows = match self.rows {
+            Some(selected) => rows.into_iter().map(|i| i.and_then(|i| selected[i])).collect(),
+            None => rows,
+        };
+        Selection { df: self.df, rows: Some(rows) }
+    }
+
+    fn materialize(self) -> DataFrame {
+        match self.rows {
+            Some(rows) => self.df.take(&rows),
+            None => self.df,
+        }
+    }
+}
+
+impl LogicalPlan {
+    pub(crate) fn execute(self) -> Result<DataFrame> {
+        self.run().map(Selection::materialize)
+    }
+
+    fn run(self) -> Result<Selection> {
+        let df = match self {
+            LogicalPlan::Scan { source: ScanSource::Csv(reader), projection, predicate } => {
+                reader.read(projection.as_ref().map(|names| &names[..]), predicate.as_ref())?
+            },
+            LogicalPlan::Scan { source: ScanSource::Frame(df), projection, predicate } => {
+                if projection.is_none() && predicate.is_none() {
+                    return Ok(Selection::all(Rc::try_unwrap(df).unwrap_or_else(|df| (*df).clone())));
+                }
+                let rows = match predicate {
+                    Some(predicate) => Some(selected_rows(&*predicate.evaluate(&df)?)?),
+                    None => None,
+                };
+                let names = projection.unwrap_or_else(|| df.column_names().to_vec());
+                let mut res = DataFrame::new();
+                for name in &names {
+                    let column = df.column(name)?;
+                    let column = match rows {
+                        Some(ref rows) => column.take(rows),
+                        None => column.clone_box(),
+                    };
+                    res.add_named_column(name, column)?;
+                }
+                res
+            },
+            LogicalPlan::Filter { input, predicate } => {
+                let selection = input.run()?;
+                let rows = {
+                    let df = selection.narrow(&predicate.columns())?;
+                    selected_rows(&*predicate.evaluate(&df)?)?
+                };
+                return Ok(selection.compose(rows));
+            },
+            LogicalPlan::Select { input, exprs } => {
+                let selection = input.run()?;
+                let names = exprs.iter().flat_map(Expr::columns).collect();
+                let df = selection.narrow(&names)?;
+                let mut res = DataFrame::new();
+                for (name, column) in evaluate_all(&exprs, &df)? {
+                    res.add_named_column(&name, column)?;
+                }
+                res
+            },
+            LogicalPlan::WithColumns { input, exprs } => {
+                let mut df = input.execute()?;
+                // evaluated against the input, not each other
+                let columns = exprs.iter()
+                    .map(|e| Ok((e.output_name(), broadcast(e.evaluate(&df)?, df.len())?)))
+                    .collect::<Result<Vec<_>>>()?;
+                for (name, column) in columns {
+                    df.set_column(&name, column)?;
+                }
+                df
+            },
+            LogicalPlan::Aggregate { input, keys, aggs } => {
+                let selection = input.run()?;
+                let names = keys.iter().cloned().chain(aggs.iter().flat_map(Expr::columns)).collect();
+                let df = selection.narrow(&names)?;
+                let keys: Vec<&str> = keys.iter().map(|key| key.as_str()).collect();
+                let group_by = df.group_by(&keys)?;
+                let mut res = group_by.key_frame()?;
+                for e in &aggs {
+                    let (inner, agg) = split_agg(e).ok_or_else(|| Error::TypeMismatch {
+                        expected: "an aggregation".to_owned(),
+                        found: e.to_string(),
+                    })?;
+                    let column = inner.evaluate(&df)?;
+                    res.add_named_column(&e.output_name(), aggregate(&*column, group_by.groups(), agg)?)?;
+                }
+                res
+            },
+            LogicalPlan::Sort { input, by, descending } => {
+                let selection = input.run()?;
+                let by_name = Some(by.clone()).into_iter().collect();
+                let order = selection.narrow(&by_name)?.sort_order(&by, descending)?;
+                return Ok(selection.compose(order.into_iter().map(Some).collect()));
+            },
+            LogicalPlan::Join { left, right, on, how } => {
+                let on: Vec<&str> = on.iter().map(|key| key.as_str()).collect();
+                left.execute()?.join(&right.execute()?, &on, how)?
+            },
+        };
+        Ok(Selection::all(df))
+    }
+}
+
+impl LogicalPlan {
+    /// Names of the columns it gives, as far as can be told without
+    /// running it.
+    pub(crate) fn schema(&self) -> Vec<String> {
+        match *self {
+            LogicalPlan::Scan { ref source, ref projection, .. } => {
+                projection.clone().unwrap_or_else(|| source.column_names())
+            },
+            LogicalPlan::Filter { ref input, .. } | LogicalPlan::Sort { ref input, .. } => input.schema(),
+            LogicalPlan::Select { ref exprs, .. } => exprs.iter().map(Expr::output_name).collect(),
+            LogicalPlan::WithColumns { ref input, ref exprs } => {
+                let mut schema = input.schema();
+                for name in exprs.iter().map(Expr::output_name) {
+                    if !schema.contains(&name) {
+                        schema.push(name);
+                    }
+                }
+                schema
+            },
+            LogicalPlan::Aggregate { ref keys, ref aggs, .. } => {
+                keys.iter().cloned().chain(aggs.iter().map(Expr::output_name)).collect()
+            },
+            LogicalPlan::Join { ref left, ref right, ref on, .. } => {
+                let mut schema = left.schema();
+                for name in right.schema() {
+                    if on.contains(&name) {
+                        continue;
+                    }
+                    let name = if schema.contains(&name) { format!("{}_right", name) } else { name };
+                    schema.push(name);
+                }
+                schema
+            },
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        let list = |exprs: &[Expr]| exprs.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ");
+        write!(f, "{:width$}", "", width = 2 * depth)?;
+        match *self {
+            LogicalPlan::Scan { ref source, ref projection, ref predicate } => {
+                match *source {
+                    ScanSource::Frame(ref df) => write!(f, "SCAN {} rows", df.len())?,
+                    ScanSource::Csv(_) => write!(f, "SCAN CSV")?,
+                }
+                match *projection {
+                    Some(ref names) => write!(f, " [{}]", names.join(", "))?,
+                    None => write!(f, " [*]")?,
+                }
+                if let Some(ref predicate) = *predicate {
+                    write!(f, " FILTER {}", predicate)?;
+                }
+                writeln!(f)
+            },
+            LogicalPlan::Filter { ref input, ref predicate } => {
+                writeln!(f, "FILTER {}", predicate)?;
+                input.fmt_indented(f, depth + 1)
+            },
+            LogicalPlan::Select { ref input, ref exprs } => {
+                writeln!(f, "SELECT [{}]", list(exprs))?;
+                input.fmt_indented(f, depth + 1)
+            },
+            LogicalPlan::WithColumns { ref input, ref exprs } => {
+                writeln!(f, "WITH COLUMNS [{}]", list(exprs))?;
+                input.fmt_indented(f, depth + 1)
+            },
+            LogicalPlan::Aggregate { ref input, ref keys, ref aggs } => {
+                writeln!(f, "AGGREGATE [{}] BY [{}]", list(aggs), keys.join(", "))?;
+                input.fmt_indented(f, depth + 1)
+            },
+            LogicalPlan::Sort { ref input, ref by, descending } => {
+                writeln!(f, "SORT {}{}", by, if descending { " DESC" } else { "" })?;
+                input.fmt_indented(f, depth + 1)
+            },
+            LogicalPlan::Join { ref left, ref right, ref on, how } => {
+                writeln!(f, "{:?} JOIN ON [{}]", how, on.join(", "))?;
+                left.fmt_indented(f, depth + 1)?;
+                right.fmt_indented(f, depth + 1)
+            },
+        }
+    }
+}
+
+/// One line per step, indented under the step that uses it.
+impl fmt::Display for LogicalPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// The expression an aggregation reduces, under any aliases.
+fn split_agg(e: &Expr) -> Option<(&Expr, Agg)> {
+    match *e {
+        Expr::Agg(ref inner, agg) => Some((inner, agg)),
+        Expr::Alias(ref inner, _) => split_agg(inner),
+        _ => None,
+    }
+}
@@ -8,7 +8,13 @@ extern crate bit_vec;
 extern crate llamas_categorical;
 //extern crate ndarray;
 extern crate rayon;
+extern crate regex;
 
 pub mod column;
+pub mod csv;
 pub mod dataframe;
+pub mod lazy;
+mod error;
+
+pub use error::{Error, Result};
 